@@ -29,7 +29,7 @@ fn main() -> Result<(), SdkmsError> {
 fn sign(client: &SdkmsClient, req: &SignRequest) -> Result<SignResponse, SdkmsError> {
     println!("trying direct call to Sign API first...");
     match client.sign(req) {
-        Err(SdkmsError::Forbidden(ref msg)) if msg == "This operation requires approval" => {
+        Err(SdkmsError::ApprovalRequired(_)) => {
             println!("trying approval request path...");
             sign_with_approval(client, req)
         }
@@ -7,7 +7,7 @@
 use serde::de::Error as DeserializeError;
 use serde::ser::Error as SerializeError;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
-use simple_hyper_client::StatusCode;
+use simple_hyper_client::{Method, StatusCode};
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::ops::{Deref, DerefMut};
@@ -176,15 +176,126 @@ impl fmt::Display for TimeOutOfRange {
 
 impl error::Error for TimeOutOfRange {}
 
+/// Error response returned by SDKMS.
+///
+/// SDKMS usually responds with a plain text message. If the response body is a JSON object, its fields are parsed and
+/// the message is taken from its `message` (or `error`) field.
+#[derive(Clone, Debug)]
+pub struct ApiError(Box<ApiErrorInner>);
+
+#[derive(Clone, Debug)]
+struct ApiErrorInner {
+    status: StatusCode,
+    message: String,
+    code: Option<String>,
+    fields: serde_json::Map<String, serde_json::Value>,
+    method: Option<Method>,
+    path: Option<String>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, body: &str) -> Self {
+        match serde_json::from_str(body) {
+            Ok(value) => Self::from_json(status, value),
+            Err(_) => Self::from_json(status, serde_json::Value::String(body.to_owned())),
+        }
+    }
+
+    pub(crate) fn from_json(status: StatusCode, body: serde_json::Value) -> Self {
+        let (message, code, fields) = match body {
+            serde_json::Value::String(message) => (message, None, serde_json::Map::new()),
+            serde_json::Value::Object(fields) => {
+                let message = ["message", "error"]
+                    .iter()
+                    .filter_map(|field| fields.get(*field)?.as_str())
+                    .next()
+                    .map_or_else(
+                        || serde_json::Value::Object(fields.clone()).to_string(),
+                        str::to_owned,
+                    );
+                let code = fields.get("code").and_then(|code| match *code {
+                    serde_json::Value::String(ref code) => Some(code.clone()),
+                    serde_json::Value::Number(ref code) => Some(code.to_string()),
+                    _ => None,
+                });
+                (message, code, fields)
+            }
+            other => (other.to_string(), None, serde_json::Map::new()),
+        };
+        ApiError(Box::new(ApiErrorInner {
+            status,
+            message,
+            code,
+            fields,
+            method: None,
+            path: None,
+        }))
+    }
+
+    pub(crate) fn with_request(mut self, method: &Method, path: &str) -> Self {
+        self.0.method = Some(method.clone());
+        self.0.path = Some(path.to_owned());
+        self
+    }
+
+    /// HTTP status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.0.status
+    }
+
+    /// Error message sent by the server.
+    pub fn message(&self) -> &str {
+        &self.0.message
+    }
+
+    /// Error code, if the server sent one.
+    pub fn code(&self) -> Option<&str> {
+        self.0.code.as_deref()
+    }
+
+    /// Fields of the JSON error response. Empty if the server responded with a plain text message.
+    pub fn fields(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.0.fields
+    }
+
+    /// HTTP method of the failed request. Not available for errors returned as the result of an approval request.
+    pub fn method(&self) -> Option<&Method> {
+        self.0.method.as_ref()
+    }
+
+    /// Path of the failed request, e.g. `/crypto/v1/keys`.
+    pub fn path(&self) -> Option<&str> {
+        self.0.path.as_deref()
+    }
+
+    fn is_approval_required(&self) -> bool {
+        self.0.status == StatusCode::FORBIDDEN && self.0.message == APPROVAL_REQUIRED_MESSAGE
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0.message)
+    }
+}
+
+pub(crate) const APPROVAL_REQUIRED_MESSAGE: &str = "This operation requires approval";
+
 #[derive(Debug)]
 pub enum Error {
-    Unauthorized(String),
-    Forbidden(String),
-    BadRequest(String),
-    Conflict(String),
-    Locked(String),
-    NotFound(String),
-    StatusCode(String),
+    Unauthorized(ApiError),
+    Forbidden(ApiError),
+    /// The operation is subject to a quorum policy, use the corresponding `request_approval_to_*()` method instead.
+    ApprovalRequired(ApiError),
+    BadRequest(ApiError),
+    Conflict(ApiError),
+    Locked(ApiError),
+    NotFound(ApiError),
+    StatusCode(ApiError),
+    /// Invalid input detected on the client side, the request was not sent to SDKMS.
+    InvalidInput(String),
+    /// SDKMS returned a response the client can not use, e.g. a batch response with a missing item.
+    UnexpectedResponse(String),
     EncoderError(serde_json::error::Error),
     IoError(io::Error),
     NetworkError(simple_hyper_client::Error),
@@ -196,37 +307,114 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         "sdkms-client error"
     }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::EncoderError(ref err) => Some(err),
+            Error::IoError(ref err) => Some(err),
+            Error::NetworkError(ref err) => Some(err),
+            #[cfg(feature = "native-tls")]
+            Error::TlsError(ref err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::NotFound(ref msg) => write!(fmt, "{}", msg),
-            Error::Unauthorized(ref msg) => write!(fmt, "{}", msg),
-            Error::Forbidden(ref msg) => write!(fmt, "{}", msg),
-            Error::BadRequest(ref msg) => write!(fmt, "{}", msg),
-            Error::Conflict(ref msg) => write!(fmt, "{}", msg),
-            Error::Locked(ref msg) => write!(fmt, "{}", msg),
+            Error::NotFound(ref err) => write!(fmt, "{}", err),
+            Error::Unauthorized(ref err) => write!(fmt, "{}", err),
+            Error::Forbidden(ref err) => write!(fmt, "{}", err),
+            Error::ApprovalRequired(ref err) => write!(fmt, "{}", err),
+            Error::BadRequest(ref err) => write!(fmt, "{}", err),
+            Error::Conflict(ref err) => write!(fmt, "{}", err),
+            Error::Locked(ref err) => write!(fmt, "{}", err),
+            Error::InvalidInput(ref msg) => write!(fmt, "{}", msg),
+            Error::UnexpectedResponse(ref msg) => write!(fmt, "unexpected response: {}", msg),
             Error::EncoderError(ref err) => write!(fmt, "{}", err),
             Error::IoError(ref err) => write!(fmt, "{}", err),
             Error::NetworkError(ref err) => write!(fmt, "{}", err),
             #[cfg(feature = "native-tls")]
             Error::TlsError(ref err) => write!(fmt, "{}", err),
-            Error::StatusCode(ref msg) => write!(fmt, "unexpected status code: {}", msg),
+            Error::StatusCode(ref err) => {
+                write!(fmt, "unexpected status code: {}\n{}", err.status(), err)
+            }
         }
     }
 }
 
 impl Error {
     pub fn from_status(status: StatusCode, msg: String) -> Self {
-        match status {
-            StatusCode::UNAUTHORIZED => Error::Unauthorized(msg),
-            StatusCode::FORBIDDEN => Error::Forbidden(msg),
-            StatusCode::BAD_REQUEST => Error::BadRequest(msg),
-            StatusCode::CONFLICT => Error::Conflict(msg),
-            StatusCode::LOCKED => Error::Locked(msg),
-            StatusCode::NOT_FOUND => Error::NotFound(msg),
-            _ => Error::StatusCode(format!("{}\n{}", status.to_string(), msg)),
+        ApiError::new(status, &msg).into()
+    }
+
+    /// An [`Error::InvalidInput`] for invalid input detected on the client side.
+    pub(crate) fn invalid_input<M: Into<String>>(message: M) -> Self {
+        Error::InvalidInput(message.into())
+    }
+
+    /// An [`Error::UnexpectedResponse`] for a response of SDKMS the client can not use.
+    pub(crate) fn unexpected_response<M: Into<String>>(message: M) -> Self {
+        Error::UnexpectedResponse(message.into())
+    }
+
+    /// An [`Error::EncoderError`] for a value of the client that can not be encoded, or a local cryptographic
+    /// operation that failed.
+    pub(crate) fn encoding<M: fmt::Display>(message: M) -> Self {
+        Error::EncoderError(SerializeError::custom(message))
+    }
+
+    /// The error response returned by SDKMS, if the request reached the server.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match *self {
+            Error::Unauthorized(ref err)
+            | Error::Forbidden(ref err)
+            | Error::ApprovalRequired(ref err)
+            | Error::BadRequest(ref err)
+            | Error::Conflict(ref err)
+            | Error::Locked(ref err)
+            | Error::NotFound(ref err)
+            | Error::StatusCode(ref err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// HTTP status code of the error response, if the request reached the server.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.api_error().map(ApiError::status)
+    }
+
+    /// Whether the error is likely transient, i.e. the same request may succeed if retried later. This includes rate
+    /// limiting, temporary unavailability of SDKMS and network errors such as connection resets. See also
+    /// [`RetryPolicy`].
+    ///
+    /// [`RetryPolicy`]: ../struct.RetryPolicy.html
+    pub fn is_retryable(&self) -> bool {
+        match *self {
+            Error::NetworkError(ref err) => crate::retry::is_transient(err),
+            _ => matches!(
+                self.status(),
+                Some(StatusCode::TOO_MANY_REQUESTS)
+                    | Some(StatusCode::BAD_GATEWAY)
+                    | Some(StatusCode::SERVICE_UNAVAILABLE)
+                    | Some(StatusCode::GATEWAY_TIMEOUT)
+            ),
+        }
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(err: ApiError) -> Error {
+        match err.status() {
+            StatusCode::UNAUTHORIZED => Error::Unauthorized(err),
+            StatusCode::FORBIDDEN if err.is_approval_required() => Error::ApprovalRequired(err),
+            StatusCode::FORBIDDEN => Error::Forbidden(err),
+            StatusCode::BAD_REQUEST => Error::BadRequest(err),
+            StatusCode::CONFLICT => Error::Conflict(err),
+            StatusCode::LOCKED => Error::Locked(err),
+            StatusCode::NOT_FOUND => Error::NotFound(err),
+            _ => Error::StatusCode(err),
         }
     }
 }
@@ -295,6 +483,30 @@ impl<T> BatchResponseItem<T> {
 
 pub type BatchResponse<T> = Vec<BatchResponseItem<T>>;
 
+impl Sobject {
+    /// State of the security object at time `now`, taking its activation, deactivation and compromise dates into
+    /// account: `state` is not updated when one of these dates passes.
+    pub fn effective_state(&self, now: Time) -> SobjectState {
+        let past = |date: Option<Time>| matches!(date, Some(date) if date <= now);
+        match self.state {
+            Some(SobjectState::Compromised) => SobjectState::Compromised,
+            _ if past(self.compromise_date) => SobjectState::Compromised,
+            Some(SobjectState::Deactivated) => SobjectState::Deactivated,
+            _ if past(self.deactivation_date) => SobjectState::Deactivated,
+            Some(SobjectState::PreActive) => SobjectState::PreActive,
+            _ if matches!(self.activation_date, Some(date) if date > now) => {
+                SobjectState::PreActive
+            }
+            _ => SobjectState::Active,
+        }
+    }
+
+    /// Whether the security object is enabled and in the `Active` state at time `now`.
+    pub fn is_active(&self, now: Time) -> bool {
+        self.enabled && self.effective_state(now) == SobjectState::Active
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AuthResponse {
     pub token_type: String,
@@ -487,4 +699,43 @@ mod tests {
             "date/times before Unix epoch (Jan. 1, 1970 00:00:00 UTC) cannot be stored as `Time`"
         );
     }
+
+    #[test]
+    fn error_from_status() {
+        let err = Error::from_status(StatusCode::NOT_FOUND, "sobject does not exist".to_owned());
+        assert!(matches!(err, Error::NotFound(_)));
+        assert_eq!(err.to_string(), "sobject does not exist");
+        assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+        assert!(err.api_error().unwrap().fields().is_empty());
+
+        let err = Error::from_status(
+            StatusCode::FORBIDDEN,
+            "This operation requires approval".to_owned(),
+        );
+        assert!(matches!(err, Error::ApprovalRequired(_)));
+
+        let err: Error = ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            r#"{"message":"try again later","code":"UNAVAILABLE"}"#,
+        )
+        .with_request(&Method::POST, "/crypto/v1/encrypt")
+        .into();
+        assert!(err.is_retryable());
+        let api_err = err.api_error().unwrap();
+        assert_eq!(api_err.message(), "try again later");
+        assert_eq!(api_err.code(), Some("UNAVAILABLE"));
+        assert_eq!(api_err.method(), Some(&Method::POST));
+        assert_eq!(api_err.path(), Some("/crypto/v1/encrypt"));
+        assert!(!Error::from_status(StatusCode::BAD_REQUEST, String::new()).is_retryable());
+
+        let err = Error::invalid_input("invalid PEM");
+        assert!(matches!(err, Error::InvalidInput(_)));
+        assert_eq!(err.to_string(), "invalid PEM");
+        assert!(err.api_error().is_none());
+        assert_eq!(err.status(), None);
+        assert!(matches!(
+            Error::encoding("invalid length"),
+            Error::EncoderError(_)
+        ));
+    }
 }
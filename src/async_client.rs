@@ -0,0 +1,451 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::api_model::*;
+use crate::client::{
+    approvable_result, json_decode_reader, now, request_headers_and_body, Auth, PendingApproval,
+    Result, DEFAULT_API_ENDPOINT,
+};
+use crate::operations::*;
+use crate::retry::{Retry, RetryPolicy};
+
+use serde::{Deserialize, Serialize};
+use simple_hyper_client::{to_bytes, Client as HttpClient, Method};
+use uuid::Uuid;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A builder for [`AsyncSdkmsClient`](./struct.AsyncSdkmsClient.html)
+pub struct AsyncSdkmsClientBuilder {
+    client: Option<HttpClient>,
+    api_endpoint: Option<String>,
+    auth: Option<Auth>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl AsyncSdkmsClientBuilder {
+    /// This can be used to customize the underlying HTTP client if desired.
+    pub fn with_http_client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+    /// This can be used to set the API endpoint. Otherwise the [default endpoint](./constant.DEFAULT_API_ENDPOINT.html) is used.
+    pub fn with_api_endpoint(mut self, api_endpoint: &str) -> Self {
+        self.api_endpoint = Some(api_endpoint.to_owned());
+        self
+    }
+    /// This can be used to make API calls without establishing a session.
+    /// The API key will be passed along as HTTP Basic auth header on all API calls.
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.auth = Some(Auth::from_api_key(api_key));
+        self
+    }
+    /// This can be used to restore an established session.
+    pub fn with_access_token(mut self, access_token: &str) -> Self {
+        self.auth = Some(Auth::Bearer(access_token.to_owned()));
+        self
+    }
+    /// This can be used to retry requests that fail due to transient errors, e.g. when SDKMS is overloaded.
+    /// By default, each request is attempted once.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+    /// Build [`AsyncSdkmsClient`](./struct.AsyncSdkmsClient.html)
+    pub fn build(self) -> Result<AsyncSdkmsClient> {
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                #[cfg(feature = "native-tls")]
+                {
+                    use simple_hyper_client::HttpsConnector;
+                    use tokio_native_tls::native_tls::TlsConnector;
+
+                    let ssl = TlsConnector::new()?;
+                    let connector = HttpsConnector::new(ssl.into());
+                    HttpClient::with_connector(connector)
+                }
+                #[cfg(not(feature = "native-tls"))]
+                panic!("You should either provide an HTTP Client or compile this crate with native-tls feature");
+            }
+        };
+
+        Ok(AsyncSdkmsClient {
+            client,
+            api_endpoint: self
+                .api_endpoint
+                .unwrap_or_else(|| DEFAULT_API_ENDPOINT.to_owned()),
+            auth: self.auth,
+            last_used: AtomicU64::new(0),
+            auth_response: None,
+            retry_policy: self.retry_policy,
+        })
+    }
+}
+
+/// An asynchronous client session with SDKMS.
+///
+/// This is the non-blocking counterpart of [`SdkmsClient`] and exposes the same REST APIs as `async` methods. It is
+/// built on top of [`simple_hyper_client::Client`] and must be used from within a tokio runtime.
+///
+/// Unlike [`SdkmsClient`], dropping an `AsyncSdkmsClient` does not terminate the session since that would require
+/// blocking in `drop()`. Call [`terminate()`](#method.terminate) explicitly when the session is no longer needed.
+///
+/// [`SdkmsClient`]: ./struct.SdkmsClient.html
+/// [`simple_hyper_client::Client`]: https://docs.rs/simple-hyper-client/0.1.0/simple_hyper_client/struct.Client.html
+pub struct AsyncSdkmsClient {
+    auth: Option<Auth>,
+    api_endpoint: String,
+    client: HttpClient,
+    last_used: AtomicU64, // Time.0
+    auth_response: Option<AuthResponse>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl AsyncSdkmsClient {
+    pub fn builder() -> AsyncSdkmsClientBuilder {
+        AsyncSdkmsClientBuilder {
+            client: None,
+            api_endpoint: None,
+            auth: None,
+            retry_policy: None,
+        }
+    }
+
+    async fn authenticate(&self, auth: Option<&Auth>) -> Result<Self> {
+        let auth_response: AuthResponse = json_request_with_auth(
+            &self.client,
+            &self.api_endpoint,
+            Method::POST,
+            "/sys/v1/session/auth",
+            auth,
+            None::<&()>,
+            Retry::new(self.retry_policy.as_ref(), true),
+        )
+        .await?;
+        Ok(AsyncSdkmsClient {
+            client: self.client.clone(),
+            api_endpoint: self.api_endpoint.clone(),
+            auth: Some(Auth::Bearer(auth_response.access_token.clone())),
+            last_used: AtomicU64::new(now().0),
+            auth_response: Some(auth_response),
+            retry_policy: self.retry_policy.clone(),
+        })
+    }
+
+    pub async fn authenticate_with_api_key(&self, api_key: &str) -> Result<Self> {
+        self.authenticate(Some(Auth::from_api_key(api_key)).as_ref())
+            .await
+    }
+
+    pub async fn authenticate_with_cert(&self, app_id: Option<&Uuid>) -> Result<Self> {
+        self.authenticate(app_id.map(|id| Auth::from_user_pass(id, "")).as_ref())
+            .await
+    }
+
+    pub async fn authenticate_app(&self, app_id: &Uuid, app_secret: &str) -> Result<Self> {
+        self.authenticate(Some(Auth::from_user_pass(app_id, app_secret)).as_ref())
+            .await
+    }
+
+    pub async fn authenticate_user(&self, email: &str, password: &str) -> Result<Self> {
+        self.authenticate(Some(Auth::from_user_pass(email, password)).as_ref())
+            .await
+    }
+
+    pub fn api_endpoint(&self) -> &str {
+        &self.api_endpoint
+    }
+
+    pub fn auth_response(&self) -> Option<&AuthResponse> {
+        self.auth_response.as_ref()
+    }
+
+    pub fn entity_id(&self) -> Option<Uuid> {
+        self.auth_response().map(|ar| ar.entity_id)
+    }
+
+    pub fn has_session(&self) -> bool {
+        matches!(self.auth, Some(Auth::Bearer(_)))
+    }
+
+    async fn json_request<E, D>(
+        &self,
+        method: Method,
+        uri: &str,
+        req: Option<&E>,
+        retry: Retry<'_>,
+    ) -> Result<D>
+    where
+        E: Serialize,
+        D: for<'de> Deserialize<'de>,
+    {
+        let Self {
+            ref client,
+            ref api_endpoint,
+            ref auth,
+            ..
+        } = *self;
+        let result =
+            json_request_with_auth(client, api_endpoint, method, uri, auth.as_ref(), req, retry)
+                .await?;
+        self.last_used.store(now().0, Ordering::Relaxed);
+        Ok(result)
+    }
+
+    pub async fn terminate(&mut self) -> Result<()> {
+        if let Some(Auth::Bearer(_)) = self.auth {
+            self.json_request::<(), ()>(
+                Method::POST,
+                "/sys/v1/session/terminate",
+                None,
+                Retry::none(),
+            )
+            .await?;
+            self.auth = None;
+        }
+        Ok(())
+    }
+
+    pub async fn invoke_plugin_nice<I, O>(&self, id: &Uuid, req: &I) -> Result<O>
+    where
+        I: Serialize,
+        O: for<'de> Deserialize<'de>,
+    {
+        let req = serde_json::to_value(req)?;
+        let output = self
+            .execute::<OperationInvokePlugin>(&req, (id,), None)
+            .await?;
+        Ok(serde_json::from_value(output)?)
+    }
+
+    pub async fn execute<O: Operation>(
+        &self,
+        body: &O::Body,
+        p: <O::PathParams as TupleRef<'_>>::Ref,
+        q: Option<&O::QueryParams>,
+    ) -> Result<O::Output> {
+        let path = O::path(p, q);
+        let body = O::to_body(body);
+        let retry = Retry::new(self.retry_policy.as_ref(), O::is_idempotent());
+        self.json_request(O::method(), &path, body.as_ref(), retry)
+            .await
+    }
+
+    pub async fn request_approval<O: Operation>(
+        &self,
+        body: &O::Body,
+        p: <O::PathParams as TupleRef<'_>>::Ref,
+        q: Option<&O::QueryParams>,
+        description: Option<String>,
+    ) -> Result<PendingApproval<O>> {
+        let request = self
+            .create_approval_request(&ApprovalRequestRequest {
+                operation: Some(O::path(p, q)),
+                method: Some(format!("{}", O::method())),
+                body: O::to_body(body),
+                description,
+            })
+            .await?;
+        Ok(PendingApproval::from_request_id(request.request_id))
+    }
+
+    pub fn expires_in(&self) -> Option<u64> {
+        let expires_at = self.last_used.load(Ordering::Relaxed)
+            + self.auth_response().map_or(0, |ar| ar.expires_in as u64);
+        expires_at.checked_sub(now().0)
+    }
+}
+
+impl<O: Operation> PendingApproval<O> {
+    pub async fn get_async(&self, sdkms: &AsyncSdkmsClient) -> Result<ApprovalRequest> {
+        sdkms.get_approval_request(&self.request_id()).await
+    }
+
+    pub async fn status_async(&self, sdkms: &AsyncSdkmsClient) -> Result<ApprovalStatus> {
+        Ok(self.get_async(sdkms).await?.status)
+    }
+
+    pub async fn result_async(&self, sdkms: &AsyncSdkmsClient) -> Result<Result<O::Output>> {
+        let result = sdkms
+            .get_approval_request_result(&self.request_id())
+            .await?;
+        approvable_result::<O>(result)
+    }
+}
+
+async fn json_request_with_auth<E, D>(
+    client: &HttpClient,
+    api_endpoint: &str,
+    method: Method,
+    path: &str,
+    auth: Option<&Auth>,
+    body: Option<&E>,
+    mut retry: Retry<'_>,
+) -> Result<D>
+where
+    E: Serialize,
+    D: for<'de> Deserialize<'de>,
+{
+    let url = format!("{}{}", api_endpoint, path);
+    let (headers, body) = request_headers_and_body(auth, body)?;
+    loop {
+        let mut req = client.request(method.clone(), &url)?;
+        if let Some(ref body) = body {
+            req = req.body(body.clone());
+        }
+        req = req.headers(headers.clone());
+        let res = match req.send().await {
+            Err(e) => {
+                info!("Error {} {}", method, url);
+                if let Some(delay) = retry.after_error(&e) {
+                    info!(
+                        "Retrying {} {} in {:?} (attempt {})",
+                        method,
+                        url,
+                        delay,
+                        retry.attempt()
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                return Err(Error::NetworkError(e));
+            }
+            Ok(res) => res,
+        };
+        info!("{} {} {}", res.status().as_u16(), method, url);
+        let status = res.status();
+        if !status.is_success() {
+            if let Some(delay) = retry.after_status(status, res.headers()) {
+                info!(
+                    "Retrying {} {} in {:?} (attempt {})",
+                    method,
+                    url,
+                    delay,
+                    retry.attempt()
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+        }
+        let body = to_bytes(res.into_body())
+            .await
+            .map_err(|err| Error::NetworkError(err.into()))?;
+        return if status.is_success() {
+            json_decode_reader(&mut &body[..]).map_err(Error::EncoderError)
+        } else {
+            Err(ApiError::new(status, &String::from_utf8_lossy(&body))
+                .with_request(&method, path)
+                .into())
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn client_is_send_and_sync() {
+        assert_send::<AsyncSdkmsClient>();
+        assert_sync::<AsyncSdkmsClient>();
+
+        assert_send::<AsyncSdkmsClientBuilder>();
+        assert_sync::<AsyncSdkmsClientBuilder>();
+    }
+
+    #[allow(unused)]
+    fn futures_are_send(client: &AsyncSdkmsClient, req: &EncryptRequest, id: &Uuid) {
+        fn assert_send_val<T: Send>(_: &T) {}
+
+        assert_send_val(&client.encrypt(req));
+        assert_send_val(&client.get_sobject(None, &SobjectDescriptor::Kid(*id)));
+        assert_send_val(&client.request_approval_to_encrypt(req, None));
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn mock_server() {
+        use crate::mock::MockServer;
+
+        let server = MockServer::start().unwrap();
+        let mut client = server
+            .async_client()
+            .unwrap()
+            .authenticate_with_api_key(&server.api_key())
+            .await
+            .unwrap();
+        assert!(client.has_session());
+        assert_eq!(client.entity_id(), Some(server.app_id()));
+
+        let kid = client
+            .create_sobject(&SobjectRequest {
+                name: Some("key".to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .kid
+            .unwrap();
+        let req = EncryptRequest {
+            plain: "hello, world!".into(),
+            alg: Algorithm::Aes,
+            key: Some(SobjectDescriptor::Kid(kid)),
+            mode: Some(CryptMode::Symmetric(CipherMode::Cbc)),
+            iv: None,
+            ad: None,
+            tag_len: None,
+        };
+        let encrypted = client.encrypt(&req).await.unwrap();
+        assert_eq!(encrypted.kid, Some(kid));
+        let decrypted = client
+            .decrypt(&DecryptRequest {
+                key: Some(SobjectDescriptor::Kid(kid)),
+                alg: Some(Algorithm::Aes),
+                cipher: encrypted.cipher,
+                mode: Some(CryptMode::Symmetric(CipherMode::Cbc)),
+                iv: encrypted.iv,
+                ad: None,
+                tag: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(decrypted.plain, req.plain);
+
+        let policy = ApprovalPolicy {
+            quorum: None,
+            user: None,
+            app: Some(server.app_id()),
+        };
+        assert!(server.set_approval_policy(server.group_id(), Some(policy)));
+        assert!(matches!(
+            client.encrypt(&req).await,
+            Err(Error::ApprovalRequired(_))
+        ));
+        let pending = client
+            .request_approval_to_encrypt(&req, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            pending.status_async(&client).await.unwrap(),
+            ApprovalStatus::Pending
+        );
+        client
+            .approve_request(&pending.request_id(), &ApproveRequest::default())
+            .await
+            .unwrap();
+        let encrypted = pending.result_async(&client).await.unwrap().unwrap();
+        assert_eq!(encrypted.kid, Some(kid));
+
+        client.terminate().await.unwrap();
+        assert!(!client.has_session());
+    }
+}
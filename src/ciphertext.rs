@@ -0,0 +1,501 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A self-describing container for ciphertext produced by SDKMS.
+//!
+//! A [`Ciphertext`] holds everything needed to decrypt data besides the key itself: the key id, algorithm, mode,
+//! IV, length of the authenticated data and tag. Since it records the id of the key that encrypted the data,
+//! decryption keeps working after the key is rotated. [`seal()`] and [`open()`] encrypt and decrypt through a
+//! [`CryptoProvider`]. Authenticated data is not stored in the ciphertext, it must be passed again to [`open()`].
+//!
+//! Ciphertexts have three encodings: a compact binary format ([`Ciphertext::to_bytes()`]), its base64 encoding
+//! ([`Ciphertext::to_base64()`]) and JSON, through the `Serialize` and `Deserialize` implementations.
+//!
+//! # Binary format
+//!
+//! Lengths are unsigned big-endian integers.
+//!
+//! | Size (bytes)  | Field                                                                             |
+//! |---------------|-----------------------------------------------------------------------------------|
+//! | 1             | Version, currently 1                                                              |
+//! | 16            | Key id                                                                            |
+//! | 1             | Algorithm: 1 = AES, 2 = DES, 3 = DES3, 4 = RSA                                    |
+//! | 1             | Mode, see below                                                                   |
+//! | 0 or 1        | MGF1 hash of RSA OAEP, present only with mode 0x81, see below                     |
+//! | 1 + n         | IV length and IV, the length is 0 if there is no IV                               |
+//! | 4             | Authenticated data length                                                         |
+//! | 1 + n         | Tag length and tag, the length is 0 if there is no tag                            |
+//! | rest          | Ciphertext                                                                        |
+//!
+//! Modes are 0 if the mode is not set, 1 to 11 for the cipher modes ECB, CBC, CBCNOPAD, CFB, OFB, CTR, GCM, CCM, KW,
+//! KWP and FF1 (in that order), 0x80 for RSA with PKCS#1 v1.5 padding and 0x81 for RSA with OAEP padding. Hash
+//! algorithms are numbered from 1 in the order BLAKE2B256, BLAKE2B384, BLAKE2B512, BLAKE2S256, RIPEMD160, SSL3,
+//! SHA1, SHA256, SHA384, SHA512, STREEBOG256, STREEBOG512, SHA3_224, SHA3_256, SHA3_384, SHA3_512.
+//!
+//! [`Ciphertext`]: ./struct.Ciphertext.html
+//! [`seal()`]: ./fn.seal.html
+//! [`open()`]: ./fn.open.html
+//! [`CryptoProvider`]: ../trait.CryptoProvider.html
+//! [`Ciphertext::to_bytes()`]: ./struct.Ciphertext.html#method.to_bytes
+//! [`Ciphertext::to_base64()`]: ./struct.Ciphertext.html#method.to_base64
+
+use crate::api_model::*;
+use crate::client::Result;
+use crate::provider::CryptoProvider;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use std::convert::TryFrom;
+
+/// Version of the ciphertext format.
+pub const CIPHERTEXT_VERSION: u8 = 1;
+
+const ALGORITHMS: [Algorithm; 4] = [
+    Algorithm::Aes,
+    Algorithm::Des,
+    Algorithm::Des3,
+    Algorithm::Rsa,
+];
+
+const CIPHER_MODES: [CipherMode; 11] = [
+    CipherMode::Ecb,
+    CipherMode::Cbc,
+    CipherMode::CbcNoPad,
+    CipherMode::Cfb,
+    CipherMode::Ofb,
+    CipherMode::Ctr,
+    CipherMode::Gcm,
+    CipherMode::Ccm,
+    CipherMode::Kw,
+    CipherMode::Kwp,
+    CipherMode::Ff1,
+];
+
+const DIGEST_ALGORITHMS: [DigestAlgorithm; 16] = [
+    DigestAlgorithm::Blake2b256,
+    DigestAlgorithm::Blake2b384,
+    DigestAlgorithm::Blake2b512,
+    DigestAlgorithm::Blake2s256,
+    DigestAlgorithm::Ripemd160,
+    DigestAlgorithm::Ssl3,
+    DigestAlgorithm::Sha1,
+    DigestAlgorithm::Sha256,
+    DigestAlgorithm::Sha384,
+    DigestAlgorithm::Sha512,
+    DigestAlgorithm::Streebog256,
+    DigestAlgorithm::Streebog512,
+    DigestAlgorithm::Sha3_224,
+    DigestAlgorithm::Sha3_256,
+    DigestAlgorithm::Sha3_384,
+    DigestAlgorithm::Sha3_512,
+];
+
+const MODE_RSA_PKCS1_V15: u8 = 0x80;
+const MODE_RSA_OAEP: u8 = 0x81;
+
+/// Default algorithm and mode to encrypt or wrap with `sobject`: AES-GCM, 3DES/DES-CBC, or RSA with the padding of
+/// the first encryption policy of the key (OAEP with MGF1-SHA1 if unconstrained).
+pub(crate) fn cipher_params(sobject: &Sobject) -> Result<(Algorithm, Option<CryptMode>)> {
+    let symmetric = |alg, mode| Ok((alg, Some(CryptMode::Symmetric(mode))));
+    match sobject.obj_type {
+        ObjectType::Aes => symmetric(Algorithm::Aes, CipherMode::Gcm),
+        ObjectType::Des3 => symmetric(Algorithm::Des3, CipherMode::Cbc),
+        ObjectType::Des => symmetric(Algorithm::Des, CipherMode::Cbc),
+        ObjectType::Rsa => {
+            let policy = sobject
+                .rsa
+                .as_ref()
+                .and_then(|rsa| rsa.encryption_policy.first())
+                .and_then(|policy| policy.padding);
+            let padding = match policy {
+                Some(RsaEncryptionPaddingPolicy::Pkcs1V15 {}) => RsaEncryptionPadding::Pkcs1V15 {},
+                Some(RsaEncryptionPaddingPolicy::Oaep {
+                    mgf: Some(MgfPolicy::Mgf1 { hash: Some(hash) }),
+                }) => RsaEncryptionPadding::Oaep {
+                    mgf: Mgf::Mgf1 { hash },
+                },
+                _ => RsaEncryptionPadding::Oaep {
+                    mgf: Mgf::Mgf1 {
+                        hash: DigestAlgorithm::Sha1,
+                    },
+                },
+            };
+            Ok((Algorithm::Rsa, Some(CryptMode::Rsa(padding))))
+        }
+        obj_type => Err(Error::invalid_input(format!(
+            "{:?} keys can not be used for encryption",
+            obj_type
+        ))),
+    }
+}
+
+fn malformed() -> Error {
+    Error::invalid_input("malformed ciphertext")
+}
+
+/// Number (starting from 1) of `item` in `table`.
+fn code<T: PartialEq>(table: &[T], item: &T) -> u8 {
+    table
+        .iter()
+        .position(|t| t == item)
+        .expect("table is complete") as u8
+        + 1
+}
+
+fn lookup<T: Copy>(table: &[T], code: u8) -> Result<T> {
+    (code as usize)
+        .checked_sub(1)
+        .and_then(|i| table.get(i))
+        .copied()
+        .ok_or_else(malformed)
+}
+
+/// Encrypted data along with the parameters needed to decrypt it, see the [module documentation](./index.html).
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Ciphertext {
+    version: u8,
+    /// Id of the encryption key.
+    pub kid: Uuid,
+    pub alg: Algorithm,
+    #[serde(default)]
+    pub mode: Option<CryptMode>,
+    #[serde(default)]
+    pub iv: Option<Blob>,
+    /// Length of the authenticated data, which is not stored in the ciphertext.
+    #[serde(default)]
+    pub ad_len: u32,
+    #[serde(default)]
+    pub tag: Option<Blob>,
+    pub cipher: Blob,
+}
+
+impl Ciphertext {
+    /// Combines an encryption request and the response of SDKMS. Fails if the response has no key id, i.e. for
+    /// transient keys.
+    pub fn new(req: &EncryptRequest, resp: EncryptResponse) -> Result<Self> {
+        let ad_len = req.ad.as_ref().map_or(0, |ad| ad.len());
+        Ok(Ciphertext {
+            version: CIPHERTEXT_VERSION,
+            kid: resp
+                .kid
+                .ok_or_else(|| Error::invalid_input("key id is required"))?,
+            alg: req.alg,
+            mode: req.mode.clone(),
+            iv: resp.iv,
+            ad_len: u32::try_from(ad_len).map_err(|_| Error::invalid_input("ad is too long"))?,
+            tag: resp.tag,
+            cipher: resp.cipher,
+        })
+    }
+
+    /// Version of the ciphertext format, [`CIPHERTEXT_VERSION`](./constant.CIPHERTEXT_VERSION.html) for
+    /// ciphertexts created by this version of the crate.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// A request to decrypt the ciphertext, with the authenticated data `ad` it was encrypted with. Fails if the
+    /// version is not supported or the length of `ad` does not match the ciphertext.
+    pub fn decrypt_request(&self, ad: Option<&[u8]>) -> Result<DecryptRequest> {
+        if self.version != CIPHERTEXT_VERSION {
+            return Err(Error::invalid_input("unsupported ciphertext version"));
+        }
+        if ad.map_or(0, <[u8]>::len) != self.ad_len as usize {
+            return Err(Error::invalid_input(
+                "the authenticated data does not match the ciphertext",
+            ));
+        }
+        Ok(DecryptRequest {
+            key: Some(SobjectDescriptor::Kid(self.kid)),
+            alg: Some(self.alg),
+            cipher: self.cipher.clone(),
+            mode: self.mode.clone(),
+            iv: self.iv.clone(),
+            ad: ad.filter(|ad| !ad.is_empty()).map(|ad| ad.to_vec().into()),
+            tag: self.tag.clone(),
+        })
+    }
+
+    /// Encodes the ciphertext in the binary format. Fails if the version is not supported, the algorithm is not an
+    /// encryption algorithm or the IV or tag is longer than 255 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.version != CIPHERTEXT_VERSION {
+            return Err(Error::invalid_input("unsupported ciphertext version"));
+        }
+        if !ALGORITHMS.contains(&self.alg) {
+            return Err(Error::invalid_input("alg is not an encryption algorithm"));
+        }
+        let mut out = Vec::with_capacity(64 + self.cipher.len());
+        out.push(self.version);
+        out.extend_from_slice(self.kid.as_bytes());
+        out.push(code(&ALGORITHMS, &self.alg));
+        match self.mode {
+            None => out.push(0),
+            Some(CryptMode::Symmetric(mode)) => out.push(code(&CIPHER_MODES, &mode)),
+            Some(CryptMode::Rsa(RsaEncryptionPadding::Pkcs1V15 {})) => out.push(MODE_RSA_PKCS1_V15),
+            Some(CryptMode::Rsa(RsaEncryptionPadding::Oaep {
+                mgf: Mgf::Mgf1 { hash },
+            })) => {
+                out.push(MODE_RSA_OAEP);
+                out.push(code(&DIGEST_ALGORITHMS, &hash));
+            }
+        }
+        push_short(&mut out, "iv", self.iv.as_deref())?;
+        out.extend_from_slice(&self.ad_len.to_be_bytes());
+        push_short(&mut out, "tag", self.tag.as_deref())?;
+        out.extend_from_slice(&self.cipher);
+        Ok(out)
+    }
+
+    /// Decodes a ciphertext in the binary format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        let version = reader.u8()?;
+        if version != CIPHERTEXT_VERSION {
+            return Err(Error::invalid_input("unsupported ciphertext version"));
+        }
+        let kid = Uuid::from_slice(reader.take(16)?).map_err(|_| malformed())?;
+        let alg = lookup(&ALGORITHMS, reader.u8()?)?;
+        let mode = match reader.u8()? {
+            0 => None,
+            MODE_RSA_PKCS1_V15 => Some(CryptMode::Rsa(RsaEncryptionPadding::Pkcs1V15 {})),
+            MODE_RSA_OAEP => Some(CryptMode::Rsa(RsaEncryptionPadding::Oaep {
+                mgf: Mgf::Mgf1 {
+                    hash: lookup(&DIGEST_ALGORITHMS, reader.u8()?)?,
+                },
+            })),
+            mode => Some(CryptMode::Symmetric(lookup(&CIPHER_MODES, mode)?)),
+        };
+        let len = reader.u8()? as usize;
+        let iv = reader.optional(len)?;
+        let mut ad_len = [0; 4];
+        ad_len.copy_from_slice(reader.take(4)?);
+        let len = reader.u8()? as usize;
+        let tag = reader.optional(len)?;
+        Ok(Ciphertext {
+            version,
+            kid,
+            alg,
+            mode,
+            iv,
+            ad_len: u32::from_be_bytes(ad_len),
+            tag,
+            cipher: reader.0.to_vec().into(),
+        })
+    }
+
+    /// Encodes the ciphertext in the binary format, then in base64.
+    pub fn to_base64(&self) -> Result<String> {
+        Ok(base64::encode(self.to_bytes()?))
+    }
+
+    /// Decodes a ciphertext encoded with [`to_base64()`](#method.to_base64).
+    pub fn from_base64(text: &str) -> Result<Self> {
+        Self::from_bytes(&base64::decode(text.trim()).map_err(|_| malformed())?)
+    }
+}
+
+/// Appends the length of `value` as a single byte, followed by `value`.
+fn push_short(out: &mut Vec<u8>, field: &str, value: Option<&[u8]>) -> Result<()> {
+    let value = value.unwrap_or_default();
+    let len = u8::try_from(value.len())
+        .map_err(|_| Error::invalid_input(format!("{} is too long", field)))?;
+    out.push(len);
+    out.extend_from_slice(value);
+    Ok(())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(malformed());
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// `len` bytes, or `None` if `len` is 0.
+    fn optional(&mut self, len: usize) -> Result<Option<Blob>> {
+        let value = self.take(len)?;
+        Ok(if value.is_empty() {
+            None
+        } else {
+            Some(value.to_vec().into())
+        })
+    }
+}
+
+/// Encrypts `plain` with `key` and returns it as a [`Ciphertext`]. AES keys are used in GCM mode, DES and DES3 keys in
+/// CBC mode and RSA keys with the padding of their first encryption policy (OAEP with SHA-1 if unconstrained), use
+/// [`seal_with()`] for other algorithms and modes.
+///
+/// [`Ciphertext`]: ./struct.Ciphertext.html
+/// [`seal_with()`]: ./fn.seal_with.html
+pub fn seal<P: CryptoProvider + ?Sized>(
+    provider: &P,
+    key: &SobjectDescriptor,
+    plain: &[u8],
+) -> Result<Ciphertext> {
+    let (alg, mode) = cipher_params(&provider.get_sobject(None, key)?)?;
+    seal_with(
+        provider,
+        &EncryptRequest {
+            key: Some(key.clone()),
+            alg,
+            plain: plain.to_vec().into(),
+            mode,
+            iv: None,
+            ad: None,
+            tag_len: None,
+        },
+    )
+}
+
+/// Encrypts with `req` and returns the result as a [`Ciphertext`].
+///
+/// [`Ciphertext`]: ./struct.Ciphertext.html
+pub fn seal_with<P: CryptoProvider + ?Sized>(
+    provider: &P,
+    req: &EncryptRequest,
+) -> Result<Ciphertext> {
+    Ciphertext::new(req, provider.encrypt(req)?)
+}
+
+/// Decrypts `ciphertext` with the key that encrypted it. `ad` is the authenticated data passed to [`seal_with()`],
+/// if any.
+///
+/// [`seal_with()`]: ./fn.seal_with.html
+pub fn open<P: CryptoProvider + ?Sized>(
+    provider: &P,
+    ciphertext: &Ciphertext,
+    ad: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    Ok(provider
+        .decrypt(&ciphertext.decrypt_request(ad)?)?
+        .plain
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings() {
+        let ciphertext = Ciphertext {
+            version: CIPHERTEXT_VERSION,
+            kid: Uuid::new_v4(),
+            alg: Algorithm::Rsa,
+            mode: Some(CryptMode::Rsa(RsaEncryptionPadding::Oaep {
+                mgf: Mgf::Mgf1 {
+                    hash: DigestAlgorithm::Sha256,
+                },
+            })),
+            iv: None,
+            ad_len: 3,
+            tag: None,
+            cipher: vec![4; 256].into(),
+        };
+        let bytes = ciphertext.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 1 + 16 + 1 + 2 + 1 + 4 + 1 + 256);
+        let unsupported = Ciphertext {
+            version: CIPHERTEXT_VERSION + 1,
+            ..ciphertext.clone()
+        };
+        assert!(unsupported.to_bytes().is_err());
+        assert!(unsupported.decrypt_request(Some(&[1, 2, 3])).is_err());
+        assert!(ciphertext.decrypt_request(None).is_err());
+        let req = ciphertext.decrypt_request(Some(&[1, 2, 3])).unwrap();
+        assert_eq!(req.ad.as_deref(), Some(&[1, 2, 3][..]));
+        assert_eq!(Ciphertext::from_bytes(&bytes).unwrap(), ciphertext);
+        assert!(Ciphertext::from_bytes(&bytes[..24]).is_err());
+        let text = ciphertext.to_base64().unwrap();
+        assert_eq!(Ciphertext::from_base64(&text).unwrap(), ciphertext);
+        let json = serde_json::to_string(&ciphertext).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Ciphertext>(&json).unwrap(),
+            ciphertext
+        );
+        let json = json.replace(r#""version":1"#, r#""version":2"#);
+        let unsupported = serde_json::from_str::<Ciphertext>(&json).unwrap();
+        assert!(unsupported.decrypt_request(Some(&[1, 2, 3])).is_err());
+    }
+
+    #[cfg(feature = "local")]
+    #[test]
+    fn seal_open() {
+        let backend = crate::local::LocalBackend::new();
+        backend
+            .create_sobject(&SobjectRequest {
+                name: Some("aes".to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                ..Default::default()
+            })
+            .unwrap();
+        let key = SobjectDescriptor::Name("aes".to_owned());
+        let ciphertext = seal(&backend, &key, b"secret").unwrap();
+        assert_eq!(ciphertext.mode, Some(CryptMode::Symmetric(CipherMode::Gcm)));
+        let ciphertext = Ciphertext::from_bytes(&ciphertext.to_bytes().unwrap()).unwrap();
+        assert_eq!(open(&backend, &ciphertext, None).unwrap(), b"secret");
+
+        let ciphertext = seal_with(
+            &backend,
+            &EncryptRequest {
+                key: Some(key.clone()),
+                alg: Algorithm::Aes,
+                plain: b"secret".to_vec().into(),
+                mode: Some(CryptMode::Symmetric(CipherMode::Gcm)),
+                iv: None,
+                ad: Some(b"header".to_vec().into()),
+                tag_len: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(ciphertext.ad_len, 6);
+        assert_eq!(
+            open(&backend, &ciphertext, Some(b"header")).unwrap(),
+            b"secret"
+        );
+        assert!(open(&backend, &ciphertext, Some(b"HEADER")).is_err());
+        assert!(open(&backend, &ciphertext, None).is_err());
+
+        backend
+            .create_sobject(&SobjectRequest {
+                name: Some("rsa".to_owned()),
+                obj_type: Some(ObjectType::Rsa),
+                key_size: Some(2048),
+                rsa: Some(RsaOptions {
+                    key_size: None,
+                    public_exponent: None,
+                    encryption_policy: vec![RsaEncryptionPolicy {
+                        padding: Some(RsaEncryptionPaddingPolicy::Pkcs1V15 {}),
+                    }],
+                    signature_policy: Vec::new(),
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+        let ciphertext = seal(
+            &backend,
+            &SobjectDescriptor::Name("rsa".to_owned()),
+            b"secret",
+        )
+        .unwrap();
+        assert_eq!(
+            ciphertext.mode,
+            Some(CryptMode::Rsa(RsaEncryptionPadding::Pkcs1V15 {}))
+        );
+        assert_eq!(open(&backend, &ciphertext, None).unwrap(), b"secret");
+    }
+}
@@ -6,6 +6,7 @@
 
 use crate::api_model::*;
 use crate::operations::*;
+use crate::retry::{Retry, RetryPolicy};
 
 use headers::{ContentType, HeaderMap, HeaderMapExt, HeaderValue};
 use serde::{Deserialize, Serialize};
@@ -18,23 +19,26 @@ use std::fmt;
 use std::io::Read;
 use std::marker::PhantomData;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::thread;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const DEFAULT_API_ENDPOINT: &'static str = "https://sdkms.fortanix.com";
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
-enum Auth {
+#[derive(Clone, PartialEq)]
+pub(crate) enum Auth {
     Basic(String),
     Bearer(String),
 }
 
 impl Auth {
-    fn from_api_key(api_key: &str) -> Self {
+    pub(crate) fn from_api_key(api_key: &str) -> Self {
         Auth::Basic(api_key.to_owned())
     }
 
-    fn from_user_pass<T: fmt::Display>(username: T, password: &str) -> Self {
+    pub(crate) fn from_user_pass<T: fmt::Display>(username: T, password: &str) -> Self {
         Auth::Basic(base64::encode(format!("{}:{}", username, password)))
     }
 
@@ -54,6 +58,8 @@ pub struct SdkmsClientBuilder {
     client: Option<HttpClient>,
     api_endpoint: Option<String>,
     auth: Option<Auth>,
+    auto_reauth: bool,
+    retry_policy: Option<RetryPolicy>,
 }
 
 impl SdkmsClientBuilder {
@@ -78,6 +84,22 @@ impl SdkmsClientBuilder {
         self.auth = Some(Auth::Bearer(access_token.to_owned()));
         self
     }
+    /// This can be used to keep sessions alive automatically. When enabled, sessions established through one of the
+    /// `authenticate_*()` methods retain the credentials used to authenticate. The session is refreshed when it is
+    /// about to expire and re-established when it has expired or a request fails with [`Error::Unauthorized`].
+    /// Sessions that require two-factor authentication are never re-established automatically.
+    ///
+    /// [`Error::Unauthorized`]: ./api_model/enum.Error.html#variant.Unauthorized
+    pub fn with_auto_reauth(mut self, auto_reauth: bool) -> Self {
+        self.auto_reauth = auto_reauth;
+        self
+    }
+    /// This can be used to retry requests that fail due to transient errors, e.g. when SDKMS is overloaded.
+    /// By default, each request is attempted once.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
     /// Build [`SdkmsClient`](./struct.SdkmsClient.html)
     pub fn build(self) -> Result<SdkmsClient> {
         let client = match self.client {
@@ -102,13 +124,25 @@ impl SdkmsClientBuilder {
             api_endpoint: self
                 .api_endpoint
                 .unwrap_or_else(|| DEFAULT_API_ENDPOINT.to_owned()),
-            auth: self.auth,
+            auth: RwLock::new(self.auth),
             last_used: AtomicU64::new(0),
             auth_response: None,
+            latest_auth_response: RwLock::new(None),
+            auto_reauth: self.auto_reauth,
+            retry_policy: self.retry_policy,
+            credentials: None,
+            renewal: Mutex::new(()),
         })
     }
 }
 
+/// Credentials retained for re-establishing a session, see [`SdkmsClientBuilder::with_auto_reauth()`].
+///
+/// [`SdkmsClientBuilder::with_auto_reauth()`]: ./struct.SdkmsClientBuilder.html#method.with_auto_reauth
+struct SessionCredentials {
+    auth: Option<Auth>,
+}
+
 /// A client session with SDKMS.
 ///
 /// REST APIs are exposed as methods on this type. Communication with SDKMS API endpoint is protected with TLS and this
@@ -123,6 +157,9 @@ impl SdkmsClientBuilder {
 /// - [`authenticate_with_cert()`](#method.authenticate_with_cert)
 /// - [`authenticate_app()`](#method.authenticate_app)
 ///
+/// Sessions expire after a period of inactivity. Long-running processes can opt into automatic session renewal using
+/// [`SdkmsClientBuilder::with_auto_reauth()`].
+///
 /// Note that certain non-cryptographic APIs require a user session, which can be established using
 /// [`authenticate_user()`](#method.authenticate_user). This includes many APIs such as:
 /// - [`create_group()`](#method.create_group)
@@ -143,15 +180,21 @@ impl SdkmsClientBuilder {
 /// [`simple_hyper_client::blocking::Client`]: https://docs.rs/simple-hyper-client/0.1.0/simple_hyper_client/blocking/struct.Client.html
 /// [`tokio_native_tls::TlsConnector`]: https://docs.rs/tokio-native-tls/0.3.0/tokio_native_tls/struct.TlsConnector.html
 /// [`SdkmsClientBuilder::with_api_key()`]: ./struct.SdkmsClientBuilder.html#method.with_api_key
+/// [`SdkmsClientBuilder::with_auto_reauth()`]: ./struct.SdkmsClientBuilder.html#method.with_auto_reauth
 /// [`SdkmsClient`]: ./struct.SdkmsClient.html
 /// [`encrypt()`]: #method.encrypt
 /// [`request_approval_to_encrypt()`]: #method.request_approval_to_encrypt
 pub struct SdkmsClient {
-    auth: Option<Auth>,
+    auth: RwLock<Option<Auth>>,
     api_endpoint: String,
     client: HttpClient,
     last_used: AtomicU64, // Time.0
     auth_response: Option<AuthResponse>,
+    latest_auth_response: RwLock<Option<AuthResponse>>,
+    auto_reauth: bool,
+    retry_policy: Option<RetryPolicy>,
+    credentials: Option<SessionCredentials>,
+    renewal: Mutex<()>,
 }
 
 impl SdkmsClient {
@@ -160,24 +203,42 @@ impl SdkmsClient {
             client: None,
             api_endpoint: None,
             auth: None,
+            auto_reauth: false,
+            retry_policy: None,
         }
     }
 
-    fn authenticate(&self, auth: Option<&Auth>) -> Result<Self> {
-        let auth_response: AuthResponse = json_request_with_auth(
+    fn session_auth(&self, auth: Option<&Auth>) -> Result<AuthResponse> {
+        json_request_with_auth(
             &self.client,
             &self.api_endpoint,
             Method::POST,
             "/sys/v1/session/auth",
             auth,
             None::<&()>,
-        )?;
+            Retry::new(self.retry_policy.as_ref(), true),
+        )
+    }
+
+    fn authenticate(&self, auth: Option<&Auth>) -> Result<Self> {
+        let auth_response = self.session_auth(auth)?;
+        let credentials = match self.auto_reauth && auth_response.challenge.is_none() {
+            true => Some(SessionCredentials {
+                auth: auth.cloned(),
+            }),
+            false => None,
+        };
         Ok(SdkmsClient {
             client: self.client.clone(),
             api_endpoint: self.api_endpoint.clone(),
-            auth: Some(Auth::Bearer(auth_response.access_token.clone())),
+            auth: RwLock::new(Some(Auth::Bearer(auth_response.access_token.clone()))),
             last_used: AtomicU64::new(now().0),
+            latest_auth_response: RwLock::new(Some(auth_response.clone())),
             auth_response: Some(auth_response),
+            auto_reauth: self.auto_reauth,
+            retry_policy: self.retry_policy.clone(),
+            credentials,
+            renewal: Mutex::new(()),
         })
     }
 
@@ -201,36 +262,122 @@ impl SdkmsClient {
         &self.api_endpoint
     }
 
+    /// Returns the response of the session authentication that created this client. When the session is
+    /// re-established automatically, see [`SdkmsClientBuilder::with_auto_reauth()`], the most recent response is
+    /// returned by [`auth_response_cloned()`].
+    ///
+    /// [`SdkmsClientBuilder::with_auto_reauth()`]: ./struct.SdkmsClientBuilder.html#method.with_auto_reauth
+    /// [`auth_response_cloned()`]: #method.auth_response_cloned
     pub fn auth_response(&self) -> Option<&AuthResponse> {
         self.auth_response.as_ref()
     }
 
+    /// Returns the response of the most recent session authentication.
+    pub fn auth_response_cloned(&self) -> Option<AuthResponse> {
+        self.latest_auth_response.read().unwrap().clone()
+    }
+
     pub fn entity_id(&self) -> Option<Uuid> {
         self.auth_response().map(|ar| ar.entity_id)
     }
 
     pub fn has_session(&self) -> bool {
-        match self.auth {
-            Some(Auth::Bearer(_)) => true,
-            _ => false,
-        }
+        matches!(*self.auth.read().unwrap(), Some(Auth::Bearer(_)))
     }
 
-    fn json_request<E, D>(&self, method: Method, uri: &str, req: Option<&E>) -> Result<D>
+    fn json_request<E, D>(
+        &self,
+        method: Method,
+        uri: &str,
+        req: Option<&E>,
+        idempotent: bool,
+    ) -> Result<D>
     where
         E: Serialize,
         D: for<'de> Deserialize<'de>,
     {
-        let Self {
-            ref client,
-            ref api_endpoint,
-            ref auth,
-            ..
-        } = *self;
-        let result = json_request_with_auth(client, api_endpoint, method, uri, auth.as_ref(), req)?;
+        if self.credentials.is_some() {
+            self.renew_session(None)?;
+        }
+        let auth = self.auth.read().unwrap().clone();
+        let result = match json_request_with_auth(
+            &self.client,
+            &self.api_endpoint,
+            method.clone(),
+            uri,
+            auth.as_ref(),
+            req,
+            Retry::new(self.retry_policy.as_ref(), idempotent),
+        ) {
+            Err(Error::Unauthorized(_)) if self.credentials.is_some() => {
+                self.renew_session(auth.as_ref())?;
+                let auth = self.auth.read().unwrap().clone();
+                json_request_with_auth(
+                    &self.client,
+                    &self.api_endpoint,
+                    method,
+                    uri,
+                    auth.as_ref(),
+                    req,
+                    Retry::new(self.retry_policy.as_ref(), idempotent),
+                )?
+            }
+            result => result?,
+        };
         self.last_used.store(now().0, Ordering::Relaxed);
         Ok(result)
     }
+
+    /// Refreshes the session if it is about to expire, or re-authenticates if it has expired. If `rejected` is
+    /// specified, the session is re-established unless another thread has already replaced the rejected token.
+    fn renew_session(&self, rejected: Option<&Auth>) -> Result<()> {
+        let credentials = match self.credentials {
+            Some(ref credentials) => credentials,
+            None => return Ok(()),
+        };
+        let _guard = self.renewal.lock().unwrap();
+        if rejected.is_some() && self.auth.read().unwrap().as_ref() != rejected {
+            return Ok(());
+        }
+        let force = rejected.is_some();
+        let session_lifetime = self
+            .auth_response_cloned()
+            .map_or(0, |ar| ar.expires_in as u64);
+        match self.expires_in() {
+            Some(expires_in) if !force && expires_in > refresh_margin(session_lifetime) => Ok(()),
+            Some(expires_in) if !force && expires_in > 0 => match self.refresh_session() {
+                Err(Error::Unauthorized(_)) => self.reauthenticate(credentials),
+                result => result,
+            },
+            _ => self.reauthenticate(credentials),
+        }
+    }
+
+    fn refresh_session(&self) -> Result<()> {
+        let auth = self.auth.read().unwrap().clone();
+        json_request_with_auth::<(), ()>(
+            &self.client,
+            &self.api_endpoint,
+            OperationRefresh::method(),
+            &OperationRefresh::path((), None),
+            auth.as_ref(),
+            None,
+            Retry::new(
+                self.retry_policy.as_ref(),
+                OperationRefresh::is_idempotent(),
+            ),
+        )?;
+        self.last_used.store(now().0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn reauthenticate(&self, credentials: &SessionCredentials) -> Result<()> {
+        let auth_response = self.session_auth(credentials.auth.as_ref())?;
+        *self.auth.write().unwrap() = Some(Auth::Bearer(auth_response.access_token.clone()));
+        *self.latest_auth_response.write().unwrap() = Some(auth_response);
+        self.last_used.store(now().0, Ordering::Relaxed);
+        Ok(())
+    }
 }
 
 impl Drop for SdkmsClient {
@@ -241,9 +388,19 @@ impl Drop for SdkmsClient {
 
 impl SdkmsClient {
     pub fn terminate(&mut self) -> Result<()> {
-        if let Some(Auth::Bearer(_)) = self.auth {
-            self.json_request(Method::POST, "/sys/v1/session/terminate", None::<&()>)?;
-            self.auth = None;
+        let auth = self.auth.get_mut().unwrap();
+        if let Some(Auth::Bearer(_)) = auth {
+            json_request_with_auth::<(), ()>(
+                &self.client,
+                &self.api_endpoint,
+                Method::POST,
+                "/sys/v1/session/terminate",
+                auth.as_ref(),
+                None,
+                Retry::none(),
+            )?;
+            *auth = None;
+            self.credentials = None;
         }
         Ok(())
     }
@@ -264,7 +421,12 @@ impl SdkmsClient {
         p: <O::PathParams as TupleRef>::Ref,
         q: Option<&O::QueryParams>,
     ) -> Result<O::Output> {
-        self.json_request(O::method(), &O::path(p, q), O::to_body(body).as_ref())
+        self.json_request(
+            O::method(),
+            &O::path(p, q),
+            O::to_body(body).as_ref(),
+            O::is_idempotent(),
+        )
     }
 
     pub fn request_approval<O: Operation>(
@@ -285,11 +447,22 @@ impl SdkmsClient {
 
     pub fn expires_in(&self) -> Option<u64> {
         let expires_at = self.last_used.load(Ordering::Relaxed)
-            + self.auth_response().map_or(0, |ar| ar.expires_in as u64);
+            + self
+                .latest_auth_response
+                .read()
+                .unwrap()
+                .as_ref()
+                .map_or(0, |ar| ar.expires_in as u64);
         expires_at.checked_sub(now().0)
     }
 }
 
+/// Number of seconds before session expiry at which an automatic refresh is attempted.
+fn refresh_margin(session_lifetime: u64) -> u64 {
+    const MAX_REFRESH_MARGIN: u64 = 60;
+    (session_lifetime / 4).min(MAX_REFRESH_MARGIN)
+}
+
 pub struct PendingApproval<O: Operation>(Uuid, PhantomData<O>);
 
 impl<O: Operation> fmt::Debug for PendingApproval<O> {
@@ -317,25 +490,28 @@ impl<O: Operation> PendingApproval<O> {
 
     pub fn result(&self, sdkms: &SdkmsClient) -> Result<Result<O::Output>> {
         let result = sdkms.get_approval_request_result(&self.0)?;
-        Ok(if result.is_ok() {
-            serde_json::from_value::<O::Output>(result.body).map_err(Error::EncoderError)
-        } else {
-            let msg: String = serde_json::from_value(result.body).map_err(Error::EncoderError)?;
-            Err(Error::from_status(
-                StatusCode::from_u16(result.status).unwrap(),
-                msg,
-            ))
-        })
+        approvable_result::<O>(result)
     }
 }
 
+pub(crate) fn approvable_result<O: Operation>(
+    result: ApprovableResult,
+) -> Result<Result<O::Output>> {
+    Ok(if result.is_ok() {
+        serde_json::from_value::<O::Output>(result.body).map_err(Error::EncoderError)
+    } else {
+        let status = StatusCode::from_u16(result.status).unwrap();
+        Err(ApiError::from_json(status, result.body).into())
+    })
+}
+
 impl<O: Operation> Clone for PendingApproval<O> {
     fn clone(&self) -> Self {
         PendingApproval(self.0, PhantomData)
     }
 }
 
-fn now() -> Time {
+pub(crate) fn now() -> Time {
     Time(
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -344,7 +520,9 @@ fn now() -> Time {
     )
 }
 
-fn json_decode_reader<R: Read, T: for<'de> Deserialize<'de>>(rdr: &mut R) -> serde_json::Result<T> {
+pub(crate) fn json_decode_reader<R: Read, T: for<'de> Deserialize<'de>>(
+    rdr: &mut R,
+) -> serde_json::Result<T> {
     match serde_json::from_reader(rdr) {
         // When the body of the response is empty, attempt to deserialize null value instead
         Err(ref e) if e.is_eof() && e.line() == 1 && e.column() == 0 => {
@@ -354,6 +532,24 @@ fn json_decode_reader<R: Read, T: for<'de> Deserialize<'de>>(rdr: &mut R) -> ser
     }
 }
 
+pub(crate) fn request_headers_and_body<E: Serialize>(
+    auth: Option<&Auth>,
+    body: Option<&E>,
+) -> Result<(HeaderMap, Option<String>)> {
+    let mut headers = HeaderMap::new();
+    if let Some(auth) = auth {
+        headers.insert(AUTHORIZATION, auth.format_header());
+    }
+    let body = match body {
+        Some(request_body) => {
+            headers.typed_insert(ContentType::json());
+            Some(serde_json::to_string(request_body).map_err(Error::EncoderError)?)
+        }
+        None => None,
+    };
+    Ok((headers, body))
+}
+
 fn json_request_with_auth<E, D>(
     client: &HttpClient,
     api_endpoint: &str,
@@ -361,39 +557,61 @@ fn json_request_with_auth<E, D>(
     path: &str,
     auth: Option<&Auth>,
     body: Option<&E>,
+    mut retry: Retry,
 ) -> Result<D>
 where
     E: Serialize,
     D: for<'de> Deserialize<'de>,
 {
     let url = format!("{}{}", api_endpoint, path);
-    let mut req = client.request(method.clone(), &url)?;
-    let mut headers = HeaderMap::new();
-    if let Some(auth) = auth {
-        headers.insert(AUTHORIZATION, auth.format_header());
-    }
-    if let Some(request_body) = body {
-        headers.typed_insert(ContentType::json());
-        let body = serde_json::to_string(request_body).map_err(Error::EncoderError)?;
-        req = req.body(body);
-    }
-    req = req.headers(headers);
-    match req.send() {
-        Err(e) => {
-            info!("Error {} {}", method, url);
-            Err(Error::NetworkError(e))
-        }
-        Ok(ref mut res) if res.status().is_success() => {
-            info!("{} {} {}", res.status().as_u16(), method, url);
-            json_decode_reader(res.body_mut()).map_err(|err| Error::EncoderError(err))
+    let (headers, body) = request_headers_and_body(auth, body)?;
+    loop {
+        let mut req = client.request(method.clone(), &url)?;
+        if let Some(ref body) = body {
+            req = req.body(body.clone());
         }
-        Ok(ref mut res) => {
-            info!("{} {} {}", res.status().as_u16(), method, url);
-            let mut buffer = String::new();
-            res.body_mut()
-                .read_to_string(&mut buffer)
-                .map_err(|err| Error::IoError(err))?;
-            Err(Error::from_status(res.status(), buffer))
+        req = req.headers(headers.clone());
+        match req.send() {
+            Err(e) => {
+                info!("Error {} {}", method, url);
+                if let Some(delay) = retry.after_error(&e) {
+                    info!(
+                        "Retrying {} {} in {:?} (attempt {})",
+                        method,
+                        url,
+                        delay,
+                        retry.attempt()
+                    );
+                    thread::sleep(delay);
+                    continue;
+                }
+                return Err(Error::NetworkError(e));
+            }
+            Ok(ref mut res) if res.status().is_success() => {
+                info!("{} {} {}", res.status().as_u16(), method, url);
+                return json_decode_reader(res.body_mut()).map_err(Error::EncoderError);
+            }
+            Ok(ref mut res) => {
+                info!("{} {} {}", res.status().as_u16(), method, url);
+                if let Some(delay) = retry.after_status(res.status(), res.headers()) {
+                    info!(
+                        "Retrying {} {} in {:?} (attempt {})",
+                        method,
+                        url,
+                        delay,
+                        retry.attempt()
+                    );
+                    thread::sleep(delay);
+                    continue;
+                }
+                let mut buffer = String::new();
+                res.body_mut()
+                    .read_to_string(&mut buffer)
+                    .map_err(|err| Error::IoError(err))?;
+                return Err(ApiError::new(res.status(), &buffer)
+                    .with_request(&method, path)
+                    .into());
+            }
         }
     }
 }
@@ -413,4 +631,11 @@ mod tests {
         assert_send::<SdkmsClientBuilder>();
         assert_sync::<SdkmsClientBuilder>();
     }
+
+    #[test]
+    fn session_refresh_margin() {
+        assert_eq!(refresh_margin(0), 0);
+        assert_eq!(refresh_margin(100), 25);
+        assert_eq!(refresh_margin(600), 60);
+    }
 }
@@ -0,0 +1,285 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Envelope encryption: data is encrypted locally with a data encryption key (DEK), which is generated by SDKMS and
+//! stored next to the data, wrapped with a key encryption key (KEK) that never leaves SDKMS.
+//!
+//! [`EnvelopeEncryptor::seal()`] generates a transient AES-256 key in SDKMS, exports it to encrypt the data locally
+//! with AES-GCM and wraps it with the KEK. The resulting [`Envelope`] describes everything needed to decrypt the data
+//! except the KEK itself, and serializes to JSON with [`Envelope::to_bytes()`].
+//!
+//! [`Envelope::open()`] unwraps the DEK into a transient key in SDKMS and decrypts the data with it, so the DEK is
+//! not exported when opening envelopes. Transient keys are released with [`CryptoProvider::drop_transient_key()`]
+//! once they have been used.
+//!
+//! Every envelope has its own randomly generated DEK. Deriving DEKs from a key in SDKMS, e.g. to share one DEK
+//! between envelopes, is out of scope of this module.
+//!
+//! ```no_run
+//! # use sdkms::{api_model::SobjectDescriptor, envelope::*, SdkmsClient};
+//! # fn main() -> Result<(), sdkms::Error> {
+//! # let client = SdkmsClient::builder().build()?;
+//! let encryptor = EnvelopeEncryptor::new(&client, SobjectDescriptor::Name("kek".to_owned()));
+//! let serialized = encryptor.seal(b"payload", None)?.to_bytes()?;
+//! let plain = Envelope::from_bytes(&serialized)?.open(&client, None)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`EnvelopeEncryptor::seal()`]: ./struct.EnvelopeEncryptor.html#method.seal
+//! [`Envelope`]: ./struct.Envelope.html
+//! [`Envelope::to_bytes()`]: ./struct.Envelope.html#method.to_bytes
+//! [`Envelope::open()`]: ./struct.Envelope.html#method.open
+//! [`CryptoProvider::drop_transient_key()`]: ../trait.CryptoProvider.html#method.drop_transient_key
+
+use crate::api_model::*;
+use crate::client::Result;
+use crate::provider::CryptoProvider;
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::Aes256Gcm;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Version of the serialized envelope format.
+pub const ENVELOPE_VERSION: u32 = 1;
+
+const DEK_SIZE: u32 = 256;
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Data encrypted with a wrapped data encryption key, see the [module documentation](./index.html).
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Envelope {
+    pub version: u32,
+    /// Key id of the key encryption key that wrapped the data encryption key, so that the envelope can still be
+    /// opened after the key is renamed or rotated.
+    pub kek: SobjectDescriptor,
+    /// Algorithm used to wrap the data encryption key.
+    pub wrap_alg: Algorithm,
+    #[serde(default)]
+    pub wrap_mode: Option<CryptMode>,
+    /// The data encryption key, wrapped with `kek`.
+    pub wrapped_key: Blob,
+    #[serde(default)]
+    pub wrap_iv: Option<Blob>,
+    #[serde(default)]
+    pub wrap_tag: Option<Blob>,
+    /// Algorithm, mode and key size used to encrypt the data.
+    pub alg: Algorithm,
+    pub mode: CipherMode,
+    pub key_size: u32,
+    pub iv: Blob,
+    pub tag: Blob,
+    pub cipher: Blob,
+}
+
+impl Envelope {
+    /// Serializes the envelope to JSON.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parses an envelope serialized with [`to_bytes()`](#method.to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let envelope: Envelope = serde_json::from_slice(bytes)?;
+        if envelope.version != ENVELOPE_VERSION {
+            return Err(Error::invalid_input("unsupported envelope version"));
+        }
+        Ok(envelope)
+    }
+
+    /// Unwraps the data encryption key with `provider` and decrypts the data. `ad` must be the authenticated data
+    /// passed to [`EnvelopeEncryptor::seal()`].
+    ///
+    /// The data encryption key is unwrapped into a transient key and is only used for decryption within SDKMS.
+    ///
+    /// [`EnvelopeEncryptor::seal()`]: ./struct.EnvelopeEncryptor.html#method.seal
+    pub fn open<P: CryptoProvider + ?Sized>(
+        &self,
+        provider: &P,
+        ad: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        if (self.alg, self.mode, self.key_size) != (Algorithm::Aes, CipherMode::Gcm, DEK_SIZE) {
+            return Err(Error::invalid_input("unsupported envelope algorithm"));
+        }
+        let dek = provider.unwrap(&UnwrapKeyRequest {
+            key: Some(self.kek.clone()),
+            alg: self.wrap_alg,
+            obj_type: ObjectType::Aes,
+            rsa: None,
+            wrapped_key: self.wrapped_key.clone(),
+            mode: self.wrap_mode.clone(),
+            iv: self.wrap_iv.clone(),
+            ad: None,
+            tag: self.wrap_tag.clone(),
+            name: None,
+            group_id: None,
+            enabled: None,
+            description: None,
+            custom_metadata: None,
+            key_ops: Some(KeyOperations::DECRYPT),
+            transient: Some(true),
+        })?;
+        let dek = dek
+            .transient_key
+            .ok_or_else(|| Error::unexpected_response("unwrapped key is not transient"))?;
+        let resp = provider.decrypt(&DecryptRequest {
+            key: Some(SobjectDescriptor::TransientKey(dek.clone())),
+            alg: Some(self.alg),
+            cipher: self.cipher.clone(),
+            mode: Some(CryptMode::Symmetric(self.mode)),
+            iv: Some(self.iv.clone()),
+            ad: ad.map(|ad| ad.to_vec().into()),
+            tag: Some(self.tag.clone()),
+        });
+        let dropped = provider.drop_transient_key(&dek);
+        let resp = resp?;
+        dropped?;
+        Ok(resp.plain.into())
+    }
+}
+
+/// Seals data into [`Envelope`]s with a key encryption key, see the [module documentation](./index.html).
+///
+/// [`Envelope`]: ./struct.Envelope.html
+#[derive(Debug, Clone)]
+pub struct EnvelopeEncryptor<P> {
+    provider: P,
+    kek: SobjectDescriptor,
+    wrap_alg: Algorithm,
+    wrap_mode: Option<CryptMode>,
+}
+
+impl<P: CryptoProvider> EnvelopeEncryptor<P> {
+    /// Creates an encryptor wrapping data encryption keys with `kek`, which must be an AES key unless configured
+    /// otherwise with [`with_wrapping()`](#method.with_wrapping).
+    pub fn new(provider: P, kek: SobjectDescriptor) -> Self {
+        EnvelopeEncryptor {
+            provider,
+            kek,
+            wrap_alg: Algorithm::Aes,
+            wrap_mode: Some(CryptMode::Symmetric(CipherMode::Gcm)),
+        }
+    }
+
+    /// Sets the algorithm and mode used to wrap data encryption keys. The default is AES-GCM.
+    pub fn with_wrapping(mut self, alg: Algorithm, mode: Option<CryptMode>) -> Self {
+        self.wrap_alg = alg;
+        self.wrap_mode = mode;
+        self
+    }
+
+    /// Encrypts `plain` with a new data encryption key. `ad` is authenticated with the data but not stored in the
+    /// envelope, and must be passed to [`Envelope::open()`].
+    ///
+    /// [`Envelope::open()`]: ./struct.Envelope.html#method.open
+    pub fn seal(&self, plain: &[u8], ad: Option<&[u8]>) -> Result<Envelope> {
+        let kek = self
+            .provider
+            .get_sobject(None, &self.kek)?
+            .kid
+            .map(SobjectDescriptor::Kid)
+            .ok_or_else(|| Error::invalid_input("the key encryption key has no key id"))?;
+        let dek = self
+            .provider
+            .create_sobject(&SobjectRequest {
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(DEK_SIZE),
+                key_ops: Some(KeyOperations::EXPORT),
+                transient: Some(true),
+                ..Default::default()
+            })?
+            .transient_key
+            .ok_or_else(|| Error::unexpected_response("generated key is not transient"))?;
+        let exported = self.export_and_wrap(&kek, SobjectDescriptor::TransientKey(dek.clone()));
+        let dropped = self.provider.drop_transient_key(&dek);
+        let (value, wrapped) = exported?;
+        dropped?;
+
+        let cipher = Aes256Gcm::new_from_slice(&value)
+            .map_err(|_| Error::unexpected_response("invalid data encryption key"))?;
+        let mut iv = [0; IV_LEN];
+        OsRng.fill_bytes(&mut iv);
+        let mut buffer = plain.to_vec();
+        let tag = cipher
+            .encrypt_in_place_detached(
+                GenericArray::from_slice(&iv),
+                ad.unwrap_or_default(),
+                &mut buffer,
+            )
+            .map_err(|_| Error::encoding("encryption failed"))?;
+        Ok(Envelope {
+            version: ENVELOPE_VERSION,
+            kek,
+            wrap_alg: self.wrap_alg,
+            wrap_mode: self.wrap_mode.clone(),
+            wrapped_key: wrapped.wrapped_key,
+            wrap_iv: wrapped.iv,
+            wrap_tag: wrapped.tag,
+            alg: Algorithm::Aes,
+            mode: CipherMode::Gcm,
+            key_size: DEK_SIZE,
+            iv: iv.to_vec().into(),
+            tag: tag.to_vec().into(),
+            cipher: buffer.into(),
+        })
+    }
+
+    fn export_and_wrap(
+        &self,
+        kek: &SobjectDescriptor,
+        dek: SobjectDescriptor,
+    ) -> Result<(Blob, WrapKeyResponse)> {
+        let value = self
+            .provider
+            .export_sobject(&dek)?
+            .value
+            .ok_or_else(|| Error::unexpected_response("exported key has no value"))?;
+        let gcm = matches!(self.wrap_mode, Some(CryptMode::Symmetric(CipherMode::Gcm)));
+        let wrapped = self.provider.wrap(&WrapKeyRequest {
+            key: Some(kek.clone()),
+            subject: Some(dek),
+            kid: None,
+            alg: self.wrap_alg,
+            mode: self.wrap_mode.clone(),
+            iv: None,
+            ad: None,
+            tag_len: if gcm { Some(TAG_LEN * 8) } else { None },
+        })?;
+        Ok((value, wrapped))
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod tests {
+    use super::*;
+    use crate::local::LocalBackend;
+
+    #[test]
+    fn seal_open() {
+        let backend = LocalBackend::new();
+        let kek = backend
+            .create_sobject(&SobjectRequest {
+                name: Some("kek".to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                ..Default::default()
+            })
+            .unwrap();
+        let envelope = EnvelopeEncryptor::new(&backend, SobjectDescriptor::Name("kek".to_owned()))
+            .seal(b"payload", Some(b"header"))
+            .unwrap();
+        assert_eq!(envelope.kek, SobjectDescriptor::Kid(kek.kid.unwrap()));
+        let envelope = Envelope::from_bytes(&envelope.to_bytes().unwrap()).unwrap();
+        assert_eq!(
+            envelope.open(&backend, Some(b"header")).unwrap(),
+            b"payload"
+        );
+        assert!(envelope.open(&backend, None).is_err());
+        assert_eq!(backend.transient_key_count(), 0);
+    }
+}
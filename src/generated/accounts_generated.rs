@@ -461,6 +461,17 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_accounts(
+        &self,
+        query_params: Option<&GetAccountParams>,
+    ) -> Result<Vec<Account>> {
+        self.execute::<OperationListAccounts>(&(), (), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetAccount;
 #[allow(unused)]
 impl Operation for OperationGetAccount {
@@ -490,6 +501,18 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_account(
+        &self,
+        id: &Uuid,
+        query_params: Option<&GetAccountParams>,
+    ) -> Result<Account> {
+        self.execute::<OperationGetAccount>(&(), (id,), query_params)
+            .await
+    }
+}
+
 pub struct OperationCreateAccount;
 #[allow(unused)]
 impl Operation for OperationCreateAccount {
@@ -519,6 +542,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_account(&self, req: &AccountRequest) -> Result<Account> {
+        self.execute::<OperationCreateAccount>(req, (), None).await
+    }
+    pub async fn request_approval_to_create_account(
+        &self,
+        req: &AccountRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationCreateAccount>> {
+        self.request_approval::<OperationCreateAccount>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationUpdateAccount;
 #[allow(unused)]
 impl Operation for OperationUpdateAccount {
@@ -549,6 +587,23 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn update_account(&self, id: &Uuid, req: &AccountRequest) -> Result<Account> {
+        self.execute::<OperationUpdateAccount>(req, (id,), None)
+            .await
+    }
+    pub async fn request_approval_to_update_account(
+        &self,
+        id: &Uuid,
+        req: &AccountRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationUpdateAccount>> {
+        self.request_approval::<OperationUpdateAccount>(req, (id,), None, description)
+            .await
+    }
+}
+
 pub struct OperationDeleteAccount;
 #[allow(unused)]
 impl Operation for OperationDeleteAccount {
@@ -574,6 +629,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_account(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeleteAccount>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationAccountUsage;
 #[allow(unused)]
 impl Operation for OperationAccountUsage {
@@ -602,3 +665,15 @@ impl SdkmsClient {
         self.execute::<OperationAccountUsage>(&(), (id,), query_params)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn account_usage(
+        &self,
+        id: &Uuid,
+        query_params: Option<&CountParams>,
+    ) -> Result<GetUsageResponse> {
+        self.execute::<OperationAccountUsage>(&(), (id,), query_params)
+            .await
+    }
+}
@@ -144,6 +144,17 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_approval_requests(
+        &self,
+        query_params: Option<&ListApprovalRequestsParams>,
+    ) -> Result<Vec<ApprovalRequest>> {
+        self.execute::<OperationListApprovalRequests>(&(), (), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetApprovalRequest;
 #[allow(unused)]
 impl Operation for OperationGetApprovalRequest {
@@ -169,6 +180,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_approval_request(&self, id: &Uuid) -> Result<ApprovalRequest> {
+        self.execute::<OperationGetApprovalRequest>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationCreateApprovalRequest;
 #[allow(unused)]
 impl Operation for OperationCreateApprovalRequest {
@@ -191,6 +210,17 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_approval_request(
+        &self,
+        req: &ApprovalRequestRequest,
+    ) -> Result<ApprovalRequest> {
+        self.execute::<OperationCreateApprovalRequest>(req, (), None)
+            .await
+    }
+}
+
 pub struct OperationApproveRequest;
 #[allow(unused)]
 impl Operation for OperationApproveRequest {
@@ -213,6 +243,18 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn approve_request(
+        &self,
+        id: &Uuid,
+        req: &ApproveRequest,
+    ) -> Result<ApprovalRequest> {
+        self.execute::<OperationApproveRequest>(req, (id,), None)
+            .await
+    }
+}
+
 pub struct OperationDenyRequest;
 #[allow(unused)]
 impl Operation for OperationDenyRequest {
@@ -238,6 +280,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn deny_request(&self, id: &Uuid) -> Result<ApprovalRequest> {
+        self.execute::<OperationDenyRequest>(&(), (id,), None).await
+    }
+}
+
 pub struct OperationGetApprovalRequestResult;
 #[allow(unused)]
 impl Operation for OperationGetApprovalRequestResult {
@@ -252,6 +301,9 @@ impl Operation for OperationGetApprovalRequestResult {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/sys/v1/approval_requests/{id}/result", id = p.0)
     }
+    fn is_idempotent() -> bool {
+        true
+    }
     fn to_body(body: &Self::Body) -> Option<serde_json::Value> {
         None
     }
@@ -263,6 +315,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_approval_request_result(&self, id: &Uuid) -> Result<ApprovableResult> {
+        self.execute::<OperationGetApprovalRequestResult>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationDeleteApprovalRequest;
 #[allow(unused)]
 impl Operation for OperationDeleteApprovalRequest {
@@ -287,3 +347,11 @@ impl SdkmsClient {
         self.execute::<OperationDeleteApprovalRequest>(&(), (id,), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_approval_request(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeleteApprovalRequest>(&(), (id,), None)
+            .await
+    }
+}
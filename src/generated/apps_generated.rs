@@ -219,6 +219,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_apps(&self, query_params: Option<&ListAppsParams>) -> Result<Vec<App>> {
+        self.execute::<OperationListApps>(&(), (), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetApp;
 #[allow(unused)]
 impl Operation for OperationGetApp {
@@ -244,6 +252,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_app(&self, id: &Uuid, query_params: Option<&GetAppParams>) -> Result<App> {
+        self.execute::<OperationGetApp>(&(), (id,), query_params)
+            .await
+    }
+}
+
 pub struct OperationCreateApp;
 #[allow(unused)]
 impl Operation for OperationCreateApp {
@@ -266,6 +282,18 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_app(
+        &self,
+        query_params: Option<&GetAppParams>,
+        req: &AppRequest,
+    ) -> Result<App> {
+        self.execute::<OperationCreateApp>(req, (), query_params)
+            .await
+    }
+}
+
 pub struct OperationUpdateApp;
 #[allow(unused)]
 impl Operation for OperationUpdateApp {
@@ -293,6 +321,19 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn update_app(
+        &self,
+        id: &Uuid,
+        query_params: Option<&GetAppParams>,
+        req: &AppRequest,
+    ) -> Result<App> {
+        self.execute::<OperationUpdateApp>(req, (id,), query_params)
+            .await
+    }
+}
+
 pub struct OperationDeleteApp;
 #[allow(unused)]
 impl Operation for OperationDeleteApp {
@@ -318,6 +359,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_app(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeleteApp>(&(), (id,), None).await
+    }
+}
+
 pub struct OperationResetAppSecret;
 #[allow(unused)]
 impl Operation for OperationResetAppSecret {
@@ -349,6 +397,19 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn reset_app_secret(
+        &self,
+        id: &Uuid,
+        query_params: Option<&GetAppParams>,
+        req: &AppResetSecretRequest,
+    ) -> Result<App> {
+        self.execute::<OperationResetAppSecret>(req, (id,), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetAppCredential;
 #[allow(unused)]
 impl Operation for OperationGetAppCredential {
@@ -380,3 +441,19 @@ impl SdkmsClient {
         self.request_approval::<OperationGetAppCredential>(&(), (id,), None, description)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_app_credential(&self, id: &Uuid) -> Result<AppCredentialResponse> {
+        self.execute::<OperationGetAppCredential>(&(), (id,), None)
+            .await
+    }
+    pub async fn request_approval_to_get_app_credential(
+        &self,
+        id: &Uuid,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationGetAppCredential>> {
+        self.request_approval::<OperationGetAppCredential>(&(), (id,), None, description)
+            .await
+    }
+}
@@ -486,6 +486,9 @@ impl Operation for OperationEncrypt {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/encrypt")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -501,6 +504,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn encrypt(&self, req: &EncryptRequest) -> Result<EncryptResponse> {
+        self.execute::<OperationEncrypt>(req, (), None).await
+    }
+    pub async fn request_approval_to_encrypt(
+        &self,
+        req: &EncryptRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationEncrypt>> {
+        self.request_approval::<OperationEncrypt>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationEncryptInit;
 #[allow(unused)]
 impl Operation for OperationEncryptInit {
@@ -523,6 +541,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn encrypt_init(&self, req: &EncryptInitRequest) -> Result<EncryptInitResponse> {
+        self.execute::<OperationEncryptInit>(req, (), None).await
+    }
+}
+
 pub struct OperationEncryptUpdate;
 #[allow(unused)]
 impl Operation for OperationEncryptUpdate {
@@ -545,6 +570,16 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn encrypt_update(
+        &self,
+        req: &EncryptUpdateRequest,
+    ) -> Result<EncryptUpdateResponse> {
+        self.execute::<OperationEncryptUpdate>(req, (), None).await
+    }
+}
+
 pub struct OperationEncryptFinal;
 #[allow(unused)]
 impl Operation for OperationEncryptFinal {
@@ -567,6 +602,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn encrypt_final(&self, req: &EncryptFinalRequest) -> Result<EncryptFinalResponse> {
+        self.execute::<OperationEncryptFinal>(req, (), None).await
+    }
+}
+
 pub struct OperationDecrypt;
 #[allow(unused)]
 impl Operation for OperationDecrypt {
@@ -581,6 +623,9 @@ impl Operation for OperationDecrypt {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/decrypt")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -596,6 +641,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn decrypt(&self, req: &DecryptRequest) -> Result<DecryptResponse> {
+        self.execute::<OperationDecrypt>(req, (), None).await
+    }
+    pub async fn request_approval_to_decrypt(
+        &self,
+        req: &DecryptRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationDecrypt>> {
+        self.request_approval::<OperationDecrypt>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationDecryptInit;
 #[allow(unused)]
 impl Operation for OperationDecryptInit {
@@ -618,6 +678,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn decrypt_init(&self, req: &DecryptInitRequest) -> Result<DecryptInitResponse> {
+        self.execute::<OperationDecryptInit>(req, (), None).await
+    }
+}
+
 pub struct OperationDecryptUpdate;
 #[allow(unused)]
 impl Operation for OperationDecryptUpdate {
@@ -640,6 +707,16 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn decrypt_update(
+        &self,
+        req: &DecryptUpdateRequest,
+    ) -> Result<DecryptUpdateResponse> {
+        self.execute::<OperationDecryptUpdate>(req, (), None).await
+    }
+}
+
 pub struct OperationDecryptFinal;
 #[allow(unused)]
 impl Operation for OperationDecryptFinal {
@@ -662,6 +739,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn decrypt_final(&self, req: &DecryptFinalRequest) -> Result<DecryptFinalResponse> {
+        self.execute::<OperationDecryptFinal>(req, (), None).await
+    }
+}
+
 pub struct OperationSign;
 #[allow(unused)]
 impl Operation for OperationSign {
@@ -676,6 +760,9 @@ impl Operation for OperationSign {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/sign")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -691,6 +778,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn sign(&self, req: &SignRequest) -> Result<SignResponse> {
+        self.execute::<OperationSign>(req, (), None).await
+    }
+    pub async fn request_approval_to_sign(
+        &self,
+        req: &SignRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationSign>> {
+        self.request_approval::<OperationSign>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationVerify;
 #[allow(unused)]
 impl Operation for OperationVerify {
@@ -705,6 +807,9 @@ impl Operation for OperationVerify {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/verify")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -713,6 +818,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn verify(&self, req: &VerifyRequest) -> Result<VerifyResponse> {
+        self.execute::<OperationVerify>(req, (), None).await
+    }
+}
+
 pub struct OperationWrap;
 #[allow(unused)]
 impl Operation for OperationWrap {
@@ -742,6 +854,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn wrap(&self, req: &WrapKeyRequest) -> Result<WrapKeyResponse> {
+        self.execute::<OperationWrap>(req, (), None).await
+    }
+    pub async fn request_approval_to_wrap(
+        &self,
+        req: &WrapKeyRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationWrap>> {
+        self.request_approval::<OperationWrap>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationUnwrap;
 #[allow(unused)]
 impl Operation for OperationUnwrap {
@@ -771,6 +898,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn unwrap(&self, req: &UnwrapKeyRequest) -> Result<Sobject> {
+        self.execute::<OperationUnwrap>(req, (), None).await
+    }
+    pub async fn request_approval_to_unwrap(
+        &self,
+        req: &UnwrapKeyRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationUnwrap>> {
+        self.request_approval::<OperationUnwrap>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationMac;
 #[allow(unused)]
 impl Operation for OperationMac {
@@ -785,6 +927,9 @@ impl Operation for OperationMac {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/mac")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -800,6 +945,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn mac(&self, req: &MacRequest) -> Result<MacResponse> {
+        self.execute::<OperationMac>(req, (), None).await
+    }
+    pub async fn request_approval_to_mac(
+        &self,
+        req: &MacRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationMac>> {
+        self.request_approval::<OperationMac>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationMacVerify;
 #[allow(unused)]
 impl Operation for OperationMacVerify {
@@ -814,6 +974,9 @@ impl Operation for OperationMacVerify {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/macverify")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -822,6 +985,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn mac_verify(&self, req: &VerifyMacRequest) -> Result<VerifyResponse> {
+        self.execute::<OperationMacVerify>(req, (), None).await
+    }
+}
+
 pub struct OperationDerive;
 #[allow(unused)]
 impl Operation for OperationDerive {
@@ -851,6 +1021,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn derive(&self, req: &DeriveKeyRequest) -> Result<Sobject> {
+        self.execute::<OperationDerive>(req, (), None).await
+    }
+    pub async fn request_approval_to_derive(
+        &self,
+        req: &DeriveKeyRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationDerive>> {
+        self.request_approval::<OperationDerive>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationAgree;
 #[allow(unused)]
 impl Operation for OperationAgree {
@@ -880,6 +1065,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn agree(&self, req: &AgreeKeyRequest) -> Result<Sobject> {
+        self.execute::<OperationAgree>(req, (), None).await
+    }
+    pub async fn request_approval_to_agree(
+        &self,
+        req: &AgreeKeyRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationAgree>> {
+        self.request_approval::<OperationAgree>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationCreateDigest;
 #[allow(unused)]
 impl Operation for OperationCreateDigest {
@@ -894,6 +1094,9 @@ impl Operation for OperationCreateDigest {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/digest")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -901,3 +1104,10 @@ impl SdkmsClient {
         self.execute::<OperationCreateDigest>(req, (), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_digest(&self, req: &DigestRequest) -> Result<DigestResponse> {
+        self.execute::<OperationCreateDigest>(req, (), None).await
+    }
+}
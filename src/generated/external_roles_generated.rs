@@ -84,6 +84,17 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_external_roles(
+        &self,
+        query_params: Option<&ListExternalRolesParams>,
+    ) -> Result<Vec<ExternalRole>> {
+        self.execute::<OperationListExternalRoles>(&(), (), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetExternalRole;
 #[allow(unused)]
 impl Operation for OperationGetExternalRole {
@@ -109,6 +120,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_external_role(&self, id: &Uuid) -> Result<ExternalRole> {
+        self.execute::<OperationGetExternalRole>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationCreateExternalRole;
 #[allow(unused)]
 impl Operation for OperationCreateExternalRole {
@@ -131,6 +150,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_external_role(&self, req: &ExternalRoleRequest) -> Result<ExternalRole> {
+        self.execute::<OperationCreateExternalRole>(req, (), None)
+            .await
+    }
+}
+
 pub struct OperationSyncExternalRole;
 #[allow(unused)]
 impl Operation for OperationSyncExternalRole {
@@ -156,6 +183,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn sync_external_role(&self, id: &Uuid) -> Result<ExternalRole> {
+        self.execute::<OperationSyncExternalRole>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationUpdateExternalRole;
 #[allow(unused)]
 impl Operation for OperationUpdateExternalRole {
@@ -182,6 +217,18 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn update_external_role(
+        &self,
+        id: &Uuid,
+        req: &ExternalRoleRequest,
+    ) -> Result<ExternalRole> {
+        self.execute::<OperationUpdateExternalRole>(req, (id,), None)
+            .await
+    }
+}
+
 pub struct OperationDeleteExternalRole;
 #[allow(unused)]
 impl Operation for OperationDeleteExternalRole {
@@ -206,3 +253,11 @@ impl SdkmsClient {
         self.execute::<OperationDeleteExternalRole>(&(), (id,), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_external_role(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeleteExternalRole>(&(), (id,), None)
+            .await
+    }
+}
@@ -55,6 +55,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_groups(&self) -> Result<Vec<Group>> {
+        self.execute::<OperationListGroups>(&(), (), None).await
+    }
+}
+
 pub struct OperationGetGroup;
 #[allow(unused)]
 impl Operation for OperationGetGroup {
@@ -80,6 +87,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_group(&self, id: &Uuid) -> Result<Group> {
+        self.execute::<OperationGetGroup>(&(), (id,), None).await
+    }
+}
+
 pub struct OperationCreateGroup;
 #[allow(unused)]
 impl Operation for OperationCreateGroup {
@@ -102,6 +116,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_group(&self, req: &GroupRequest) -> Result<Group> {
+        self.execute::<OperationCreateGroup>(req, (), None).await
+    }
+}
+
 pub struct OperationUpdateGroup;
 #[allow(unused)]
 impl Operation for OperationUpdateGroup {
@@ -132,6 +153,22 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn update_group(&self, id: &Uuid, req: &GroupRequest) -> Result<Group> {
+        self.execute::<OperationUpdateGroup>(req, (id,), None).await
+    }
+    pub async fn request_approval_to_update_group(
+        &self,
+        id: &Uuid,
+        req: &GroupRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationUpdateGroup>> {
+        self.request_approval::<OperationUpdateGroup>(req, (id,), None, description)
+            .await
+    }
+}
+
 pub struct OperationDeleteGroup;
 #[allow(unused)]
 impl Operation for OperationDeleteGroup {
@@ -156,3 +193,10 @@ impl SdkmsClient {
         self.execute::<OperationDeleteGroup>(&(), (id,), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_group(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeleteGroup>(&(), (id,), None).await
+    }
+}
@@ -195,6 +195,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_sobject(&self, req: &SobjectRequest) -> Result<Sobject> {
+        self.execute::<OperationCreateSobject>(req, (), None).await
+    }
+}
+
 pub struct OperationImportSobject;
 #[allow(unused)]
 impl Operation for OperationImportSobject {
@@ -209,6 +216,9 @@ impl Operation for OperationImportSobject {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/keys")
     }
+    fn is_idempotent() -> bool {
+        false
+    }
 }
 
 impl SdkmsClient {
@@ -217,6 +227,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn import_sobject(&self, req: &SobjectRequest) -> Result<Sobject> {
+        self.execute::<OperationImportSobject>(req, (), None).await
+    }
+}
+
 pub struct OperationUpdateSobject;
 #[allow(unused)]
 impl Operation for OperationUpdateSobject {
@@ -247,6 +264,23 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn update_sobject(&self, id: &Uuid, req: &SobjectRequest) -> Result<Sobject> {
+        self.execute::<OperationUpdateSobject>(req, (id,), None)
+            .await
+    }
+    pub async fn request_approval_to_update_sobject(
+        &self,
+        id: &Uuid,
+        req: &SobjectRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationUpdateSobject>> {
+        self.request_approval::<OperationUpdateSobject>(req, (id,), None, description)
+            .await
+    }
+}
+
 pub struct OperationDeleteSobject;
 #[allow(unused)]
 impl Operation for OperationDeleteSobject {
@@ -279,6 +313,22 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_sobject(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeleteSobject>(&(), (id,), None)
+            .await
+    }
+    pub async fn request_approval_to_delete_sobject(
+        &self,
+        id: &Uuid,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationDeleteSobject>> {
+        self.request_approval::<OperationDeleteSobject>(&(), (id,), None, description)
+            .await
+    }
+}
+
 pub struct OperationListSobjects;
 #[allow(unused)]
 impl Operation for OperationListSobjects {
@@ -304,6 +354,17 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_sobjects(
+        &self,
+        query_params: Option<&ListSobjectsParams>,
+    ) -> Result<Vec<Sobject>> {
+        self.execute::<OperationListSobjects>(&(), (), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetSobject;
 #[allow(unused)]
 impl Operation for OperationGetSobject {
@@ -318,6 +379,9 @@ impl Operation for OperationGetSobject {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/keys/info?{q}", q = q.encode())
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -330,6 +394,18 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_sobject(
+        &self,
+        query_params: Option<&GetSobjectParams>,
+        req: &SobjectDescriptor,
+    ) -> Result<Sobject> {
+        self.execute::<OperationGetSobject>(req, (), query_params)
+            .await
+    }
+}
+
 pub struct OperationRemovePrivate;
 #[allow(unused)]
 impl Operation for OperationRemovePrivate {
@@ -355,6 +431,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn remove_private(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationRemovePrivate>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationExportSobject;
 #[allow(unused)]
 impl Operation for OperationExportSobject {
@@ -369,6 +453,9 @@ impl Operation for OperationExportSobject {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/keys/export")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -384,6 +471,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn export_sobject(&self, req: &SobjectDescriptor) -> Result<Sobject> {
+        self.execute::<OperationExportSobject>(req, (), None).await
+    }
+    pub async fn request_approval_to_export_sobject(
+        &self,
+        req: &SobjectDescriptor,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationExportSobject>> {
+        self.request_approval::<OperationExportSobject>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationDigestSobject;
 #[allow(unused)]
 impl Operation for OperationDigestSobject {
@@ -398,6 +500,9 @@ impl Operation for OperationDigestSobject {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/keys/digest")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -406,6 +511,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn digest_sobject(&self, req: &ObjectDigestRequest) -> Result<ObjectDigestResponse> {
+        self.execute::<OperationDigestSobject>(req, (), None).await
+    }
+}
+
 pub struct OperationPersistTransientKey;
 #[allow(unused)]
 impl Operation for OperationPersistTransientKey {
@@ -428,6 +540,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn persist_transient_key(&self, req: &PersistTransientKeyRequest) -> Result<Sobject> {
+        self.execute::<OperationPersistTransientKey>(req, (), None)
+            .await
+    }
+}
+
 pub struct OperationRotateSobject;
 #[allow(unused)]
 impl Operation for OperationRotateSobject {
@@ -450,6 +570,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn rotate_sobject(&self, req: &SobjectRequest) -> Result<Sobject> {
+        self.execute::<OperationRotateSobject>(req, (), None).await
+    }
+}
+
 pub struct OperationActivateSobject;
 #[allow(unused)]
 impl Operation for OperationActivateSobject {
@@ -475,6 +602,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn activate_sobject(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationActivateSobject>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationRevokeSobject;
 #[allow(unused)]
 impl Operation for OperationRevokeSobject {
@@ -497,6 +632,120 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn revoke_sobject(&self, id: &Uuid, req: &RevocationReason) -> Result<()> {
+        self.execute::<OperationRevokeSobject>(req, (id,), None)
+            .await
+    }
+}
+
+pub struct OperationBatchEncrypt;
+#[allow(unused)]
+impl Operation for OperationBatchEncrypt {
+    type PathParams = ();
+    type QueryParams = ();
+    type Body = Vec<BatchEncryptRequestItem>;
+    type Output = Vec<BatchResponseItem<EncryptResponse>>;
+
+    fn method() -> Method {
+        Method::POST
+    }
+    fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
+        format!("/crypto/v1/keys/batch/encrypt")
+    }
+    fn is_idempotent() -> bool {
+        true
+    }
+}
+
+impl SdkmsClient {
+    pub fn batch_encrypt(
+        &self,
+        req: &Vec<BatchEncryptRequestItem>,
+    ) -> Result<Vec<BatchResponseItem<EncryptResponse>>> {
+        self.execute::<OperationBatchEncrypt>(req, (), None)
+    }
+    pub fn request_approval_to_batch_encrypt(
+        &self,
+        req: &Vec<BatchEncryptRequestItem>,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationBatchEncrypt>> {
+        self.request_approval::<OperationBatchEncrypt>(req, (), None, description)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn batch_encrypt(
+        &self,
+        req: &Vec<BatchEncryptRequestItem>,
+    ) -> Result<Vec<BatchResponseItem<EncryptResponse>>> {
+        self.execute::<OperationBatchEncrypt>(req, (), None).await
+    }
+    pub async fn request_approval_to_batch_encrypt(
+        &self,
+        req: &Vec<BatchEncryptRequestItem>,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationBatchEncrypt>> {
+        self.request_approval::<OperationBatchEncrypt>(req, (), None, description)
+            .await
+    }
+}
+
+pub struct OperationBatchDecrypt;
+#[allow(unused)]
+impl Operation for OperationBatchDecrypt {
+    type PathParams = ();
+    type QueryParams = ();
+    type Body = Vec<BatchDecryptRequestItem>;
+    type Output = Vec<BatchResponseItem<DecryptResponse>>;
+
+    fn method() -> Method {
+        Method::POST
+    }
+    fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
+        format!("/crypto/v1/keys/batch/decrypt")
+    }
+    fn is_idempotent() -> bool {
+        true
+    }
+}
+
+impl SdkmsClient {
+    pub fn batch_decrypt(
+        &self,
+        req: &Vec<BatchDecryptRequestItem>,
+    ) -> Result<Vec<BatchResponseItem<DecryptResponse>>> {
+        self.execute::<OperationBatchDecrypt>(req, (), None)
+    }
+    pub fn request_approval_to_batch_decrypt(
+        &self,
+        req: &Vec<BatchDecryptRequestItem>,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationBatchDecrypt>> {
+        self.request_approval::<OperationBatchDecrypt>(req, (), None, description)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn batch_decrypt(
+        &self,
+        req: &Vec<BatchDecryptRequestItem>,
+    ) -> Result<Vec<BatchResponseItem<DecryptResponse>>> {
+        self.execute::<OperationBatchDecrypt>(req, (), None).await
+    }
+    pub async fn request_approval_to_batch_decrypt(
+        &self,
+        req: &Vec<BatchDecryptRequestItem>,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationBatchDecrypt>> {
+        self.request_approval::<OperationBatchDecrypt>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationBatchSign;
 #[allow(unused)]
 impl Operation for OperationBatchSign {
@@ -511,6 +760,9 @@ impl Operation for OperationBatchSign {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/keys/batch/sign")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -529,6 +781,24 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn batch_sign(
+        &self,
+        req: &Vec<SignRequest>,
+    ) -> Result<Vec<BatchResponseItem<SignResponse>>> {
+        self.execute::<OperationBatchSign>(req, (), None).await
+    }
+    pub async fn request_approval_to_batch_sign(
+        &self,
+        req: &Vec<SignRequest>,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationBatchSign>> {
+        self.request_approval::<OperationBatchSign>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationBatchVerify;
 #[allow(unused)]
 impl Operation for OperationBatchVerify {
@@ -543,6 +813,9 @@ impl Operation for OperationBatchVerify {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/crypto/v1/keys/batch/verify")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
 }
 
 impl SdkmsClient {
@@ -553,3 +826,13 @@ impl SdkmsClient {
         self.execute::<OperationBatchVerify>(req, (), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn batch_verify(
+        &self,
+        req: &Vec<VerifyRequest>,
+    ) -> Result<Vec<BatchResponseItem<VerifyResponse>>> {
+        self.execute::<OperationBatchVerify>(req, (), None).await
+    }
+}
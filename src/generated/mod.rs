@@ -5,6 +5,8 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::api_model::*;
+#[cfg(feature = "async")]
+use crate::async_client::AsyncSdkmsClient;
 use crate::client::{PendingApproval, Result, SdkmsClient};
 use crate::operations::*;
 use simple_hyper_client::Method;
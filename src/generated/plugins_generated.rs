@@ -168,6 +168,17 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_plugins(
+        &self,
+        query_params: Option<&ListPluginsParams>,
+    ) -> Result<Vec<Plugin>> {
+        self.execute::<OperationListPlugins>(&(), (), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetPlugin;
 #[allow(unused)]
 impl Operation for OperationGetPlugin {
@@ -193,6 +204,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_plugin(&self, id: &Uuid) -> Result<Plugin> {
+        self.execute::<OperationGetPlugin>(&(), (id,), None).await
+    }
+}
+
 pub struct OperationCreatePlugin;
 #[allow(unused)]
 impl Operation for OperationCreatePlugin {
@@ -222,6 +240,21 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn create_plugin(&self, req: &PluginRequest) -> Result<Plugin> {
+        self.execute::<OperationCreatePlugin>(req, (), None).await
+    }
+    pub async fn request_approval_to_create_plugin(
+        &self,
+        req: &PluginRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationCreatePlugin>> {
+        self.request_approval::<OperationCreatePlugin>(req, (), None, description)
+            .await
+    }
+}
+
 pub struct OperationUpdatePlugin;
 #[allow(unused)]
 impl Operation for OperationUpdatePlugin {
@@ -252,6 +285,23 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn update_plugin(&self, id: &Uuid, req: &PluginRequest) -> Result<Plugin> {
+        self.execute::<OperationUpdatePlugin>(req, (id,), None)
+            .await
+    }
+    pub async fn request_approval_to_update_plugin(
+        &self,
+        id: &Uuid,
+        req: &PluginRequest,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationUpdatePlugin>> {
+        self.request_approval::<OperationUpdatePlugin>(req, (id,), None, description)
+            .await
+    }
+}
+
 pub struct OperationDeletePlugin;
 #[allow(unused)]
 impl Operation for OperationDeletePlugin {
@@ -277,6 +327,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_plugin(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeletePlugin>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationInvokePlugin;
 #[allow(unused)]
 impl Operation for OperationInvokePlugin {
@@ -306,3 +364,20 @@ impl SdkmsClient {
         self.request_approval::<OperationInvokePlugin>(req, (id,), None, description)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn invoke_plugin(&self, id: &Uuid, req: &serde_json::Value) -> Result<PluginOutput> {
+        self.execute::<OperationInvokePlugin>(req, (id,), None)
+            .await
+    }
+    pub async fn request_approval_to_invoke_plugin(
+        &self,
+        id: &Uuid,
+        req: &serde_json::Value,
+        description: Option<String>,
+    ) -> Result<PendingApproval<OperationInvokePlugin>> {
+        self.request_approval::<OperationInvokePlugin>(req, (id,), None, description)
+            .await
+    }
+}
@@ -64,6 +64,9 @@ impl Operation for OperationRefresh {
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String {
         format!("/sys/v1/session/refresh")
     }
+    fn is_idempotent() -> bool {
+        true
+    }
     fn to_body(body: &Self::Body) -> Option<serde_json::Value> {
         None
     }
@@ -75,6 +78,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn refresh(&self) -> Result<()> {
+        self.execute::<OperationRefresh>(&(), (), None).await
+    }
+}
+
 pub struct OperationSelectAccount;
 #[allow(unused)]
 impl Operation for OperationSelectAccount {
@@ -97,6 +107,16 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn select_account(
+        &self,
+        req: &SelectAccountRequest,
+    ) -> Result<SelectAccountResponse> {
+        self.execute::<OperationSelectAccount>(req, (), None).await
+    }
+}
+
 pub struct OperationU2fAuth;
 #[allow(unused)]
 impl Operation for OperationU2fAuth {
@@ -119,6 +139,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn u2f_auth(&self, req: &U2fAuthRequest) -> Result<()> {
+        self.execute::<OperationU2fAuth>(req, (), None).await
+    }
+}
+
 pub struct OperationRecoveryCodeAuth;
 #[allow(unused)]
 impl Operation for OperationRecoveryCodeAuth {
@@ -141,6 +168,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn recovery_code_auth(&self, req: &RecoveryCodeAuthRequest) -> Result<()> {
+        self.execute::<OperationRecoveryCodeAuth>(req, (), None)
+            .await
+    }
+}
+
 pub struct OperationConfig2faAuth;
 #[allow(unused)]
 impl Operation for OperationConfig2faAuth {
@@ -163,6 +198,16 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn config_2fa_auth(
+        &self,
+        req: &Config2faAuthRequest,
+    ) -> Result<Config2faAuthResponse> {
+        self.execute::<OperationConfig2faAuth>(req, (), None).await
+    }
+}
+
 pub struct OperationConfig2faTerminate;
 #[allow(unused)]
 impl Operation for OperationConfig2faTerminate {
@@ -188,6 +233,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn config_2fa_terminate(&self) -> Result<()> {
+        self.execute::<OperationConfig2faTerminate>(&(), (), None)
+            .await
+    }
+}
+
 pub struct OperationU2fNewChallenge;
 #[allow(unused)]
 impl Operation for OperationU2fNewChallenge {
@@ -212,3 +265,11 @@ impl SdkmsClient {
         self.execute::<OperationU2fNewChallenge>(&(), (), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn u2f_new_challenge(&self) -> Result<MfaChallengeResponse> {
+        self.execute::<OperationU2fNewChallenge>(&(), (), None)
+            .await
+    }
+}
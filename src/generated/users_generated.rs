@@ -227,6 +227,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn signup_user(&self, req: &SignupRequest) -> Result<User> {
+        self.execute::<OperationSignupUser>(req, (), None).await
+    }
+}
+
 pub struct OperationListUsers;
 #[allow(unused)]
 impl Operation for OperationListUsers {
@@ -252,6 +259,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn list_users(&self, query_params: Option<&ListUsersParams>) -> Result<Vec<User>> {
+        self.execute::<OperationListUsers>(&(), (), query_params)
+            .await
+    }
+}
+
 pub struct OperationGetUser;
 #[allow(unused)]
 impl Operation for OperationGetUser {
@@ -277,6 +292,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_user(&self, id: &Uuid) -> Result<User> {
+        self.execute::<OperationGetUser>(&(), (id,), None).await
+    }
+}
+
 pub struct OperationUpdateUser;
 #[allow(unused)]
 impl Operation for OperationUpdateUser {
@@ -299,6 +321,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn update_user(&self, id: &Uuid, req: &UserRequest) -> Result<User> {
+        self.execute::<OperationUpdateUser>(req, (id,), None).await
+    }
+}
+
 pub struct OperationResetPassword;
 #[allow(unused)]
 impl Operation for OperationResetPassword {
@@ -321,6 +350,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn reset_password(&self, id: &Uuid, req: &PasswordResetRequest) -> Result<()> {
+        self.execute::<OperationResetPassword>(req, (id,), None)
+            .await
+    }
+}
+
 pub struct OperationForgotPassword;
 #[allow(unused)]
 impl Operation for OperationForgotPassword {
@@ -343,6 +380,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn forgot_password(&self, req: &ForgotPasswordRequest) -> Result<()> {
+        self.execute::<OperationForgotPassword>(req, (), None).await
+    }
+}
+
 pub struct OperationInviteUser;
 #[allow(unused)]
 impl Operation for OperationInviteUser {
@@ -365,6 +409,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn invite_user(&self, req: &UserRequest) -> Result<User> {
+        self.execute::<OperationInviteUser>(req, (), None).await
+    }
+}
+
 pub struct OperationProcessInvite;
 #[allow(unused)]
 impl Operation for OperationProcessInvite {
@@ -387,6 +438,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn process_invite(&self, req: &ProcessInviteRequest) -> Result<()> {
+        self.execute::<OperationProcessInvite>(req, (), None).await
+    }
+}
+
 pub struct OperationResendInvite;
 #[allow(unused)]
 impl Operation for OperationResendInvite {
@@ -412,6 +470,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn resend_invite(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationResendInvite>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationDeleteUser;
 #[allow(unused)]
 impl Operation for OperationDeleteUser {
@@ -437,6 +503,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_user(&self) -> Result<()> {
+        self.execute::<OperationDeleteUser>(&(), (), None).await
+    }
+}
+
 pub struct OperationChangePassword;
 #[allow(unused)]
 impl Operation for OperationChangePassword {
@@ -459,6 +532,13 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn change_password(&self, req: &PasswordChangeRequest) -> Result<()> {
+        self.execute::<OperationChangePassword>(req, (), None).await
+    }
+}
+
 pub struct OperationGetUserAccounts;
 #[allow(unused)]
 impl Operation for OperationGetUserAccounts {
@@ -484,6 +564,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn get_user_accounts(&self) -> Result<HashMap<Uuid, UserAccountFlags>> {
+        self.execute::<OperationGetUserAccounts>(&(), (), None)
+            .await
+    }
+}
+
 pub struct OperationDeleteUserAccount;
 #[allow(unused)]
 impl Operation for OperationDeleteUserAccount {
@@ -509,6 +597,14 @@ impl SdkmsClient {
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn delete_user_account(&self, id: &Uuid) -> Result<()> {
+        self.execute::<OperationDeleteUserAccount>(&(), (id,), None)
+            .await
+    }
+}
+
 pub struct OperationGenerateRecoveryCodes;
 #[allow(unused)]
 impl Operation for OperationGenerateRecoveryCodes {
@@ -533,3 +629,11 @@ impl SdkmsClient {
         self.execute::<OperationGenerateRecoveryCodes>(&(), (), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn generate_recovery_codes(&self) -> Result<RecoveryCodes> {
+        self.execute::<OperationGenerateRecoveryCodes>(&(), (), None)
+            .await
+    }
+}
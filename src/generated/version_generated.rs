@@ -52,3 +52,10 @@ impl SdkmsClient {
         self.execute::<OperationVersion>(&(), (), None)
     }
 }
+
+#[cfg(feature = "async")]
+impl AsyncSdkmsClient {
+    pub async fn version(&self) -> Result<VersionResponse> {
+        self.execute::<OperationVersion>(&(), (), None).await
+    }
+}
@@ -0,0 +1,405 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Compact JWS ([RFC 7515]) and JWT ([RFC 7519]) tokens signed with SDKMS keys.
+//!
+//! [`JwsSigner`] maps the JOSE `alg` to the corresponding [`SignRequest`] and converts the DER encoded ECDSA
+//! signatures returned by SDKMS to the fixed size `r || s` encoding used by JOSE. [`Jws::verify()`] verifies a token
+//! with an SDKMS key, and [`Jws::verify_with_public_key()`] with a public key fetched elsewhere, e.g. from a JWK Set.
+//!
+//! ```no_run
+//! # use sdkms::{api_model::SobjectDescriptor, jose::*, SdkmsClient};
+//! # fn main() -> Result<(), sdkms::Error> {
+//! # let client = SdkmsClient::builder().build()?;
+//! # let kid = uuid::Uuid::nil();
+//! let key = SobjectDescriptor::Kid(kid);
+//! let claims = serde_json::json!({ "sub": "1234567890", "iat": 1516239022 });
+//! let token = JwsSigner::new(&client, key.clone(), JwsAlgorithm::Es256).sign_claims(&claims)?;
+//!
+//! let jws = Jws::parse(&token)?;
+//! assert!(jws.verify(&client, &key, JwsAlgorithm::Es256)?);
+//! let claims: serde_json::Value = jws.claims()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`JwsSigner`]: ./struct.JwsSigner.html
+//! [`SignRequest`]: ../api_model/struct.SignRequest.html
+//! [`Jws::verify()`]: ./struct.Jws.html#method.verify
+//! [`Jws::verify_with_public_key()`]: ./struct.Jws.html#method.verify_with_public_key
+//! [RFC 7515]: https://tools.ietf.org/html/rfc7515
+//! [RFC 7519]: https://tools.ietf.org/html/rfc7519
+
+use crate::api_model::*;
+use crate::client::Result;
+use crate::provider::CryptoProvider;
+use crate::public_key::PublicKey;
+
+use der::asn1::UintRef;
+use der::{Decode, Encode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+fn b64url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// JWS signature algorithms supported by SDKMS keys.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum JwsAlgorithm {
+    #[serde(rename = "RS256")]
+    Rs256,
+    #[serde(rename = "RS384")]
+    Rs384,
+    #[serde(rename = "RS512")]
+    Rs512,
+    #[serde(rename = "PS256")]
+    Ps256,
+    #[serde(rename = "PS384")]
+    Ps384,
+    #[serde(rename = "PS512")]
+    Ps512,
+    #[serde(rename = "ES256")]
+    Es256,
+    #[serde(rename = "ES384")]
+    Es384,
+    #[serde(rename = "ES512")]
+    Es512,
+    /// Ed25519 signatures.
+    #[serde(rename = "EdDSA")]
+    EdDsa,
+}
+
+impl JwsAlgorithm {
+    pub fn hash_alg(&self) -> DigestAlgorithm {
+        match *self {
+            JwsAlgorithm::Rs256 | JwsAlgorithm::Ps256 | JwsAlgorithm::Es256 => {
+                DigestAlgorithm::Sha256
+            }
+            JwsAlgorithm::Rs384 | JwsAlgorithm::Ps384 | JwsAlgorithm::Es384 => {
+                DigestAlgorithm::Sha384
+            }
+            JwsAlgorithm::Rs512
+            | JwsAlgorithm::Ps512
+            | JwsAlgorithm::Es512
+            | JwsAlgorithm::EdDsa => DigestAlgorithm::Sha512,
+        }
+    }
+
+    /// The signature mode of RSA algorithms.
+    pub fn mode(&self) -> Option<SignatureMode> {
+        match *self {
+            JwsAlgorithm::Rs256 | JwsAlgorithm::Rs384 | JwsAlgorithm::Rs512 => {
+                Some(SignatureMode::Rsa(RsaSignaturePadding::Pkcs1V15 {}))
+            }
+            JwsAlgorithm::Ps256 | JwsAlgorithm::Ps384 | JwsAlgorithm::Ps512 => {
+                Some(SignatureMode::Rsa(RsaSignaturePadding::Pss {
+                    mgf: Mgf::Mgf1 {
+                        hash: self.hash_alg(),
+                    },
+                }))
+            }
+            JwsAlgorithm::Es256
+            | JwsAlgorithm::Es384
+            | JwsAlgorithm::Es512
+            | JwsAlgorithm::EdDsa => None,
+        }
+    }
+
+    /// The curve of EC algorithms.
+    pub fn elliptic_curve(&self) -> Option<EllipticCurve> {
+        match *self {
+            JwsAlgorithm::Es256 => Some(EllipticCurve::NistP256),
+            JwsAlgorithm::Es384 => Some(EllipticCurve::NistP384),
+            JwsAlgorithm::Es512 => Some(EllipticCurve::NistP521),
+            JwsAlgorithm::EdDsa => Some(EllipticCurve::Ed25519),
+            _ => None,
+        }
+    }
+
+    /// Size of the `r` and `s` components of ECDSA signatures.
+    fn ecdsa_size(&self) -> Option<usize> {
+        match *self {
+            JwsAlgorithm::Es256 => Some(32),
+            JwsAlgorithm::Es384 => Some(48),
+            JwsAlgorithm::Es512 => Some(66),
+            _ => None,
+        }
+    }
+
+    /// Whether this algorithm can be used with `key`.
+    pub fn is_compatible(&self, key: &PublicKey) -> bool {
+        match *key {
+            PublicKey::Rsa { .. } => self.mode().is_some(),
+            PublicKey::Ec { curve, .. } | PublicKey::Okp { curve, .. } => {
+                self.elliptic_curve() == Some(curve)
+            }
+        }
+    }
+}
+
+/// The JOSE header of a JWS.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct JwsHeader {
+    pub alg: JwsAlgorithm,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+}
+
+/// Converts a DER encoded ECDSA signature, as returned by SDKMS, to the JOSE encoding: `r` and `s` as big-endian
+/// integers of `size` bytes.
+pub fn ecdsa_der_to_raw(der: &[u8], size: usize) -> Result<Vec<u8>> {
+    let invalid = || Error::invalid_input("invalid ECDSA signature");
+    let components = Vec::<UintRef<'_>>::from_der(der).map_err(|_| invalid())?;
+    if components.len() != 2 {
+        return Err(invalid());
+    }
+    let mut raw = vec![0; 2 * size];
+    for (i, component) in components.iter().enumerate() {
+        let bytes = component.as_bytes();
+        if bytes.len() > size {
+            return Err(invalid());
+        }
+        raw[(i + 1) * size - bytes.len()..(i + 1) * size].copy_from_slice(bytes);
+    }
+    Ok(raw)
+}
+
+/// Converts an ECDSA signature in the JOSE encoding to DER.
+pub fn ecdsa_raw_to_der(raw: &[u8]) -> Result<Vec<u8>> {
+    let invalid = || Error::invalid_input("invalid ECDSA signature");
+    if raw.is_empty() || raw.len() % 2 != 0 {
+        return Err(invalid());
+    }
+    let (r, s) = raw.split_at(raw.len() / 2);
+    let strip = |bytes: &[u8]| -> Vec<u8> {
+        let zeros = bytes.iter().take_while(|b| **b == 0).count();
+        bytes[zeros.min(bytes.len() - 1)..].to_vec()
+    };
+    let (r, s) = (strip(r), strip(s));
+    let components = vec![
+        UintRef::new(&r).map_err(|_| invalid())?,
+        UintRef::new(&s).map_err(|_| invalid())?,
+    ];
+    components.to_der().map_err(|_| invalid())
+}
+
+/// Creates compact JWS tokens signed with an SDKMS key, see the [module documentation](./index.html).
+#[derive(Debug, Clone)]
+pub struct JwsSigner<P> {
+    provider: P,
+    key: SobjectDescriptor,
+    alg: JwsAlgorithm,
+    kid: Option<String>,
+}
+
+impl<P: CryptoProvider> JwsSigner<P> {
+    /// Creates a signer for `key`. If `key` is a key id, it is used as the `kid` of the JOSE header.
+    pub fn new(provider: P, key: SobjectDescriptor, alg: JwsAlgorithm) -> Self {
+        let kid = match key {
+            SobjectDescriptor::Kid(kid) => Some(kid.to_string()),
+            _ => None,
+        };
+        JwsSigner {
+            provider,
+            key,
+            alg,
+            kid,
+        }
+    }
+
+    /// Sets the `kid` of the JOSE header.
+    pub fn with_kid(mut self, kid: Option<String>) -> Self {
+        self.kid = kid;
+        self
+    }
+
+    /// Signs `payload` and returns the compact serialization of the JWS.
+    pub fn sign(&self, payload: &[u8]) -> Result<String> {
+        self.sign_with_type(None, payload)
+    }
+
+    /// Serializes `claims` to JSON and returns a signed JWT.
+    pub fn sign_claims<T: Serialize>(&self, claims: &T) -> Result<String> {
+        self.sign_with_type(Some("JWT"), &serde_json::to_vec(claims)?)
+    }
+
+    fn sign_with_type(&self, typ: Option<&str>, payload: &[u8]) -> Result<String> {
+        let header = JwsHeader {
+            alg: self.alg,
+            typ: typ.map(str::to_owned),
+            kid: self.kid.clone(),
+        };
+        let signing_input = format!(
+            "{}.{}",
+            b64url(&serde_json::to_vec(&header)?),
+            b64url(payload)
+        );
+        let resp = self.provider.sign(&SignRequest {
+            key: Some(self.key.clone()),
+            hash_alg: self.alg.hash_alg(),
+            hash: None,
+            data: Some(signing_input.as_bytes().to_vec().into()),
+            mode: self.alg.mode(),
+            deterministic_signature: None,
+        })?;
+        let signature = match self.alg.ecdsa_size() {
+            Some(size) => ecdsa_der_to_raw(&resp.signature, size)?,
+            None => resp.signature.into(),
+        };
+        Ok(format!("{}.{}", signing_input, b64url(&signature)))
+    }
+}
+
+/// A parsed compact JWS. The signature is not verified by [`parse()`](#method.parse).
+#[derive(Debug, Clone)]
+pub struct Jws {
+    pub header: JwsHeader,
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+    signing_input: String,
+}
+
+impl Jws {
+    pub fn parse(token: &str) -> Result<Self> {
+        let invalid = || Error::invalid_input("invalid compact JWS");
+        let decode = |part: &str| {
+            base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())
+        };
+        let mut parts = token.split('.');
+        let (header, payload, signature) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(header), Some(payload), Some(signature), None) => {
+                    (header, payload, signature)
+                }
+                _ => return Err(invalid()),
+            };
+        Ok(Jws {
+            header: serde_json::from_slice(&decode(header)?)?,
+            payload: decode(payload)?,
+            signature: decode(signature)?,
+            signing_input: token[..header.len() + 1 + payload.len()].to_owned(),
+        })
+    }
+
+    /// Deserializes the payload as JWT claims.
+    pub fn claims<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.payload)?)
+    }
+
+    /// Verifies the signature with an SDKMS key. Returns `false` if the signature is invalid or the token is not
+    /// signed with `alg`.
+    pub fn verify<P: CryptoProvider + ?Sized>(
+        &self,
+        provider: &P,
+        key: &SobjectDescriptor,
+        alg: JwsAlgorithm,
+    ) -> Result<bool> {
+        if self.header.alg != alg {
+            return Ok(false);
+        }
+        let signature = match alg.ecdsa_size() {
+            Some(size) if self.signature.len() != 2 * size => return Ok(false),
+            Some(_) => ecdsa_raw_to_der(&self.signature)?,
+            None => self.signature.clone(),
+        };
+        let resp = provider.verify(&VerifyRequest {
+            key: Some(key.clone()),
+            hash_alg: alg.hash_alg(),
+            hash: None,
+            data: Some(self.signing_input.as_bytes().to_vec().into()),
+            mode: alg.mode(),
+            signature: signature.into(),
+        })?;
+        Ok(resp.result)
+    }
+
+    /// Verifies the signature with a public key fetched outside of SDKMS, e.g. from a JWK Set. The key is imported
+    /// into `provider` as a transient key, which is released afterwards. Returns `false` if the signature is invalid
+    /// or the `alg` of the token can not be used with `key`.
+    pub fn verify_with_public_key<P: CryptoProvider + ?Sized>(
+        &self,
+        provider: &P,
+        key: &PublicKey,
+    ) -> Result<bool> {
+        if !self.header.alg.is_compatible(key) {
+            return Ok(false);
+        }
+        let sobject = provider.import_sobject(&SobjectRequest {
+            key_ops: Some(KeyOperations::VERIFY),
+            transient: Some(true),
+            ..SobjectRequest::from_public_key(key)?
+        })?;
+        let transient_key = sobject
+            .transient_key
+            .ok_or_else(|| Error::invalid_input("imported key is not transient"))?;
+        let verified = self.verify(
+            provider,
+            &SobjectDescriptor::TransientKey(transient_key.clone()),
+            self.header.alg,
+        );
+        let dropped = provider.drop_transient_key(&transient_key);
+        let verified = verified?;
+        dropped?;
+        Ok(verified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ecdsa_signature_encoding() {
+        let mut raw = vec![0; 64];
+        raw[1] = 0x80;
+        raw[32] = 0x01;
+        raw[63] = 0xff;
+        let der = ecdsa_raw_to_der(&raw).unwrap();
+        assert_eq!(ecdsa_der_to_raw(&der, 32).unwrap(), raw);
+        assert!(ecdsa_der_to_raw(&der, 16).is_err());
+    }
+
+    #[cfg(feature = "local")]
+    #[test]
+    fn sign_verify() {
+        use crate::local::LocalBackend;
+
+        let backend = LocalBackend::new();
+        let sobject = backend
+            .create_sobject(&SobjectRequest {
+                name: Some("jwt".to_owned()),
+                obj_type: Some(ObjectType::Ec),
+                elliptic_curve: Some(EllipticCurve::NistP256),
+                key_ops: Some(KeyOperations::SIGN | KeyOperations::VERIFY),
+                ..Default::default()
+            })
+            .unwrap();
+        let key = SobjectDescriptor::Kid(sobject.kid.unwrap());
+        let claims = serde_json::json!({ "sub": "test" });
+        let token = JwsSigner::new(&backend, key.clone(), JwsAlgorithm::Es256)
+            .sign_claims(&claims)
+            .unwrap();
+
+        let jws = Jws::parse(&token).unwrap();
+        assert_eq!(jws.header.typ.as_deref(), Some("JWT"));
+        assert_eq!(jws.signature.len(), 64);
+        assert_eq!(jws.claims::<serde_json::Value>().unwrap(), claims);
+        assert!(jws.verify(&backend, &key, JwsAlgorithm::Es256).unwrap());
+        assert!(!jws.verify(&backend, &key, JwsAlgorithm::Es384).unwrap());
+        let public_key = sobject.public_key().unwrap();
+        assert!(jws.verify_with_public_key(&backend, &public_key).unwrap());
+        assert_eq!(backend.transient_key_count(), 0);
+
+        let mut jws = jws;
+        jws.signature[10] ^= 1;
+        assert!(!jws.verify(&backend, &key, JwsAlgorithm::Es256).unwrap());
+        assert!(!jws.verify_with_public_key(&backend, &public_key).unwrap());
+        assert_eq!(backend.transient_key_count(), 0);
+    }
+}
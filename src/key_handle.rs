@@ -0,0 +1,665 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Handles to security objects and their lifecycle.
+//!
+//! [`KeyHandle`] binds a security object to a client, returned by `SdkmsClient::key()`. The object is fetched on
+//! first use and cached, and operations use its key id, so that a handle created from a name keeps using the same
+//! version of the key after a rotation until it is refreshed. Cryptographic operations pick their algorithm, mode and
+//! digest from the object, and decryption accepts ciphertexts of other versions of the key, see [`KeyVersions`].
+//!
+//! Lifecycle operations refresh the object and check the requested [`Transition`] against its effective state, i.e.
+//! the state taking its activation, deactivation and compromise dates into account, before calling SDKMS:
+//!
+//! | Transition   | Allowed from                   | Operations                            |
+//! |--------------|--------------------------------|---------------------------------------|
+//! | `Activate`   | `PreActive`                    | `activate()`                          |
+//! | `Deactivate` | `PreActive`, `Active`          | `deactivate_at()`, `revoke()`         |
+//! | `Compromise` | any state but `Compromised`    | `mark_compromised()`, `revoke()`      |
+//! | `Destroy`    | any state but `Active`         | `destroy()`                           |
+//!
+//! `revoke()` with a `KeyCompromise` or `CACompromise` reason is a `Compromise` transition. Transitions that are not
+//! allowed fail with [`Error::InvalidInput`] without sending a request.
+//!
+//! [`KeyHandle`]: ./struct.KeyHandle.html
+//! [`KeyVersions`]: ./rotation/struct.KeyVersions.html
+//! [`Transition`]: ./enum.Transition.html
+//! [`Error::InvalidInput`]: ./enum.Error.html#variant.InvalidInput
+
+use crate::api_model::*;
+use crate::ciphertext::{self, cipher_params, Ciphertext};
+use crate::client::{Result, SdkmsClient};
+use crate::reencrypt::WrappedKey;
+use crate::rotation::KeyVersions;
+
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// Default digest and mode to sign with `sobject`: SHA-256 for RSA, with the padding of the first signature policy
+/// of the key (PKCS#1 v1.5 if unconstrained), and the digest matching the curve for EC keys.
+fn signature_params(sobject: &Sobject) -> Result<(DigestAlgorithm, Option<SignatureMode>)> {
+    match (sobject.obj_type, sobject.elliptic_curve) {
+        (ObjectType::Rsa, _) => {
+            let hash_alg = DigestAlgorithm::Sha256;
+            let policy = sobject
+                .rsa
+                .as_ref()
+                .and_then(|rsa| rsa.signature_policy.first())
+                .and_then(|policy| policy.padding);
+            let padding = match policy {
+                Some(RsaSignaturePaddingPolicy::Pss { mgf }) => {
+                    let hash = match mgf {
+                        Some(MgfPolicy::Mgf1 { hash: Some(hash) }) => hash,
+                        _ => hash_alg,
+                    };
+                    RsaSignaturePadding::Pss {
+                        mgf: Mgf::Mgf1 { hash },
+                    }
+                }
+                _ => RsaSignaturePadding::Pkcs1V15 {},
+            };
+            Ok((hash_alg, Some(SignatureMode::Rsa(padding))))
+        }
+        (ObjectType::Ec, Some(EllipticCurve::NistP256))
+        | (ObjectType::Ec, Some(EllipticCurve::SecP256K1)) => Ok((DigestAlgorithm::Sha256, None)),
+        (ObjectType::Ec, Some(EllipticCurve::NistP384)) => Ok((DigestAlgorithm::Sha384, None)),
+        (ObjectType::Ec, Some(EllipticCurve::NistP521))
+        | (ObjectType::Ec, Some(EllipticCurve::Ed25519)) => Ok((DigestAlgorithm::Sha512, None)),
+        (obj_type, curve) => Err(Error::invalid_input(format!(
+            "{:?} keys with curve {:?} are not supported for signing",
+            obj_type, curve
+        ))),
+    }
+}
+
+/// Default MAC algorithm of `sobject`: HMAC-SHA256 for HMAC keys, CMAC for AES and 3DES keys.
+fn mac_alg(sobject: &Sobject) -> Result<Option<DigestAlgorithm>> {
+    match sobject.obj_type {
+        ObjectType::Hmac => Ok(Some(DigestAlgorithm::Sha256)),
+        ObjectType::Aes | ObjectType::Des3 => Ok(None),
+        obj_type => Err(Error::invalid_input(format!(
+            "{:?} keys can not be used for MAC",
+            obj_type
+        ))),
+    }
+}
+
+/// A lifecycle transition of a security object, see [`KeyHandle`].
+///
+/// [`KeyHandle`]: ./struct.KeyHandle.html
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Transition {
+    /// `PreActive` to `Active`, see [`KeyHandle::activate()`](./struct.KeyHandle.html#method.activate).
+    Activate,
+    /// `PreActive` or `Active` to `Deactivated`, see
+    /// [`KeyHandle::deactivate_at()`](./struct.KeyHandle.html#method.deactivate_at) and
+    /// [`KeyHandle::revoke()`](./struct.KeyHandle.html#method.revoke).
+    Deactivate,
+    /// Any state but `Compromised` to `Compromised`, see
+    /// [`KeyHandle::mark_compromised()`](./struct.KeyHandle.html#method.mark_compromised).
+    Compromise,
+    /// Deletes the object, in any state but `Active`, see
+    /// [`KeyHandle::destroy()`](./struct.KeyHandle.html#method.destroy).
+    Destroy,
+}
+
+impl Transition {
+    /// Whether the transition is allowed from `state`.
+    pub fn is_allowed_from(self, state: SobjectState) -> bool {
+        use SobjectState::*;
+        match self {
+            Transition::Activate => state == PreActive,
+            Transition::Deactivate => matches!(state, PreActive | Active),
+            Transition::Compromise => state != Compromised,
+            Transition::Destroy => state != Active,
+        }
+    }
+
+    fn check(self, state: SobjectState) -> Result<()> {
+        if !self.is_allowed_from(state) {
+            return Err(Error::invalid_input(format!(
+                "transition {:?} is not allowed from state {:?}",
+                self, state
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A security object bound to a client.
+///
+/// The security object is fetched on first use and cached by the handle, and operations use its key id, so that a
+/// handle created from a name keeps using the same version of the key after a rotation until it is
+/// [refreshed](#method.refresh). Cryptographic operations pick the algorithm, mode and digest from the type, RSA
+/// policies and curve of the object:
+///
+/// ```no_run
+/// # use sdkms::{api_model::*, SdkmsClient};
+/// # fn main() -> Result<(), sdkms::Error> {
+/// # let client = SdkmsClient::builder().build()?;
+/// let key = client.key(SobjectDescriptor::Name("my key".to_owned()));
+/// let ciphertext = key.encrypt(b"hello, world!")?;
+/// let plain = key.decrypt(&ciphertext)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Lifecycle operations validate the transition against the current state and dates of the object before calling
+/// SDKMS, see [`Transition`]:
+///
+/// ```no_run
+/// # use sdkms::{api_model::*, SdkmsClient};
+/// # fn main() -> Result<(), sdkms::Error> {
+/// # let client = SdkmsClient::builder().build()?;
+/// let key = client.key(SobjectDescriptor::Name("my key".to_owned()));
+/// key.activate()?;
+/// key.deactivate_at(Time(Time::now().0 + 90 * 24 * 60 * 60))?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Transition`]: ./enum.Transition.html
+pub struct KeyHandle<'a> {
+    client: &'a SdkmsClient,
+    key: SobjectDescriptor,
+    sobject: RwLock<Option<Sobject>>,
+}
+
+impl<'a> Clone for KeyHandle<'a> {
+    fn clone(&self) -> Self {
+        KeyHandle {
+            client: self.client,
+            key: self.key.clone(),
+            sobject: RwLock::new(self.sobject.read().unwrap().clone()),
+        }
+    }
+}
+
+impl<'a> KeyHandle<'a> {
+    pub fn new(client: &'a SdkmsClient, key: SobjectDescriptor) -> Self {
+        KeyHandle {
+            client,
+            key,
+            sobject: RwLock::new(None),
+        }
+    }
+
+    pub fn descriptor(&self) -> &SobjectDescriptor {
+        &self.key
+    }
+
+    /// The security object, fetched on first use. Changes made outside of this handle are picked up by
+    /// [`refresh()`](#method.refresh).
+    pub fn sobject(&self) -> Result<Sobject> {
+        if let Some(ref sobject) = *self.sobject.read().unwrap() {
+            return Ok(sobject.clone());
+        }
+        self.refresh()
+    }
+
+    /// Fetches the security object again.
+    pub fn refresh(&self) -> Result<Sobject> {
+        let sobject = self.client.get_sobject(None, &self.key)?;
+        *self.sobject.write().unwrap() = Some(sobject.clone());
+        Ok(sobject)
+    }
+
+    fn invalidate(&self) {
+        *self.sobject.write().unwrap() = None;
+    }
+
+    /// The cached security object and its key id.
+    fn resolve(&self) -> Result<(SobjectDescriptor, Sobject)> {
+        let sobject = self.sobject()?;
+        let kid = sobject
+            .kid
+            .ok_or_else(|| Error::invalid_input("the security object has no key id"))?;
+        Ok((SobjectDescriptor::Kid(kid), sobject))
+    }
+
+    /// The state of the object, taking its activation, deactivation and compromise dates into account.
+    pub fn state(&self) -> Result<SobjectState> {
+        Ok(self.sobject()?.effective_state(Time::now()))
+    }
+
+    /// The transitions allowed from the current state of the object.
+    pub fn allowed_transitions(&self) -> Result<Vec<Transition>> {
+        let state = self.state()?;
+        let transitions = [
+            Transition::Activate,
+            Transition::Deactivate,
+            Transition::Compromise,
+            Transition::Destroy,
+        ];
+        Ok(transitions
+            .iter()
+            .copied()
+            .filter(|transition| transition.is_allowed_from(state))
+            .collect())
+    }
+
+    /// Fetches the object and checks that `transition` is allowed, returns the key id and the object.
+    fn prepare(&self, transition: Transition) -> Result<(Uuid, Sobject)> {
+        let sobject = self.refresh()?;
+        transition.check(sobject.effective_state(Time::now()))?;
+        let kid = sobject
+            .kid
+            .ok_or_else(|| Error::invalid_input("the security object has no key id"))?;
+        Ok((kid, sobject))
+    }
+
+    /// Activates a pre-active object.
+    pub fn activate(&self) -> Result<()> {
+        let (kid, _) = self.prepare(Transition::Activate)?;
+        self.invalidate();
+        self.client.activate_sobject(&kid)
+    }
+
+    /// Schedules the deactivation of a pre-active or active object at `time`, which must be in the future and after
+    /// the activation date of the object.
+    pub fn deactivate_at(&self, time: Time) -> Result<Sobject> {
+        let (kid, sobject) = self.prepare(Transition::Deactivate)?;
+        if time <= Time::now() {
+            return Err(Error::invalid_input(
+                "the deactivation date must be in the future",
+            ));
+        }
+        if matches!(sobject.activation_date, Some(date) if time <= date) {
+            return Err(Error::invalid_input(
+                "the deactivation date must be after the activation date",
+            ));
+        }
+        let sobject = self.client.update_sobject(
+            &kid,
+            &SobjectRequest {
+                deactivation_date: Some(time),
+                ..Default::default()
+            },
+        )?;
+        *self.sobject.write().unwrap() = Some(sobject.clone());
+        Ok(sobject)
+    }
+
+    /// Deactivates a pre-active or active object now. Compromise reasons are handled by
+    /// [`mark_compromised()`](#method.mark_compromised).
+    pub fn revoke(&self, reason: RevocationReason) -> Result<()> {
+        if is_compromise(&reason.code) {
+            return self.mark_compromised(reason);
+        }
+        let (kid, _) = self.prepare(Transition::Deactivate)?;
+        self.invalidate();
+        self.client.revoke_sobject(&kid, &reason)
+    }
+
+    /// Marks the object as compromised, `reason` must have a `KeyCompromise` or `CACompromise` code.
+    pub fn mark_compromised(&self, reason: RevocationReason) -> Result<()> {
+        if !is_compromise(&reason.code) {
+            return Err(Error::invalid_input(format!(
+                "{:?} is not a compromise reason",
+                reason.code
+            )));
+        }
+        if matches!(reason.compromise_occurance_date, Some(date) if date > Time::now()) {
+            return Err(Error::invalid_input(
+                "the compromise date must not be in the future",
+            ));
+        }
+        let (kid, _) = self.prepare(Transition::Compromise)?;
+        self.invalidate();
+        self.client.revoke_sobject(&kid, &reason)
+    }
+
+    /// Removes the private part of an asymmetric key, keeping its public key.
+    pub fn remove_private(&self) -> Result<()> {
+        let sobject = self.refresh()?;
+        if sobject.public_only || sobject.pub_key.is_none() {
+            return Err(Error::invalid_input(
+                "the security object has no private key",
+            ));
+        }
+        let kid = sobject
+            .kid
+            .ok_or_else(|| Error::invalid_input("the security object has no key id"))?;
+        self.invalidate();
+        self.client.remove_private(&kid)
+    }
+
+    /// Deletes the object. Active objects must be deactivated first.
+    pub fn destroy(self) -> Result<()> {
+        let (kid, _) = self.prepare(Transition::Destroy)?;
+        self.client.delete_sobject(&kid)
+    }
+
+    /// Encrypts `plain` with the default algorithm and mode of the key, see [`Ciphertext`].
+    ///
+    /// [`Ciphertext`]: ./ciphertext/struct.Ciphertext.html
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Ciphertext> {
+        let (key, sobject) = self.resolve()?;
+        let (alg, mode) = cipher_params(&sobject)?;
+        ciphertext::seal_with(
+            self.client,
+            &EncryptRequest {
+                key: Some(key),
+                alg,
+                plain: plain.to_vec().into(),
+                mode,
+                iv: None,
+                ad: None,
+                tag_len: None,
+            },
+        )
+    }
+
+    /// Decrypts `ciphertext`, which must have been encrypted with this key or another version of it, see
+    /// [`KeyVersions`]. Other versions are looked up without replacing the cached object, so later operations keep
+    /// using the same version.
+    ///
+    /// [`KeyVersions`]: ./rotation/struct.KeyVersions.html
+    pub fn decrypt(&self, ciphertext: &Ciphertext) -> Result<Vec<u8>> {
+        let (key, sobject) = self.resolve()?;
+        if sobject.kid != Some(ciphertext.kid)
+            && !KeyVersions::new(self.client, key).contains(&ciphertext.kid)?
+        {
+            return Err(Error::invalid_input(format!(
+                "the ciphertext was encrypted with another key: {}",
+                ciphertext.kid
+            )));
+        }
+        ciphertext::open(self.client, ciphertext, None)
+    }
+
+    /// Signs `data` with the default digest and signature mode of the key.
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let (key, sobject) = self.resolve()?;
+        let (hash_alg, mode) = signature_params(&sobject)?;
+        let resp = self.client.sign(&SignRequest {
+            key: Some(key),
+            hash_alg,
+            hash: None,
+            data: Some(data.to_vec().into()),
+            mode,
+            deterministic_signature: None,
+        })?;
+        Ok(resp.signature.into())
+    }
+
+    /// Verifies a signature made by [`sign()`](#method.sign).
+    pub fn verify(&self, data: &[u8], signature: &[u8]) -> Result<bool> {
+        let (key, sobject) = self.resolve()?;
+        let (hash_alg, mode) = signature_params(&sobject)?;
+        let resp = self.client.verify(&VerifyRequest {
+            key: Some(key),
+            hash_alg,
+            hash: None,
+            data: Some(data.to_vec().into()),
+            mode,
+            signature: signature.to_vec().into(),
+        })?;
+        Ok(resp.result)
+    }
+
+    /// Computes the MAC of `data`, HMAC-SHA256 with HMAC keys and CMAC with AES and 3DES keys.
+    pub fn mac(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let (key, sobject) = self.resolve()?;
+        let alg = mac_alg(&sobject)?;
+        let resp = self.client.mac(&MacRequest {
+            key: Some(key),
+            alg,
+            data: data.to_vec().into(),
+        })?;
+        Ok(resp.mac.into())
+    }
+
+    /// Verifies a MAC computed by [`mac()`](#method.mac).
+    pub fn mac_verify(&self, data: &[u8], mac: &[u8]) -> Result<bool> {
+        let (key, sobject) = self.resolve()?;
+        let alg = mac_alg(&sobject)?;
+        let resp = self.client.mac_verify(&VerifyMacRequest {
+            key: Some(key),
+            alg,
+            data: data.to_vec().into(),
+            digest: None,
+            mac: Some(mac.to_vec().into()),
+        })?;
+        Ok(resp.result)
+    }
+
+    /// Wraps `subject` with this key, using its default algorithm and mode.
+    pub fn wrap(&self, subject: &KeyHandle) -> Result<WrappedKey> {
+        let (key, sobject) = self.resolve()?;
+        let (alg, mode) = cipher_params(&sobject)?;
+        let (subject_key, subject) = subject.resolve()?;
+        let req = WrapKeyRequest {
+            key: Some(key.clone()),
+            subject: Some(subject_key),
+            kid: None,
+            alg,
+            mode,
+            iv: None,
+            ad: None,
+            tag_len: None,
+        };
+        let resp = self.client.wrap(&req)?;
+        let kek = sobject
+            .kid
+            .ok_or_else(|| Error::invalid_input("the security object has no key id"))?;
+        Ok(WrappedKey::new(kek, subject.obj_type, &req, resp))
+    }
+}
+
+fn is_compromise(code: &RevocationReasonCode) -> bool {
+    matches!(
+        code,
+        RevocationReasonCode::KeyCompromise | RevocationReasonCode::CACompromise
+    )
+}
+
+impl SdkmsClient {
+    /// A handle to the security object `key`, see [`KeyHandle`].
+    ///
+    /// [`KeyHandle`]: ./struct.KeyHandle.html
+    pub fn key(&self, key: SobjectDescriptor) -> KeyHandle<'_> {
+        KeyHandle::new(self, key)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+
+    /// Creates a security object named `name` that allows all key operations, returns a handle to it.
+    fn create_key<'a>(client: &'a SdkmsClient, name: &str, req: SobjectRequest) -> KeyHandle<'a> {
+        client
+            .create_sobject(&SobjectRequest {
+                name: Some(name.to_owned()),
+                key_ops: Some(KeyOperations::all()),
+                ..req
+            })
+            .unwrap();
+        client.key(SobjectDescriptor::Name(name.to_owned()))
+    }
+
+    #[test]
+    fn lifecycle() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let key = create_key(
+            &client,
+            "key",
+            SobjectRequest {
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                state: Some(SobjectState::PreActive),
+                ..Default::default()
+            },
+        );
+        assert_eq!(key.state().unwrap(), SobjectState::PreActive);
+        assert!(key.deactivate_at(Time(Time::now().0 - 10)).is_err());
+
+        key.activate().unwrap();
+        assert_eq!(key.state().unwrap(), SobjectState::Active);
+        assert!(key.activate().is_err());
+        assert!(key.clone().destroy().is_err());
+        assert_eq!(
+            key.allowed_transitions().unwrap(),
+            [Transition::Deactivate, Transition::Compromise]
+        );
+
+        let deactivation = Time(Time::now().0 + 3600);
+        let sobject = key.deactivate_at(deactivation).unwrap();
+        assert_eq!(sobject.deactivation_date, Some(deactivation));
+        assert_eq!(key.state().unwrap(), SobjectState::Active);
+
+        let reason = |code| RevocationReason {
+            code,
+            message: None,
+            compromise_occurance_date: None,
+        };
+        assert!(key
+            .mark_compromised(reason(RevocationReasonCode::Superseded))
+            .is_err());
+        key.revoke(reason(RevocationReasonCode::KeyCompromise))
+            .unwrap();
+        assert_eq!(key.state().unwrap(), SobjectState::Compromised);
+        assert!(key
+            .revoke(reason(RevocationReasonCode::Superseded))
+            .is_err());
+        assert!(key.remove_private().is_err());
+
+        key.clone().destroy().unwrap();
+        assert!(matches!(key.refresh(), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn default_algorithms() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let aes = create_key(
+            &client,
+            "aes",
+            SobjectRequest {
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                ..Default::default()
+            },
+        );
+        let rsa = create_key(
+            &client,
+            "rsa",
+            SobjectRequest {
+                obj_type: Some(ObjectType::Rsa),
+                key_size: Some(2048),
+                rsa: Some(RsaOptions {
+                    key_size: None,
+                    public_exponent: None,
+                    encryption_policy: vec![RsaEncryptionPolicy {
+                        padding: Some(RsaEncryptionPaddingPolicy::Pkcs1V15 {}),
+                    }],
+                    signature_policy: vec![RsaSignaturePolicy {
+                        padding: Some(RsaSignaturePaddingPolicy::Pss { mgf: None }),
+                    }],
+                }),
+                ..Default::default()
+            },
+        );
+        let ec = create_key(
+            &client,
+            "ec",
+            SobjectRequest {
+                obj_type: Some(ObjectType::Ec),
+                elliptic_curve: Some(EllipticCurve::NistP384),
+                ..Default::default()
+            },
+        );
+        let hmac = create_key(
+            &client,
+            "hmac",
+            SobjectRequest {
+                obj_type: Some(ObjectType::Hmac),
+                key_size: Some(256),
+                ..Default::default()
+            },
+        );
+
+        let ciphertext = aes.encrypt(b"hello").unwrap();
+        assert_eq!(ciphertext.mode, Some(CryptMode::Symmetric(CipherMode::Gcm)));
+        assert_eq!(aes.decrypt(&ciphertext).unwrap(), b"hello");
+        assert!(rsa.decrypt(&ciphertext).is_err());
+        let ciphertext = rsa.encrypt(b"hello").unwrap();
+        assert_eq!(
+            ciphertext.mode,
+            Some(CryptMode::Rsa(RsaEncryptionPadding::Pkcs1V15 {}))
+        );
+
+        let params = signature_params(&rsa.sobject().unwrap()).unwrap();
+        let pss = RsaSignaturePadding::Pss {
+            mgf: Mgf::Mgf1 {
+                hash: DigestAlgorithm::Sha256,
+            },
+        };
+        assert_eq!(
+            params,
+            (DigestAlgorithm::Sha256, Some(SignatureMode::Rsa(pss)))
+        );
+        let params = signature_params(&ec.sobject().unwrap()).unwrap();
+        assert_eq!(params, (DigestAlgorithm::Sha384, None));
+        for key in &[&rsa, &ec] {
+            let signature = key.sign(b"data").unwrap();
+            assert!(key.verify(b"data", &signature).unwrap());
+            assert!(!key.verify(b"other", &signature).unwrap());
+        }
+        assert!(aes.sign(b"data").is_err());
+
+        let mac = hmac.mac(b"data").unwrap();
+        assert!(hmac.mac_verify(b"data", &mac).unwrap());
+        assert!(ec.mac(b"data").is_err());
+
+        let wrapped = aes.wrap(&hmac).unwrap();
+        assert_eq!(Some(wrapped.kek), aes.sobject().unwrap().kid);
+        assert_eq!(wrapped.obj_type, ObjectType::Hmac);
+    }
+
+    #[test]
+    fn rotation() {
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let key = create_key(
+            &client,
+            "key",
+            SobjectRequest {
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                ..Default::default()
+            },
+        );
+        let first = key.sobject().unwrap();
+        assert_sync(&key);
+        let old = key.encrypt(b"old").unwrap();
+
+        let second = client
+            .rotate_sobject(&SobjectRequest {
+                name: Some("key".to_owned()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(Some(key.encrypt(b"cached").unwrap().kid), first.kid);
+        let new = client
+            .key(SobjectDescriptor::Name("key".to_owned()))
+            .encrypt(b"new")
+            .unwrap();
+        assert_eq!(Some(new.kid), second.kid);
+        assert_eq!(key.decrypt(&new).unwrap(), b"new");
+        assert_eq!(key.sobject().unwrap().kid, first.kid);
+        assert_eq!(Some(key.encrypt(b"cached").unwrap().kid), first.kid);
+        assert_eq!(key.decrypt(&old).unwrap(), b"old");
+        key.refresh().unwrap();
+        assert_eq!(key.sobject().unwrap().kid, second.kid);
+        assert_eq!(key.decrypt(&old).unwrap(), b"old");
+    }
+}
@@ -0,0 +1,449 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Typed builders for key generation requests.
+//!
+//! Each builder only exposes the options that apply to its key type and validates sizes, curves and key operations
+//! before producing a [`SobjectRequest`] with [`KeySpec::to_request()`]:
+//!
+//! ```no_run
+//! # use sdkms::{api_model::*, key_spec::*, SdkmsClient};
+//! # fn main() -> Result<(), sdkms::Error> {
+//! # let client = SdkmsClient::builder().build()?;
+//! let spec = RsaKeySpec::new(3072)
+//!     .with_name("signing key")
+//!     .with_key_ops(KeyOperations::SIGN | KeyOperations::VERIFY);
+//! let key = client.create_sobject(&spec.to_request()?)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SobjectRequest`]: ../api_model/struct.SobjectRequest.html
+//! [`KeySpec::to_request()`]: ./trait.KeySpec.html#tymethod.to_request
+
+use crate::api_model::*;
+use crate::client::Result;
+
+use uuid::Uuid;
+
+use std::collections::HashMap;
+
+const RSA_KEY_SIZES: std::ops::RangeInclusive<u32> = 1024..=8192;
+const HMAC_KEY_SIZES: std::ops::RangeInclusive<u32> = 112..=8192;
+
+/// A validated description of a key to generate.
+pub trait KeySpec {
+    /// Validates the spec and returns the corresponding request for `create_sobject()`.
+    fn to_request(&self) -> Result<SobjectRequest>;
+}
+
+/// Options that apply to keys of every type.
+#[derive(Debug, Default, Clone)]
+struct CommonOptions {
+    name: Option<String>,
+    description: Option<String>,
+    group_id: Option<Uuid>,
+    key_ops: Option<KeyOperations>,
+    custom_metadata: Option<HashMap<String, String>>,
+    enabled: Option<bool>,
+    transient: Option<bool>,
+    activation_date: Option<Time>,
+    deactivation_date: Option<Time>,
+}
+
+impl CommonOptions {
+    fn request(&self, obj_type: ObjectType, allowed_ops: KeyOperations) -> Result<SobjectRequest> {
+        if let Some(key_ops) = self.key_ops {
+            if !allowed_ops.contains(key_ops) {
+                return Err(Error::invalid_input(format!(
+                    "key operations {:?} are not valid for {:?} keys",
+                    key_ops - allowed_ops,
+                    obj_type
+                )));
+            }
+        }
+        if let (Some(activation), Some(deactivation)) =
+            (self.activation_date, self.deactivation_date)
+        {
+            if deactivation <= activation {
+                return Err(Error::invalid_input(
+                    "deactivation_date must be after activation_date".to_owned(),
+                ));
+            }
+        }
+        Ok(SobjectRequest {
+            activation_date: self.activation_date,
+            custom_metadata: self.custom_metadata.clone(),
+            deactivation_date: self.deactivation_date,
+            description: self.description.clone(),
+            enabled: self.enabled,
+            group_id: self.group_id,
+            key_ops: self.key_ops,
+            name: self.name.clone(),
+            obj_type: Some(obj_type),
+            transient: self.transient,
+            ..Default::default()
+        })
+    }
+}
+
+macro_rules! common_options {
+    ($spec:ident) => {
+        impl $spec {
+            pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+                self.common.name = Some(name.into());
+                self
+            }
+
+            pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+                self.common.description = Some(description.into());
+                self
+            }
+
+            pub fn with_group_id(mut self, group_id: Uuid) -> Self {
+                self.common.group_id = Some(group_id);
+                self
+            }
+
+            /// Sets the operations allowed with the key. Fails validation if an operation does not apply to the key
+            /// type. When not set, SDKMS chooses a default set of operations.
+            pub fn with_key_ops(mut self, key_ops: KeyOperations) -> Self {
+                self.common.key_ops = Some(key_ops);
+                self
+            }
+
+            pub fn with_custom_metadata(
+                mut self,
+                custom_metadata: HashMap<String, String>,
+            ) -> Self {
+                self.common.custom_metadata = Some(custom_metadata);
+                self
+            }
+
+            pub fn with_enabled(mut self, enabled: bool) -> Self {
+                self.common.enabled = Some(enabled);
+                self
+            }
+
+            pub fn with_transient(mut self, transient: bool) -> Self {
+                self.common.transient = Some(transient);
+                self
+            }
+
+            pub fn with_activation_date(mut self, activation_date: Time) -> Self {
+                self.common.activation_date = Some(activation_date);
+                self
+            }
+
+            pub fn with_deactivation_date(mut self, deactivation_date: Time) -> Self {
+                self.common.deactivation_date = Some(deactivation_date);
+                self
+            }
+        }
+    };
+}
+
+/// An AES key of 128, 192 or 256 bits.
+#[derive(Debug, Clone)]
+pub struct AesKeySpec {
+    key_size: u32,
+    common: CommonOptions,
+}
+
+common_options!(AesKeySpec);
+
+impl AesKeySpec {
+    pub fn new(key_size: u32) -> Self {
+        AesKeySpec {
+            key_size,
+            common: CommonOptions::default(),
+        }
+    }
+}
+
+impl KeySpec for AesKeySpec {
+    fn to_request(&self) -> Result<SobjectRequest> {
+        if !matches!(self.key_size, 128 | 192 | 256) {
+            return Err(Error::invalid_input(format!(
+                "invalid AES key size {}, must be 128, 192 or 256",
+                self.key_size
+            )));
+        }
+        let allowed_ops = KeyOperations::ENCRYPT
+            | KeyOperations::DECRYPT
+            | KeyOperations::WRAPKEY
+            | KeyOperations::UNWRAPKEY
+            | KeyOperations::DERIVEKEY
+            | KeyOperations::MACGENERATE
+            | KeyOperations::MACVERIFY
+            | KeyOperations::EXPORT
+            | KeyOperations::APPMANAGEABLE
+            | KeyOperations::HIGHVOLUME;
+        Ok(SobjectRequest {
+            key_size: Some(self.key_size),
+            ..self.common.request(ObjectType::Aes, allowed_ops)?
+        })
+    }
+}
+
+/// An RSA key of 1024 to 8192 bits.
+#[derive(Debug, Clone)]
+pub struct RsaKeySpec {
+    key_size: u32,
+    public_exponent: Option<u32>,
+    encryption_policy: Option<Vec<RsaEncryptionPolicy>>,
+    signature_policy: Option<Vec<RsaSignaturePolicy>>,
+    common: CommonOptions,
+}
+
+common_options!(RsaKeySpec);
+
+impl RsaKeySpec {
+    pub fn new(key_size: u32) -> Self {
+        RsaKeySpec {
+            key_size,
+            public_exponent: None,
+            encryption_policy: None,
+            signature_policy: None,
+            common: CommonOptions::default(),
+        }
+    }
+
+    /// Sets the public exponent, which must be odd and at least 3. The default is 65537.
+    pub fn with_public_exponent(mut self, public_exponent: u32) -> Self {
+        self.public_exponent = Some(public_exponent);
+        self
+    }
+
+    /// Restricts the paddings allowed for encryption, see [`RsaOptions::encryption_policy`]. If neither policy is
+    /// set, SDKMS applies its defaults for new keys, which allow OAEP only for encryption. If only the signature
+    /// policy is set, encryption is not restricted.
+    ///
+    /// [`RsaOptions::encryption_policy`]: ../api_model/struct.RsaOptions.html#structfield.encryption_policy
+    pub fn with_encryption_policy(mut self, policy: Vec<RsaEncryptionPolicy>) -> Self {
+        self.encryption_policy = Some(policy);
+        self
+    }
+
+    /// Restricts the paddings allowed for signatures, see [`RsaOptions::signature_policy`]. If only the encryption
+    /// policy is set, signatures are not restricted.
+    ///
+    /// [`RsaOptions::signature_policy`]: ../api_model/struct.RsaOptions.html#structfield.signature_policy
+    pub fn with_signature_policy(mut self, policy: Vec<RsaSignaturePolicy>) -> Self {
+        self.signature_policy = Some(policy);
+        self
+    }
+}
+
+impl KeySpec for RsaKeySpec {
+    fn to_request(&self) -> Result<SobjectRequest> {
+        if !RSA_KEY_SIZES.contains(&self.key_size) || self.key_size % 8 != 0 {
+            return Err(Error::invalid_input(format!(
+                "invalid RSA key size {}, must be a multiple of 8 between 1024 and 8192",
+                self.key_size
+            )));
+        }
+        if matches!(self.public_exponent, Some(e) if e < 3 || e % 2 == 0) {
+            return Err(Error::invalid_input(
+                "RSA public exponent must be odd and at least 3",
+            ));
+        }
+        let allowed_ops = KeyOperations::SIGN
+            | KeyOperations::VERIFY
+            | KeyOperations::ENCRYPT
+            | KeyOperations::DECRYPT
+            | KeyOperations::WRAPKEY
+            | KeyOperations::UNWRAPKEY
+            | KeyOperations::EXPORT
+            | KeyOperations::APPMANAGEABLE
+            | KeyOperations::HIGHVOLUME;
+        let rsa = match (&self.encryption_policy, &self.signature_policy) {
+            (None, None) => None,
+            (encryption_policy, signature_policy) => Some(RsaOptions {
+                key_size: Some(self.key_size),
+                public_exponent: self.public_exponent,
+                encryption_policy: encryption_policy
+                    .clone()
+                    .unwrap_or_else(|| vec![RsaEncryptionPolicy { padding: None }]),
+                signature_policy: signature_policy
+                    .clone()
+                    .unwrap_or_else(|| vec![RsaSignaturePolicy { padding: None }]),
+            }),
+        };
+        Ok(SobjectRequest {
+            key_size: Some(self.key_size),
+            pub_exponent: self.public_exponent,
+            rsa,
+            ..self.common.request(ObjectType::Rsa, allowed_ops)?
+        })
+    }
+}
+
+/// An elliptic curve key.
+#[derive(Debug, Clone)]
+pub struct EcKeySpec {
+    curve: EllipticCurve,
+    deterministic_signatures: Option<bool>,
+    common: CommonOptions,
+}
+
+common_options!(EcKeySpec);
+
+impl EcKeySpec {
+    pub fn new(curve: EllipticCurve) -> Self {
+        EcKeySpec {
+            curve,
+            deterministic_signatures: None,
+            common: CommonOptions::default(),
+        }
+    }
+
+    /// Makes ECDSA signatures deterministic (RFC 6979). Not valid for Ed25519, X25519 and X448 keys.
+    pub fn with_deterministic_signatures(mut self, deterministic_signatures: bool) -> Self {
+        self.deterministic_signatures = Some(deterministic_signatures);
+        self
+    }
+}
+
+impl KeySpec for EcKeySpec {
+    fn to_request(&self) -> Result<SobjectRequest> {
+        let common_ops = KeyOperations::EXPORT | KeyOperations::APPMANAGEABLE;
+        let (allowed_ops, ecdsa) = match self.curve {
+            EllipticCurve::X25519 | EllipticCurve::X448 => {
+                (common_ops | KeyOperations::AGREEKEY, false)
+            }
+            EllipticCurve::Ed25519 => (
+                common_ops | KeyOperations::SIGN | KeyOperations::VERIFY,
+                false,
+            ),
+            _ => (
+                common_ops | KeyOperations::SIGN | KeyOperations::VERIFY | KeyOperations::AGREEKEY,
+                true,
+            ),
+        };
+        if self.deterministic_signatures.is_some() && !ecdsa {
+            return Err(Error::invalid_input(format!(
+                "deterministic signatures are not valid for {:?} keys",
+                self.curve
+            )));
+        }
+        Ok(SobjectRequest {
+            elliptic_curve: Some(self.curve),
+            deterministic_signatures: self.deterministic_signatures,
+            ..self.common.request(ObjectType::Ec, allowed_ops)?
+        })
+    }
+}
+
+/// An HMAC key of 112 to 8192 bits.
+#[derive(Debug, Clone)]
+pub struct HmacKeySpec {
+    key_size: u32,
+    common: CommonOptions,
+}
+
+common_options!(HmacKeySpec);
+
+impl HmacKeySpec {
+    pub fn new(key_size: u32) -> Self {
+        HmacKeySpec {
+            key_size,
+            common: CommonOptions::default(),
+        }
+    }
+}
+
+impl KeySpec for HmacKeySpec {
+    fn to_request(&self) -> Result<SobjectRequest> {
+        if !HMAC_KEY_SIZES.contains(&self.key_size) || self.key_size % 8 != 0 {
+            return Err(Error::invalid_input(format!(
+                "invalid HMAC key size {}, must be a multiple of 8 between 112 and 8192",
+                self.key_size
+            )));
+        }
+        let allowed_ops = KeyOperations::MACGENERATE
+            | KeyOperations::MACVERIFY
+            | KeyOperations::DERIVEKEY
+            | KeyOperations::EXPORT
+            | KeyOperations::APPMANAGEABLE
+            | KeyOperations::HIGHVOLUME;
+        Ok(SobjectRequest {
+            key_size: Some(self.key_size),
+            ..self.common.request(ObjectType::Hmac, allowed_ops)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        let req = AesKeySpec::new(256)
+            .with_name("aes")
+            .with_key_ops(KeyOperations::ENCRYPT | KeyOperations::DECRYPT)
+            .to_request()
+            .unwrap();
+        assert_eq!(req.obj_type, Some(ObjectType::Aes));
+        assert_eq!(req.key_size, Some(256));
+        assert_eq!(req.elliptic_curve, None);
+
+        assert!(AesKeySpec::new(512).to_request().is_err());
+        assert!(AesKeySpec::new(128)
+            .with_key_ops(KeyOperations::SIGN)
+            .to_request()
+            .is_err());
+        assert!(RsaKeySpec::new(3072)
+            .with_public_exponent(4)
+            .to_request()
+            .is_err());
+        assert!(EcKeySpec::new(EllipticCurve::X25519)
+            .with_key_ops(KeyOperations::SIGN)
+            .to_request()
+            .is_err());
+        assert!(HmacKeySpec::new(64).to_request().is_err());
+    }
+
+    #[test]
+    fn rsa_policies() {
+        let encryption = vec![RsaEncryptionPolicy {
+            padding: Some(RsaEncryptionPaddingPolicy::Pkcs1V15 {}),
+        }];
+        let signature = vec![RsaSignaturePolicy {
+            padding: Some(RsaSignaturePaddingPolicy::Pkcs1V15 {}),
+        }];
+        let policies = |spec: RsaKeySpec| {
+            let rsa = spec.to_request().unwrap().rsa?;
+            assert_eq!(rsa.key_size, Some(3072));
+            Some((rsa.encryption_policy, rsa.signature_policy))
+        };
+
+        assert_eq!(policies(RsaKeySpec::new(3072)), None);
+        assert_eq!(
+            policies(RsaKeySpec::new(3072).with_encryption_policy(encryption.clone())),
+            Some((
+                encryption.clone(),
+                vec![RsaSignaturePolicy { padding: None }]
+            ))
+        );
+        assert_eq!(
+            policies(RsaKeySpec::new(3072).with_signature_policy(signature.clone())),
+            Some((
+                vec![RsaEncryptionPolicy { padding: None }],
+                signature.clone()
+            ))
+        );
+        assert_eq!(
+            policies(
+                RsaKeySpec::new(3072)
+                    .with_encryption_policy(encryption.clone())
+                    .with_signature_policy(signature.clone())
+            ),
+            Some((encryption, signature))
+        );
+    }
+}
@@ -49,8 +49,42 @@
 //! }
 //! ```
 //!
+//! ## Key handles
+//! [`KeyHandle`], returned by `SdkmsClient::key()`, binds a security object to the client. It picks default
+//! algorithms for cryptographic operations from the key type and policies, and validates lifecycle transitions.
+//!
+//! ## Async support
+//! With the `async` feature enabled, [`AsyncSdkmsClient`] exposes the same APIs as `async` methods built on top of
+//! the non-blocking HTTP client.
+//!
+//! ## Envelope encryption
+//! With the `envelope` feature enabled, the [`envelope`] module encrypts data locally with data keys generated by
+//! SDKMS and wrapped with a key that stays in SDKMS.
+//!
+//! ## Public keys and certificates
+//! With the `x509` feature enabled, the [`public_key`] module converts the public keys of RSA and EC security
+//! objects to PEM, DER and JWK. With the `jose` feature enabled, the [`jose`] module signs and verifies JWS and JWT
+//! tokens with SDKMS keys. The [`x509`] module parses and imports certificates and creates certificate
+//! signing requests for SDKMS keys.
+//!
+//! ## Testing
+//! With the `mock` feature enabled, [`mock::MockServer`] provides an in-process mock of the SDKMS REST API that
+//! [`SdkmsClient`] can be pointed at, so that tests can run without access to SDKMS.
+//!
+//! With the `local` feature enabled, [`local::LocalBackend`] performs cryptographic operations in-process. Both it
+//! and [`SdkmsClient`] implement [`CryptoProvider`], so code written against that trait can switch between them.
+//!
 //! [`SdkmsClient`]: ./struct.SdkmsClient.html
+//! [`AsyncSdkmsClient`]: ./struct.AsyncSdkmsClient.html
+//! [`KeyHandle`]: ./struct.KeyHandle.html
 //! [`api_model`]: ./api_model/index.html
+//! [`envelope`]: ./envelope/index.html
+//! [`public_key`]: ./public_key/index.html
+//! [`jose`]: ./jose/index.html
+//! [`x509`]: ./x509/index.html
+//! [`mock::MockServer`]: ./mock/struct.MockServer.html
+//! [`local::LocalBackend`]: ./local/struct.LocalBackend.html
+//! [`CryptoProvider`]: ./trait.CryptoProvider.html
 //! [REST APIs]: https://www.fortanix.com/api/sdkms/
 //! [Fortanix SDKMS]: https://fortanix.com/products/sdkms/
 
@@ -62,9 +96,41 @@ extern crate log;
 #[macro_use]
 mod macros;
 pub mod api_model;
+#[cfg(feature = "async")]
+mod async_client;
+pub mod ciphertext;
 mod client;
+#[cfg(feature = "envelope")]
+pub mod envelope;
 mod generated;
+#[cfg(feature = "jose")]
+pub mod jose;
+mod key_handle;
+pub mod key_spec;
+#[cfg(feature = "local")]
+pub mod local;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod operations;
+mod pagination;
+mod provider;
+#[cfg(feature = "x509")]
+pub mod public_key;
+pub mod reencrypt;
+mod request_builder;
+mod retry;
+pub mod rotation;
+mod stream;
+#[cfg(feature = "x509")]
+pub mod x509;
 
 pub use crate::api_model::Error;
+#[cfg(feature = "async")]
+pub use crate::async_client::*;
 pub use crate::client::*;
+pub use crate::key_handle::{KeyHandle, Transition};
+pub use crate::pagination::{Paginated, Paginator};
+pub use crate::provider::CryptoProvider;
+pub use crate::request_builder::{DecryptRequestBuilder, EncryptRequestBuilder};
+pub use crate::retry::RetryPolicy;
+pub use crate::stream::{DecryptReader, EncryptWriter};
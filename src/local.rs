@@ -0,0 +1,1389 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! In-process cryptography with the same request and response types as SDKMS, see [`LocalBackend`].
+//!
+//! This module is only available with the `local` feature.
+//!
+//! [`LocalBackend`]: ./struct.LocalBackend.html
+
+use crate::api_model::*;
+use crate::client::{now, Result};
+use crate::provider::CryptoProvider;
+
+use aes_gcm::aead::generic_array::typenum::U12;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::AesGcm;
+use cbc::cipher::block_padding::{NoPadding, Pkcs7};
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use p256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+use p256::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use rand_core::{OsRng, RngCore};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::{BigUint, Oaep, Pkcs1v15Encrypt, Pkcs1v15Sign, Pss, RsaPrivateKey, RsaPublicKey};
+use sha2::Digest;
+use simple_hyper_client::StatusCode;
+use uuid::Uuid;
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+const GCM_IV_LEN: usize = 12;
+const GCM_TAG_LEN: usize = 16;
+const DEFAULT_RSA_KEY_SIZE: u32 = 2048;
+const DEFAULT_RSA_EXPONENT: u32 = 65537;
+
+/// Runs `$body` with `$d` bound to the hash function type for `$alg`.
+macro_rules! with_digest {
+    ($alg:expr, $d:ident => $body:expr) => {
+        match $alg {
+            DigestAlgorithm::Sha1 => {
+                type $d = sha1::Sha1;
+                $body
+            }
+            DigestAlgorithm::Sha256 => {
+                type $d = sha2::Sha256;
+                $body
+            }
+            DigestAlgorithm::Sha384 => {
+                type $d = sha2::Sha384;
+                $body
+            }
+            DigestAlgorithm::Sha512 => {
+                type $d = sha2::Sha512;
+                $body
+            }
+            DigestAlgorithm::Sha3_224 => {
+                type $d = sha3::Sha3_224;
+                $body
+            }
+            DigestAlgorithm::Sha3_256 => {
+                type $d = sha3::Sha3_256;
+                $body
+            }
+            DigestAlgorithm::Sha3_384 => {
+                type $d = sha3::Sha3_384;
+                $body
+            }
+            DigestAlgorithm::Sha3_512 => {
+                type $d = sha3::Sha3_512;
+                $body
+            }
+            alg => return Err(unsupported(format!("digest algorithm {:?}", alg))),
+        }
+    };
+}
+
+/// Calls `$f::<Aes>($args)` with the AES block cipher matching the length of `$key`.
+macro_rules! with_aes {
+    ($key:expr, $f:ident($($arg:expr),*)) => {
+        match $key.len() {
+            16 => $f::<aes::Aes128>($($arg),*),
+            24 => $f::<aes::Aes192>($($arg),*),
+            32 => $f::<aes::Aes256>($($arg),*),
+            _ => Err(bad_request("invalid AES key size")),
+        }
+    };
+}
+
+fn error(status: StatusCode, message: &str) -> Error {
+    ApiError::new(status, message).into()
+}
+
+/// `LocalBackend` stands in for SDKMS, so invalid requests fail with the `400 Bad Request` error SDKMS would return
+/// rather than with `Error::InvalidInput`.
+fn bad_request<M: Into<String>>(message: M) -> Error {
+    error(StatusCode::BAD_REQUEST, &message.into())
+}
+
+fn unsupported<M: AsRef<str>>(what: M) -> Error {
+    bad_request(format!(
+        "{} is not supported by the local backend",
+        what.as_ref()
+    ))
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Key material of a security object held by [`LocalBackend`].
+enum KeyMaterial {
+    /// AES, HMAC and secret keys.
+    Symmetric(Vec<u8>),
+    Rsa(Box<RsaPrivateKey>),
+    RsaPublic(RsaPublicKey),
+    NistP256(p256::SecretKey),
+    NistP256Public(p256::PublicKey),
+    NistP384(p384::SecretKey),
+    NistP384Public(p384::PublicKey),
+}
+
+impl KeyMaterial {
+    fn generate(obj_type: ObjectType, req: &SobjectRequest) -> Result<(Self, Option<u32>)> {
+        Ok(match obj_type {
+            ObjectType::Aes => {
+                let key_size = req.key_size.unwrap_or(256);
+                if !matches!(key_size, 128 | 192 | 256) {
+                    return Err(bad_request("AES key size must be 128, 192 or 256"));
+                }
+                (
+                    KeyMaterial::Symmetric(random_bytes(key_size as usize / 8)),
+                    Some(key_size),
+                )
+            }
+            ObjectType::Hmac | ObjectType::Secret => {
+                let key_size = req.key_size.unwrap_or(256);
+                if key_size < 112 || key_size % 8 != 0 {
+                    return Err(bad_request(
+                        "key size must be a multiple of 8 and at least 112",
+                    ));
+                }
+                (
+                    KeyMaterial::Symmetric(random_bytes(key_size as usize / 8)),
+                    Some(key_size),
+                )
+            }
+            ObjectType::Rsa => {
+                let rsa = req.rsa.as_ref();
+                let key_size = req
+                    .key_size
+                    .or_else(|| rsa.and_then(|rsa| rsa.key_size))
+                    .unwrap_or(DEFAULT_RSA_KEY_SIZE);
+                let exponent = req
+                    .pub_exponent
+                    .or_else(|| rsa.and_then(|rsa| rsa.public_exponent))
+                    .unwrap_or(DEFAULT_RSA_EXPONENT);
+                let key = RsaPrivateKey::new_with_exp(
+                    &mut OsRng,
+                    key_size as usize,
+                    &BigUint::from(exponent),
+                )
+                .map_err(|e| bad_request(e.to_string()))?;
+                (KeyMaterial::Rsa(Box::new(key)), Some(key_size))
+            }
+            ObjectType::Ec => match req.elliptic_curve {
+                Some(EllipticCurve::NistP256) => (
+                    KeyMaterial::NistP256(p256::SecretKey::random(&mut OsRng)),
+                    Some(256),
+                ),
+                Some(EllipticCurve::NistP384) => (
+                    KeyMaterial::NistP384(p384::SecretKey::random(&mut OsRng)),
+                    Some(384),
+                ),
+                Some(curve) => return Err(unsupported(format!("elliptic curve {:?}", curve))),
+                None => return Err(bad_request("elliptic_curve is required")),
+            },
+            obj_type => return Err(unsupported(format!("generating {:?} objects", obj_type))),
+        })
+    }
+
+    /// Parses key material in the formats accepted by SDKMS for import: raw bytes for symmetric keys, DER encoded
+    /// PKCS#8 or PKCS#1/SEC1 private keys and DER encoded `SubjectPublicKeyInfo` for asymmetric keys.
+    fn import(
+        obj_type: ObjectType,
+        req: &SobjectRequest,
+        value: &[u8],
+    ) -> Result<(Self, Option<u32>)> {
+        let invalid = || bad_request(format!("invalid {:?} key", obj_type));
+        Ok(match obj_type {
+            ObjectType::Aes if !matches!(value.len(), 16 | 24 | 32) => {
+                return Err(bad_request("AES key size must be 128, 192 or 256"))
+            }
+            ObjectType::Aes | ObjectType::Hmac | ObjectType::Secret => (
+                KeyMaterial::Symmetric(value.to_vec()),
+                Some(value.len() as u32 * 8),
+            ),
+            ObjectType::Rsa => {
+                let material = if let Ok(key) = RsaPrivateKey::from_pkcs8_der(value) {
+                    KeyMaterial::Rsa(Box::new(key))
+                } else if let Ok(key) = RsaPrivateKey::from_pkcs1_der(value) {
+                    KeyMaterial::Rsa(Box::new(key))
+                } else if let Ok(key) = RsaPublicKey::from_public_key_der(value) {
+                    KeyMaterial::RsaPublic(key)
+                } else {
+                    KeyMaterial::RsaPublic(
+                        RsaPublicKey::from_pkcs1_der(value).map_err(|_| invalid())?,
+                    )
+                };
+                let key_size = material
+                    .rsa_public_key()
+                    .map(|key| rsa::traits::PublicKeyParts::size(&key) as u32 * 8);
+                (material, key_size)
+            }
+            ObjectType::Ec => {
+                let p256 = || {
+                    p256::SecretKey::from_pkcs8_der(value)
+                        .or_else(|_| p256::SecretKey::from_sec1_der(value))
+                        .map(KeyMaterial::NistP256)
+                        .or_else(|_| {
+                            p256::PublicKey::from_public_key_der(value)
+                                .map(KeyMaterial::NistP256Public)
+                        })
+                        .ok()
+                };
+                let p384 = || {
+                    p384::SecretKey::from_pkcs8_der(value)
+                        .or_else(|_| p384::SecretKey::from_sec1_der(value))
+                        .map(KeyMaterial::NistP384)
+                        .or_else(|_| {
+                            p384::PublicKey::from_public_key_der(value)
+                                .map(KeyMaterial::NistP384Public)
+                        })
+                        .ok()
+                };
+                // Unwrapped keys come without a curve, PKCS#8 and SPKI encodings identify it.
+                let material = match req.elliptic_curve {
+                    Some(EllipticCurve::NistP256) => p256(),
+                    Some(EllipticCurve::NistP384) => p384(),
+                    Some(curve) => return Err(unsupported(format!("elliptic curve {:?}", curve))),
+                    None => p256().or_else(p384),
+                };
+                let material = material.ok_or_else(invalid)?;
+                let key_size = material.elliptic_curve().map(|curve| match curve {
+                    EllipticCurve::NistP384 => 384,
+                    _ => 256,
+                });
+                (material, key_size)
+            }
+            obj_type => return Err(unsupported(format!("importing {:?} objects", obj_type))),
+        })
+    }
+
+    fn rsa_public_key(&self) -> Option<RsaPublicKey> {
+        match *self {
+            KeyMaterial::Rsa(ref key) => Some(key.to_public_key()),
+            KeyMaterial::RsaPublic(ref key) => Some(key.clone()),
+            _ => None,
+        }
+    }
+
+    /// DER encoded `SubjectPublicKeyInfo` of asymmetric keys.
+    fn public_key_der(&self) -> Option<Vec<u8>> {
+        let der = match *self {
+            KeyMaterial::Symmetric(_) => return None,
+            KeyMaterial::Rsa(_) | KeyMaterial::RsaPublic(_) => {
+                self.rsa_public_key()?.to_public_key_der()
+            }
+            KeyMaterial::NistP256(ref key) => key.public_key().to_public_key_der(),
+            KeyMaterial::NistP256Public(ref key) => key.to_public_key_der(),
+            KeyMaterial::NistP384(ref key) => key.public_key().to_public_key_der(),
+            KeyMaterial::NistP384Public(ref key) => key.to_public_key_der(),
+        };
+        der.ok().map(|der| der.into_vec())
+    }
+
+    fn elliptic_curve(&self) -> Option<EllipticCurve> {
+        match *self {
+            KeyMaterial::NistP256(_) | KeyMaterial::NistP256Public(_) => {
+                Some(EllipticCurve::NistP256)
+            }
+            KeyMaterial::NistP384(_) | KeyMaterial::NistP384Public(_) => {
+                Some(EllipticCurve::NistP384)
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of exported and wrapped keys: raw bytes for symmetric keys, DER encoded PKCS#8 for private keys and
+    /// `SubjectPublicKeyInfo` for public keys.
+    fn value(&self) -> Result<Vec<u8>> {
+        let der = match *self {
+            KeyMaterial::Symmetric(ref key) => return Ok(key.clone()),
+            KeyMaterial::Rsa(ref key) => key.to_pkcs8_der(),
+            KeyMaterial::NistP256(ref key) => key.to_pkcs8_der(),
+            KeyMaterial::NistP384(ref key) => key.to_pkcs8_der(),
+            KeyMaterial::RsaPublic(_)
+            | KeyMaterial::NistP256Public(_)
+            | KeyMaterial::NistP384Public(_) => {
+                return self
+                    .public_key_der()
+                    .ok_or_else(|| bad_request("invalid public key"))
+            }
+        };
+        der.map(|der| der.as_bytes().to_vec())
+            .map_err(|e| bad_request(e.to_string()))
+    }
+
+    fn is_public_only(&self) -> bool {
+        matches!(
+            *self,
+            KeyMaterial::RsaPublic(_)
+                | KeyMaterial::NistP256Public(_)
+                | KeyMaterial::NistP384Public(_)
+        )
+    }
+}
+
+fn default_key_ops(obj_type: ObjectType) -> KeyOperations {
+    let ops = match obj_type {
+        ObjectType::Aes => {
+            KeyOperations::ENCRYPT
+                | KeyOperations::DECRYPT
+                | KeyOperations::WRAPKEY
+                | KeyOperations::UNWRAPKEY
+                | KeyOperations::MACGENERATE
+                | KeyOperations::MACVERIFY
+        }
+        ObjectType::Rsa => {
+            KeyOperations::SIGN
+                | KeyOperations::VERIFY
+                | KeyOperations::ENCRYPT
+                | KeyOperations::DECRYPT
+                | KeyOperations::WRAPKEY
+                | KeyOperations::UNWRAPKEY
+        }
+        ObjectType::Ec => KeyOperations::SIGN | KeyOperations::VERIFY,
+        ObjectType::Hmac => KeyOperations::MACGENERATE | KeyOperations::MACVERIFY,
+        _ => KeyOperations::EXPORT,
+    };
+    ops | KeyOperations::APPMANAGEABLE
+}
+
+struct LocalKey {
+    sobject: Sobject,
+    material: KeyMaterial,
+}
+
+impl LocalKey {
+    /// Checks that the key can be used for `op`. As with SDKMS, pre-active keys can not be used, and deactivated or
+    /// compromised keys can only be used to process existing data, e.g. to decrypt or verify.
+    fn check(&self, op: KeyOperations) -> Result<()> {
+        if !self.sobject.enabled {
+            return Err(bad_request("sobject is disabled"));
+        }
+        if !self.sobject.key_ops.contains(op) {
+            return Err(bad_request(format!(
+                "operation {:?} is not allowed for this sobject",
+                op
+            )));
+        }
+        let protecting = KeyOperations::ENCRYPT
+            | KeyOperations::SIGN
+            | KeyOperations::WRAPKEY
+            | KeyOperations::MACGENERATE
+            | KeyOperations::DERIVEKEY
+            | KeyOperations::AGREEKEY;
+        match self.sobject.effective_state(now()) {
+            SobjectState::Active => Ok(()),
+            SobjectState::Deactivated | SobjectState::Compromised if !protecting.intersects(op) => {
+                Ok(())
+            }
+            state => Err(bad_request(format!(
+                "operation {:?} is not allowed in state {:?}",
+                op, state
+            ))),
+        }
+    }
+
+    fn symmetric_key(&self) -> Result<&[u8]> {
+        match self.material {
+            KeyMaterial::Symmetric(ref key) => Ok(key),
+            _ => Err(bad_request("a symmetric key is required")),
+        }
+    }
+
+    /// Encrypts `plain`, returns the ciphertext, IV and tag.
+    #[allow(clippy::type_complexity)]
+    fn encrypt(
+        &self,
+        alg: Algorithm,
+        mode: Option<&CryptMode>,
+        plain: &[u8],
+        iv: Option<&[u8]>,
+        ad: Option<&[u8]>,
+        tag_len: Option<usize>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>)> {
+        Ok(match (alg, self.sobject.obj_type) {
+            (Algorithm::Aes, ObjectType::Aes) => {
+                let secret = self.symmetric_key()?;
+                match cipher_mode(mode)? {
+                    CipherMode::Gcm => {
+                        if tag_len.unwrap_or(GCM_TAG_LEN * 8) != GCM_TAG_LEN * 8 {
+                            return Err(unsupported("tag_len other than 128"));
+                        }
+                        let iv = iv.map_or_else(|| random_bytes(GCM_IV_LEN), |iv| iv.to_vec());
+                        if iv.len() != GCM_IV_LEN {
+                            return Err(unsupported("GCM with an IV length other than 96 bits"));
+                        }
+                        let ad = ad.unwrap_or_default();
+                        let (cipher, tag) = with_aes!(secret, gcm_encrypt(secret, &iv, ad, plain))?;
+                        (cipher, Some(iv), Some(tag))
+                    }
+                    mode @ CipherMode::Cbc | mode @ CipherMode::CbcNoPad => {
+                        let iv = iv.map_or_else(|| random_bytes(16), |iv| iv.to_vec());
+                        let padding = mode == CipherMode::Cbc;
+                        let cipher = with_aes!(secret, cbc_encrypt(secret, &iv, plain, padding))?;
+                        (cipher, Some(iv), None)
+                    }
+                    mode => return Err(unsupported(format!("cipher mode {:?}", mode))),
+                }
+            }
+            (Algorithm::Rsa, ObjectType::Rsa) => {
+                let public = self
+                    .material
+                    .rsa_public_key()
+                    .ok_or_else(|| bad_request("invalid RSA key"))?;
+                let padding = rsa_encryption_padding(mode)?;
+                (rsa_encrypt(&public, padding, plain)?, None, None)
+            }
+            (Algorithm::Aes, _) | (Algorithm::Rsa, _) => {
+                return Err(bad_request("alg does not match the key type"))
+            }
+            (alg, _) => return Err(unsupported(format!("algorithm {:?}", alg))),
+        })
+    }
+
+    fn decrypt(
+        &self,
+        alg: Option<Algorithm>,
+        mode: Option<&CryptMode>,
+        cipher: &[u8],
+        iv: Option<&[u8]>,
+        ad: Option<&[u8]>,
+        tag: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        if !matches!(alg, None | Some(Algorithm::Aes) | Some(Algorithm::Rsa)) {
+            return Err(unsupported(format!("algorithm {:?}", alg.unwrap())));
+        }
+        Ok(match (&self.material, self.sobject.obj_type) {
+            (KeyMaterial::Symmetric(ref secret), ObjectType::Aes)
+                if alg != Some(Algorithm::Rsa) =>
+            {
+                let iv = iv.ok_or_else(|| bad_request("iv is required"))?;
+                match cipher_mode(mode)? {
+                    CipherMode::Gcm => {
+                        let tag = tag.ok_or_else(|| bad_request("tag is required"))?;
+                        if iv.len() != GCM_IV_LEN {
+                            return Err(unsupported("GCM with an IV length other than 96 bits"));
+                        }
+                        if tag.len() != GCM_TAG_LEN {
+                            return Err(unsupported("tag length other than 128 bits"));
+                        }
+                        let ad = ad.unwrap_or_default();
+                        with_aes!(secret, gcm_decrypt(secret, iv, ad, cipher, tag))?
+                    }
+                    mode @ CipherMode::Cbc | mode @ CipherMode::CbcNoPad => {
+                        let padding = mode == CipherMode::Cbc;
+                        with_aes!(secret, cbc_decrypt(secret, iv, cipher, padding))?
+                    }
+                    mode => return Err(unsupported(format!("cipher mode {:?}", mode))),
+                }
+            }
+            (KeyMaterial::Rsa(ref private), _) if alg != Some(Algorithm::Aes) => {
+                let padding = rsa_encryption_padding(mode)?;
+                rsa_decrypt(private, padding, cipher)?
+            }
+            (KeyMaterial::RsaPublic(_), _) => {
+                return Err(bad_request("sobject has no private key"))
+            }
+            _ => return Err(bad_request("alg does not match the key type")),
+        })
+    }
+}
+
+/// Performs cryptographic operations in-process, with keys held in memory.
+///
+/// `LocalBackend` accepts the same requests as [`SdkmsClient`] and implements [`CryptoProvider`], so services can run
+/// locally without an SDKMS account and switch to SDKMS through configuration. Unlike the mock server, operations use
+/// real cryptography:
+/// - AES-GCM (96-bit IV, 128-bit tag), AES-CBC and AES-CBCNOPAD encryption
+/// - RSA encryption with OAEP or PKCS#1 v1.5 padding, RSA signatures with PSS or PKCS#1 v1.5 padding
+/// - ECDSA on NIST P-256 and P-384, with DER encoded signatures
+/// - HMAC and digests with SHA-1, SHA-2 and SHA-3
+///
+/// Keys are generated with [`create_sobject()`] or imported with [`import_sobject()`]. Transient keys are held until
+/// they are released with [`drop_transient_key()`] or the backend is dropped. Other algorithms and modes, like any
+/// invalid request, fail with [`Error::BadRequest`] as they would with SDKMS.
+///
+/// Keys never leave the process but are not otherwise protected. `LocalBackend` is meant for development and tests.
+///
+/// [`SdkmsClient`]: ../struct.SdkmsClient.html
+/// [`CryptoProvider`]: ../trait.CryptoProvider.html
+/// [`create_sobject()`]: #method.create_sobject
+/// [`import_sobject()`]: #method.import_sobject
+/// [`drop_transient_key()`]: ../trait.CryptoProvider.html#method.drop_transient_key
+/// [`Error::BadRequest`]: ../api_model/enum.Error.html#variant.BadRequest
+pub struct LocalBackend {
+    acct_id: Uuid,
+    app_id: Uuid,
+    keys: RwLock<HashMap<Uuid, LocalKey>>,
+    /// Transient keys, by the opaque `transient_key` handed out to callers.
+    transient_keys: RwLock<HashMap<Vec<u8>, LocalKey>>,
+}
+
+impl Default for LocalBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalBackend {
+    pub fn new() -> Self {
+        LocalBackend {
+            acct_id: Uuid::new_v4(),
+            app_id: Uuid::new_v4(),
+            keys: RwLock::new(HashMap::new()),
+            transient_keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The app that appears as the creator of security objects.
+    pub fn app_id(&self) -> Uuid {
+        self.app_id
+    }
+
+    #[cfg(test)]
+    pub(crate) fn transient_key_count(&self) -> usize {
+        self.transient_keys
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    fn insert(
+        &self,
+        req: &SobjectRequest,
+        obj_type: ObjectType,
+        material: KeyMaterial,
+        key_size: Option<u32>,
+        origin: ObjectOrigin,
+    ) -> Result<Sobject> {
+        let transient = req.transient == Some(true);
+        if !transient && req.name.is_none() {
+            return Err(bad_request("name is required"));
+        }
+        let kid = Uuid::new_v4();
+        let transient_key = if transient {
+            Some(random_bytes(32))
+        } else {
+            None
+        };
+        let sobject = Sobject {
+            acct_id: self.acct_id,
+            activation_date: req.activation_date,
+            compromise_date: None,
+            created_at: now(),
+            creator: Principal::App(self.app_id),
+            custom_metadata: req.custom_metadata.clone(),
+            deactivation_date: req.deactivation_date,
+            description: req.description.clone(),
+            deterministic_signatures: req.deterministic_signatures,
+            elliptic_curve: material.elliptic_curve(),
+            enabled: req.enabled.unwrap_or(true),
+            fpe: None,
+            key_ops: req.key_ops.unwrap_or_else(|| default_key_ops(obj_type)),
+            key_size,
+            kid: if transient { None } else { Some(kid) },
+            lastused_at: Time(0),
+            links: None,
+            name: req.name.clone(),
+            never_exportable: None,
+            obj_type,
+            origin,
+            pub_key: material.public_key_der().map(Blob::from),
+            public_only: material.is_public_only(),
+            publish_public_key: req.publish_public_key.clone(),
+            revocation_reason: None,
+            rsa: req.rsa.clone(),
+            state: Some(req.state.unwrap_or(SobjectState::Active)),
+            transient_key: transient_key.clone().map(Blob::from),
+            value: None,
+            group_id: req.group_id,
+        };
+        let key = LocalKey {
+            sobject: sobject.clone(),
+            material,
+        };
+        match transient_key {
+            None => {
+                let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+                if keys.values().any(|key| key.sobject.name == sobject.name) {
+                    return Err(error(
+                        StatusCode::CONFLICT,
+                        "an sobject with the same name already exists",
+                    ));
+                }
+                keys.insert(kid, key);
+            }
+            Some(transient_key) => {
+                self.transient_keys
+                    .write()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(transient_key, key);
+            }
+        }
+        Ok(sobject)
+    }
+
+    fn with_key<T, F>(&self, key: Option<&SobjectDescriptor>, f: F) -> Result<T>
+    where
+        F: FnOnce(&LocalKey) -> Result<T>,
+    {
+        let not_found = || error(StatusCode::NOT_FOUND, "sobject does not exist");
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        match key {
+            Some(SobjectDescriptor::Kid(kid)) => f(keys.get(kid).ok_or_else(not_found)?),
+            Some(SobjectDescriptor::Name(name)) => f(keys
+                .values()
+                .find(|key| key.sobject.name.as_ref() == Some(name))
+                .ok_or_else(not_found)?),
+            Some(SobjectDescriptor::TransientKey(transient_key)) => {
+                drop(keys);
+                let keys = self
+                    .transient_keys
+                    .read()
+                    .unwrap_or_else(|e| e.into_inner());
+                let key = keys
+                    .get(&transient_key[..])
+                    .ok_or_else(|| bad_request("invalid transient key"))?;
+                f(key)
+            }
+            None => Err(bad_request("key is required")),
+        }
+    }
+}
+
+fn gcm_encrypt<A>(key: &[u8], iv: &[u8], ad: &[u8], plain: &[u8]) -> Result<(Vec<u8>, Vec<u8>)>
+where
+    AesGcm<A, U12>: KeyInit + AeadInPlace,
+{
+    let cipher = AesGcm::<A, U12>::new_from_slice(key).map_err(|_| bad_request("invalid key"))?;
+    let mut buffer = plain.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(GenericArray::from_slice(iv), ad, &mut buffer)
+        .map_err(|_| bad_request("encryption failed"))?;
+    Ok((buffer, tag.to_vec()))
+}
+
+fn gcm_decrypt<A>(key: &[u8], iv: &[u8], ad: &[u8], cipher: &[u8], tag: &[u8]) -> Result<Vec<u8>>
+where
+    AesGcm<A, U12>: KeyInit + AeadInPlace,
+{
+    let aead = AesGcm::<A, U12>::new_from_slice(key).map_err(|_| bad_request("invalid key"))?;
+    let mut buffer = cipher.to_vec();
+    aead.decrypt_in_place_detached(
+        GenericArray::from_slice(iv),
+        ad,
+        &mut buffer,
+        GenericArray::from_slice(tag),
+    )
+    .map_err(|_| bad_request("Decryption failed: tag mismatch"))?;
+    Ok(buffer)
+}
+
+fn cbc_encrypt<C>(key: &[u8], iv: &[u8], plain: &[u8], padding: bool) -> Result<Vec<u8>>
+where
+    cbc::Encryptor<C>: KeyIvInit + BlockEncryptMut,
+    C: cbc::cipher::BlockEncryptMut + cbc::cipher::BlockCipher,
+{
+    let cipher = cbc::Encryptor::<C>::new_from_slices(key, iv)
+        .map_err(|_| bad_request("invalid key or iv"))?;
+    match padding {
+        true => Ok(cipher.encrypt_padded_vec_mut::<Pkcs7>(plain)),
+        false if plain.len() % 16 != 0 => Err(bad_request(
+            "plaintext length must be a multiple of the block size",
+        )),
+        false => Ok(cipher.encrypt_padded_vec_mut::<NoPadding>(plain)),
+    }
+}
+
+fn cbc_decrypt<C>(key: &[u8], iv: &[u8], cipher: &[u8], padding: bool) -> Result<Vec<u8>>
+where
+    cbc::Decryptor<C>: KeyIvInit + BlockDecryptMut,
+    C: cbc::cipher::BlockDecryptMut + cbc::cipher::BlockCipher,
+{
+    let decryptor = cbc::Decryptor::<C>::new_from_slices(key, iv)
+        .map_err(|_| bad_request("invalid key or iv"))?;
+    let plain = match padding {
+        true => decryptor.decrypt_padded_vec_mut::<Pkcs7>(cipher),
+        false => decryptor.decrypt_padded_vec_mut::<NoPadding>(cipher),
+    };
+    plain.map_err(|_| bad_request("Decryption failed: invalid padding"))
+}
+
+fn cipher_mode(mode: Option<&CryptMode>) -> Result<CipherMode> {
+    match mode {
+        Some(CryptMode::Symmetric(mode)) => Ok(*mode),
+        Some(CryptMode::Rsa(_)) => Err(bad_request("invalid mode for symmetric encryption")),
+        None => Err(bad_request("mode is required for symmetric encryption")),
+    }
+}
+
+fn rsa_encryption_padding(mode: Option<&CryptMode>) -> Result<RsaEncryptionPadding> {
+    match mode {
+        Some(CryptMode::Rsa(padding)) => Ok(*padding),
+        Some(CryptMode::Symmetric(_)) => Err(bad_request("invalid mode for RSA encryption")),
+        None => Ok(RsaEncryptionPadding::Oaep {
+            mgf: Mgf::Mgf1 {
+                hash: DigestAlgorithm::Sha1,
+            },
+        }),
+    }
+}
+
+fn rsa_encrypt(key: &RsaPublicKey, padding: RsaEncryptionPadding, plain: &[u8]) -> Result<Vec<u8>> {
+    let result = match padding {
+        RsaEncryptionPadding::Oaep {
+            mgf: Mgf::Mgf1 { hash },
+        } => with_digest!(hash, D => key.encrypt(&mut OsRng, Oaep::new::<D>(), plain)),
+        RsaEncryptionPadding::Pkcs1V15 {} => key.encrypt(&mut OsRng, Pkcs1v15Encrypt, plain),
+    };
+    result.map_err(|e| bad_request(format!("encryption failed: {}", e)))
+}
+
+fn rsa_decrypt(
+    key: &RsaPrivateKey,
+    padding: RsaEncryptionPadding,
+    cipher: &[u8],
+) -> Result<Vec<u8>> {
+    let result = match padding {
+        RsaEncryptionPadding::Oaep {
+            mgf: Mgf::Mgf1 { hash },
+        } => with_digest!(hash, D => key.decrypt(Oaep::new::<D>(), cipher)),
+        RsaEncryptionPadding::Pkcs1V15 {} => key.decrypt(Pkcs1v15Encrypt, cipher),
+    };
+    result.map_err(|_| bad_request("Decryption failed"))
+}
+
+fn rsa_signature_padding(
+    mode: Option<&SignatureMode>,
+    hash_alg: DigestAlgorithm,
+) -> Result<RsaSignaturePadding> {
+    match mode {
+        Some(SignatureMode::Rsa(RsaSignaturePadding::Pss {
+            mgf: Mgf::Mgf1 { hash },
+        })) if *hash != hash_alg => {
+            Err(unsupported("PSS with a MGF1 hash different from hash_alg"))
+        }
+        Some(SignatureMode::Rsa(padding)) => Ok(*padding),
+        None => Ok(RsaSignaturePadding::Pkcs1V15 {}),
+    }
+}
+
+/// The hash to sign or verify, computing it from `data` if needed.
+fn hash_to_sign(
+    hash_alg: DigestAlgorithm,
+    hash: Option<&Blob>,
+    data: Option<&Blob>,
+) -> Result<Vec<u8>> {
+    let hash = match (hash, data) {
+        (Some(hash), None) => hash.to_vec(),
+        (None, Some(data)) => digest(hash_alg, data)?,
+        _ => return Err(bad_request("exactly one of hash and data is required")),
+    };
+    if hash.len() != with_digest!(hash_alg, D => <D as Digest>::output_size()) {
+        return Err(bad_request("hash length does not match hash_alg"));
+    }
+    Ok(hash)
+}
+
+fn digest(alg: DigestAlgorithm, data: &[u8]) -> Result<Vec<u8>> {
+    Ok(with_digest!(alg, D => D::digest(data).to_vec()))
+}
+
+fn hmac(key: &[u8], alg: DigestAlgorithm, data: &[u8]) -> Result<Vec<u8>> {
+    Ok(with_digest!(alg, D => {
+        let mut mac = <Hmac<D> as Mac>::new_from_slice(key).map_err(|_| bad_request("invalid key"))?;
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }))
+}
+
+fn verify_error(_: impl std::fmt::Debug) -> Error {
+    bad_request("invalid signature")
+}
+
+impl CryptoProvider for LocalBackend {
+    fn encrypt(&self, req: &EncryptRequest) -> Result<EncryptResponse> {
+        self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::ENCRYPT)?;
+            let (cipher, iv, tag) = key.encrypt(
+                req.alg,
+                req.mode.as_ref(),
+                &req.plain,
+                req.iv.as_deref(),
+                req.ad.as_deref(),
+                req.tag_len,
+            )?;
+            Ok(EncryptResponse {
+                kid: key.sobject.kid,
+                cipher: cipher.into(),
+                iv: iv.map(Blob::from),
+                tag: tag.map(Blob::from),
+            })
+        })
+    }
+
+    fn decrypt(&self, req: &DecryptRequest) -> Result<DecryptResponse> {
+        self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::DECRYPT)?;
+            let plain = key.decrypt(
+                req.alg,
+                req.mode.as_ref(),
+                &req.cipher,
+                req.iv.as_deref(),
+                req.ad.as_deref(),
+                req.tag.as_deref(),
+            )?;
+            Ok(DecryptResponse {
+                kid: key.sobject.kid,
+                plain: plain.into(),
+            })
+        })
+    }
+
+    fn wrap(&self, req: &WrapKeyRequest) -> Result<WrapKeyResponse> {
+        let subject = match (&req.subject, req.kid) {
+            (Some(subject), None) => subject.clone(),
+            (None, Some(kid)) => SobjectDescriptor::Kid(kid),
+            (Some(_), Some(_)) => {
+                return Err(bad_request("subject and kid are mutually exclusive"))
+            }
+            (None, None) => return Err(bad_request("subject is required")),
+        };
+        let value = self.with_key(Some(&subject), |subject| {
+            subject.check(KeyOperations::EXPORT)?;
+            subject.material.value()
+        })?;
+        self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::WRAPKEY)?;
+            let (wrapped_key, iv, tag) = key.encrypt(
+                req.alg,
+                req.mode.as_ref(),
+                &value,
+                req.iv.as_deref(),
+                req.ad.as_deref(),
+                req.tag_len,
+            )?;
+            Ok(WrapKeyResponse {
+                wrapped_key: wrapped_key.into(),
+                iv: iv.map(Blob::from),
+                tag: tag.map(Blob::from),
+            })
+        })
+    }
+
+    fn unwrap(&self, req: &UnwrapKeyRequest) -> Result<Sobject> {
+        let value = self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::UNWRAPKEY)?;
+            key.decrypt(
+                Some(req.alg),
+                req.mode.as_ref(),
+                &req.wrapped_key,
+                req.iv.as_deref(),
+                req.ad.as_deref(),
+                req.tag.as_deref(),
+            )
+        })?;
+        let import = SobjectRequest {
+            custom_metadata: req.custom_metadata.clone(),
+            description: req.description.clone(),
+            enabled: req.enabled,
+            group_id: req.group_id,
+            key_ops: req.key_ops,
+            name: req.name.clone(),
+            obj_type: Some(req.obj_type),
+            rsa: req.rsa.clone(),
+            transient: req.transient,
+            ..Default::default()
+        };
+        let (material, key_size) = KeyMaterial::import(req.obj_type, &import, &value)?;
+        self.insert(
+            &import,
+            req.obj_type,
+            material,
+            key_size,
+            ObjectOrigin::External,
+        )
+    }
+
+    fn sign(&self, req: &SignRequest) -> Result<SignResponse> {
+        self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::SIGN)?;
+            let hash = hash_to_sign(req.hash_alg, req.hash.as_ref(), req.data.as_ref())?;
+            let signature = match key.material {
+                KeyMaterial::Rsa(ref private) => {
+                    let result = match rsa_signature_padding(req.mode.as_ref(), req.hash_alg)? {
+                        RsaSignaturePadding::Pkcs1V15 {} => {
+                            with_digest!(req.hash_alg, D => private.sign(Pkcs1v15Sign::new::<D>(), &hash))
+                        }
+                        RsaSignaturePadding::Pss { .. } => {
+                            with_digest!(req.hash_alg, D => private.sign_with_rng(&mut OsRng, Pss::new::<D>(), &hash))
+                        }
+                    };
+                    result.map_err(|e| bad_request(format!("signing failed: {}", e)))?
+                }
+                KeyMaterial::NistP256(ref private) => {
+                    let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(private)
+                        .sign_prehash(&hash)
+                        .map_err(|e| bad_request(format!("signing failed: {}", e)))?;
+                    signature.to_der().as_bytes().to_vec()
+                }
+                KeyMaterial::NistP384(ref private) => {
+                    let signature: p384::ecdsa::Signature = p384::ecdsa::SigningKey::from(private)
+                        .sign_prehash(&hash)
+                        .map_err(|e| bad_request(format!("signing failed: {}", e)))?;
+                    signature.to_der().as_bytes().to_vec()
+                }
+                KeyMaterial::RsaPublic(_) | KeyMaterial::NistP256Public(_) | KeyMaterial::NistP384Public(_) => {
+                    return Err(bad_request("sobject has no private key"))
+                }
+                KeyMaterial::Symmetric(_) => return Err(bad_request("an asymmetric key is required")),
+            };
+            Ok(SignResponse {
+                kid: key.sobject.kid,
+                signature: signature.into(),
+            })
+        })
+    }
+
+    fn verify(&self, req: &VerifyRequest) -> Result<VerifyResponse> {
+        self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::VERIFY)?;
+            let hash = hash_to_sign(req.hash_alg, req.hash.as_ref(), req.data.as_ref())?;
+            let signature = &req.signature[..];
+            let result = match key.material {
+                KeyMaterial::Rsa(_) | KeyMaterial::RsaPublic(_) => {
+                    let public = key.material.rsa_public_key().ok_or_else(|| bad_request("invalid RSA key"))?;
+                    let result = match rsa_signature_padding(req.mode.as_ref(), req.hash_alg)? {
+                        RsaSignaturePadding::Pkcs1V15 {} => {
+                            with_digest!(req.hash_alg, D => public.verify(Pkcs1v15Sign::new::<D>(), &hash, signature))
+                        }
+                        RsaSignaturePadding::Pss { .. } => {
+                            with_digest!(req.hash_alg, D => public.verify(Pss::new::<D>(), &hash, signature))
+                        }
+                    };
+                    result.is_ok()
+                }
+                KeyMaterial::NistP256(_) | KeyMaterial::NistP256Public(_) => {
+                    let public = match key.material {
+                        KeyMaterial::NistP256(ref private) => private.public_key(),
+                        KeyMaterial::NistP256Public(public) => public,
+                        _ => unreachable!(),
+                    };
+                    let signature = p256::ecdsa::Signature::from_der(signature).map_err(verify_error)?;
+                    p256::ecdsa::VerifyingKey::from(&public)
+                        .verify_prehash(&hash, &signature)
+                        .is_ok()
+                }
+                KeyMaterial::NistP384(_) | KeyMaterial::NistP384Public(_) => {
+                    let public = match key.material {
+                        KeyMaterial::NistP384(ref private) => private.public_key(),
+                        KeyMaterial::NistP384Public(public) => public,
+                        _ => unreachable!(),
+                    };
+                    let signature = p384::ecdsa::Signature::from_der(signature).map_err(verify_error)?;
+                    p384::ecdsa::VerifyingKey::from(&public)
+                        .verify_prehash(&hash, &signature)
+                        .is_ok()
+                }
+                KeyMaterial::Symmetric(_) => return Err(bad_request("an asymmetric key is required")),
+            };
+            Ok(VerifyResponse {
+                kid: key.sobject.kid,
+                result,
+            })
+        })
+    }
+
+    fn mac(&self, req: &MacRequest) -> Result<MacResponse> {
+        self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::MACGENERATE)?;
+            if key.sobject.obj_type != ObjectType::Hmac {
+                return Err(unsupported(format!(
+                    "MAC with {:?} keys",
+                    key.sobject.obj_type
+                )));
+            }
+            let alg = req
+                .alg
+                .ok_or_else(|| bad_request("alg is required for HMAC"))?;
+            Ok(MacResponse {
+                kid: key.sobject.kid,
+                digest: None,
+                mac: hmac(key.symmetric_key()?, alg, &req.data)?.into(),
+            })
+        })
+    }
+
+    fn mac_verify(&self, req: &VerifyMacRequest) -> Result<VerifyResponse> {
+        self.with_key(req.key.as_ref(), |key| {
+            key.check(KeyOperations::MACVERIFY)?;
+            if key.sobject.obj_type != ObjectType::Hmac {
+                return Err(unsupported(format!(
+                    "MAC with {:?} keys",
+                    key.sobject.obj_type
+                )));
+            }
+            let alg = req
+                .alg
+                .ok_or_else(|| bad_request("alg is required for HMAC"))?;
+            let mac = req
+                .mac
+                .as_ref()
+                .or(req.digest.as_ref())
+                .ok_or_else(|| bad_request("mac is required"))?;
+            let result = with_digest!(alg, D => {
+                let mut hmac = <Hmac<D> as Mac>::new_from_slice(key.symmetric_key()?)
+                    .map_err(|_| bad_request("invalid key"))?;
+                hmac.update(&req.data);
+                hmac.verify_slice(mac).is_ok()
+            });
+            Ok(VerifyResponse {
+                kid: key.sobject.kid,
+                result,
+            })
+        })
+    }
+
+    fn create_digest(&self, req: &DigestRequest) -> Result<DigestResponse> {
+        Ok(DigestResponse {
+            digest: digest(req.alg, &req.data)?.into(),
+        })
+    }
+
+    fn create_sobject(&self, req: &SobjectRequest) -> Result<Sobject> {
+        let obj_type = req
+            .obj_type
+            .ok_or_else(|| bad_request("obj_type is required"))?;
+        let (material, key_size) = KeyMaterial::generate(obj_type, req)?;
+        self.insert(req, obj_type, material, key_size, ObjectOrigin::FortanixHSM)
+    }
+
+    fn import_sobject(&self, req: &SobjectRequest) -> Result<Sobject> {
+        let obj_type = req
+            .obj_type
+            .ok_or_else(|| bad_request("obj_type is required"))?;
+        let value = req
+            .value
+            .as_ref()
+            .ok_or_else(|| bad_request("value is required"))?;
+        let (material, key_size) = KeyMaterial::import(obj_type, req, value)?;
+        self.insert(req, obj_type, material, key_size, ObjectOrigin::External)
+    }
+
+    fn get_sobject(
+        &self,
+        _query_params: Option<&GetSobjectParams>,
+        req: &SobjectDescriptor,
+    ) -> Result<Sobject> {
+        self.with_key(Some(req), |key| {
+            Ok(Sobject {
+                state: Some(key.sobject.effective_state(now())),
+                ..key.sobject.clone()
+            })
+        })
+    }
+
+    fn export_sobject(&self, req: &SobjectDescriptor) -> Result<Sobject> {
+        self.with_key(Some(req), |key| {
+            key.check(KeyOperations::EXPORT)?;
+            Ok(Sobject {
+                value: Some(key.material.value()?.into()),
+                ..key.sobject.clone()
+            })
+        })
+    }
+
+    fn delete_sobject(&self, id: &Uuid) -> Result<()> {
+        match self
+            .keys
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id)
+        {
+            Some(_) => Ok(()),
+            None => Err(error(StatusCode::NOT_FOUND, "sobject does not exist")),
+        }
+    }
+
+    fn drop_transient_key(&self, transient_key: &Blob) -> Result<()> {
+        match self
+            .transient_keys
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&transient_key[..])
+        {
+            Some(_) => Ok(()),
+            None => Err(bad_request("invalid transient key")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn import(backend: &LocalBackend, obj_type: ObjectType, value: &[u8]) -> SobjectDescriptor {
+        let sobject = backend
+            .import_sobject(&SobjectRequest {
+                name: Some(format!("{:?}", obj_type)),
+                obj_type: Some(obj_type),
+                value: Some(value.to_vec().into()),
+                ..Default::default()
+            })
+            .unwrap();
+        SobjectDescriptor::Kid(sobject.kid.unwrap())
+    }
+
+    #[test]
+    fn aes_gcm() {
+        let backend = LocalBackend::new();
+        let key = import(&backend, ObjectType::Aes, &[0; 16]);
+        let provider: &dyn CryptoProvider = &backend;
+        // test case 2 of the GCM specification
+        let mut req = EncryptRequest {
+            key: Some(key.clone()),
+            alg: Algorithm::Aes,
+            plain: vec![0; 16].into(),
+            mode: Some(CryptMode::Symmetric(CipherMode::Gcm)),
+            iv: Some(vec![0; 12].into()),
+            ad: None,
+            tag_len: None,
+        };
+        let encrypted = provider.encrypt(&req).unwrap();
+        assert_eq!(hex(&encrypted.cipher), "0388dace60b6a392f328c2b971b2fe78");
+        assert_eq!(
+            hex(encrypted.tag.as_ref().unwrap()),
+            "ab6e47d42cec13bdf53a67b21257bddf"
+        );
+
+        req.iv = None;
+        req.ad = Some("header".into());
+        let encrypted = provider.encrypt(&req).unwrap();
+        let mut decrypt = DecryptRequest {
+            key: Some(key),
+            cipher: encrypted.cipher,
+            mode: req.mode.clone(),
+            iv: encrypted.iv,
+            ad: req.ad.clone(),
+            tag: encrypted.tag,
+            alg: None,
+        };
+        assert_eq!(provider.decrypt(&decrypt).unwrap().plain, req.plain);
+        decrypt.ad = None;
+        assert!(matches!(
+            provider.decrypt(&decrypt),
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn hmac_sha256() {
+        let backend = LocalBackend::new();
+        let key = import(&backend, ObjectType::Hmac, b"Jefe");
+        // test case 2 of RFC 4231
+        let mac = backend
+            .mac(&MacRequest {
+                key: Some(key.clone()),
+                alg: Some(DigestAlgorithm::Sha256),
+                data: "what do ya want for nothing?".into(),
+            })
+            .unwrap()
+            .mac;
+        assert_eq!(
+            hex(&mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let verify = backend
+            .mac_verify(&VerifyMacRequest {
+                key: Some(key),
+                alg: Some(DigestAlgorithm::Sha256),
+                data: "what do ya want for nothing!".into(),
+                digest: None,
+                mac: Some(mac),
+            })
+            .unwrap();
+        assert!(!verify.result);
+    }
+
+    #[test]
+    fn key_states() {
+        let backend = LocalBackend::new();
+        let import = |name: &str, activation_date, deactivation_date| {
+            let req = SobjectRequest {
+                name: Some(name.to_owned()),
+                obj_type: Some(ObjectType::Hmac),
+                value: Some(vec![1; 32].into()),
+                activation_date,
+                deactivation_date,
+                ..Default::default()
+            };
+            let kid = backend.import_sobject(&req).unwrap().kid.unwrap();
+            SobjectDescriptor::Kid(kid)
+        };
+        let mac = |key: &SobjectDescriptor| {
+            backend.mac(&MacRequest {
+                key: Some(key.clone()),
+                alg: Some(DigestAlgorithm::Sha256),
+                data: "data".into(),
+            })
+        };
+        let mac_verify = |key: &SobjectDescriptor| {
+            backend.mac_verify(&VerifyMacRequest {
+                key: Some(key.clone()),
+                alg: Some(DigestAlgorithm::Sha256),
+                data: "data".into(),
+                digest: None,
+                mac: Some(vec![0; 32].into()),
+            })
+        };
+
+        let pending = import("pending", Some(Time(now().0 + 3600)), None);
+        assert!(mac(&pending).is_err());
+        assert!(mac_verify(&pending).is_err());
+        let expired = import("expired", None, Some(Time(1)));
+        let state = backend.get_sobject(None, &expired).unwrap().state;
+        assert_eq!(state, Some(SobjectState::Deactivated));
+        assert!(mac(&expired).is_err());
+        assert!(!mac_verify(&expired).unwrap().result);
+    }
+
+    #[test]
+    fn wrap_unwrap() {
+        let backend = LocalBackend::new();
+        let kek = import(&backend, ObjectType::Aes, &[7; 32]);
+        let ec = backend
+            .create_sobject(&SobjectRequest {
+                name: Some("ec".to_owned()),
+                obj_type: Some(ObjectType::Ec),
+                elliptic_curve: Some(EllipticCurve::NistP384),
+                key_ops: Some(KeyOperations::SIGN | KeyOperations::EXPORT),
+                ..Default::default()
+            })
+            .unwrap();
+        let mode = Some(CryptMode::Symmetric(CipherMode::Gcm));
+        let wrapped = backend
+            .wrap(&WrapKeyRequest {
+                key: Some(kek.clone()),
+                subject: Some(SobjectDescriptor::Name("ec".to_owned())),
+                kid: None,
+                alg: Algorithm::Aes,
+                mode: mode.clone(),
+                iv: None,
+                ad: None,
+                tag_len: Some(128),
+            })
+            .unwrap();
+        let unwrapped = backend
+            .unwrap(&UnwrapKeyRequest {
+                key: Some(kek.clone()),
+                alg: Algorithm::Aes,
+                obj_type: ObjectType::Ec,
+                rsa: None,
+                wrapped_key: wrapped.wrapped_key,
+                mode,
+                iv: wrapped.iv,
+                ad: None,
+                tag: wrapped.tag,
+                name: Some("unwrapped".to_owned()),
+                group_id: None,
+                enabled: None,
+                description: None,
+                custom_metadata: None,
+                key_ops: None,
+                transient: None,
+            })
+            .unwrap();
+        assert_eq!(unwrapped.elliptic_curve, Some(EllipticCurve::NistP384));
+        assert_eq!(unwrapped.pub_key, ec.pub_key);
+
+        // the KEK itself lacks EXPORT
+        let err = backend.wrap(&WrapKeyRequest {
+            key: Some(kek.clone()),
+            subject: Some(kek),
+            kid: None,
+            alg: Algorithm::Aes,
+            mode: Some(CryptMode::Symmetric(CipherMode::Cbc)),
+            iv: None,
+            ad: None,
+            tag_len: None,
+        });
+        assert!(matches!(err, Err(Error::BadRequest(_))));
+    }
+
+    #[test]
+    fn signatures() {
+        let backend = LocalBackend::new();
+        let ec = backend
+            .create_sobject(&SobjectRequest {
+                name: Some("ec".to_owned()),
+                obj_type: Some(ObjectType::Ec),
+                elliptic_curve: Some(EllipticCurve::NistP256),
+                ..Default::default()
+            })
+            .unwrap();
+        let rsa = backend
+            .create_sobject(&SobjectRequest {
+                name: Some("rsa".to_owned()),
+                obj_type: Some(ObjectType::Rsa),
+                key_size: Some(1024),
+                ..Default::default()
+            })
+            .unwrap();
+        let pss = SignatureMode::Rsa(RsaSignaturePadding::Pss {
+            mgf: Mgf::Mgf1 {
+                hash: DigestAlgorithm::Sha256,
+            },
+        });
+        for (sobject, mode) in [(ec, None), (rsa.clone(), None), (rsa, Some(pss))] {
+            let key = Some(SobjectDescriptor::Kid(sobject.kid.unwrap()));
+            let signature = backend
+                .sign(&SignRequest {
+                    key: key.clone(),
+                    hash_alg: DigestAlgorithm::Sha256,
+                    hash: None,
+                    data: Some("hello".into()),
+                    mode: mode.clone(),
+                    deterministic_signature: None,
+                })
+                .unwrap()
+                .signature;
+            let mut req = VerifyRequest {
+                key,
+                hash_alg: DigestAlgorithm::Sha256,
+                hash: Some(digest(DigestAlgorithm::Sha256, b"hello").unwrap().into()),
+                data: None,
+                mode,
+                signature,
+            };
+            assert!(backend.verify(&req).unwrap().result);
+            req.hash = Some(digest(DigestAlgorithm::Sha256, b"hello!").unwrap().into());
+            assert!(!backend.verify(&req).unwrap().result);
+        }
+
+        let key = Some(SobjectDescriptor::Name("rsa".to_owned()));
+        let encrypted = backend
+            .encrypt(&EncryptRequest {
+                key: key.clone(),
+                alg: Algorithm::Rsa,
+                plain: "hello".into(),
+                mode: None,
+                iv: None,
+                ad: None,
+                tag_len: None,
+            })
+            .unwrap();
+        assert_eq!(encrypted.cipher.len(), 128);
+        let decrypted = backend
+            .decrypt(&DecryptRequest {
+                key,
+                cipher: encrypted.cipher,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(decrypted.plain, Blob::from("hello"));
+    }
+}
@@ -0,0 +1,315 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Simulated cryptographic operations.
+//!
+//! None of this is secure: keys are random bytes, "encryption" is a XOR with a keystream derived from the key using
+//! a non-cryptographic hash, and signatures/MACs/digests are derived the same way. Outputs have plausible lengths
+//! and round trip through the mock server, which is all that tests of client code need.
+
+use super::state::Failure;
+use crate::api_model::*;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use uuid::Uuid;
+
+pub(super) fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len + 16);
+    while bytes.len() < len {
+        bytes.extend_from_slice(Uuid::new_v4().as_bytes());
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+fn block(key: &[u8], parts: &[&[u8]], index: u64) -> [u8; 8] {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    parts.hash(&mut hasher);
+    index.hash(&mut hasher);
+    hasher.finish().to_le_bytes()
+}
+
+/// Bytes `offset..offset + len` of the keystream derived from `key` and `parts`.
+fn keystream(key: &[u8], parts: &[&[u8]], offset: usize, len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len + 16);
+    let mut index = offset / 8;
+    while out.len() < len + offset % 8 {
+        out.extend_from_slice(&block(key, parts, index as u64));
+        index += 1;
+    }
+    out.drain(..offset % 8);
+    out.truncate(len);
+    out
+}
+
+fn xor(data: &[u8], keystream: &[u8]) -> Vec<u8> {
+    data.iter().zip(keystream).map(|(d, k)| d ^ k).collect()
+}
+
+fn digest_len(alg: DigestAlgorithm) -> usize {
+    match alg {
+        DigestAlgorithm::Ripemd160 | DigestAlgorithm::Sha1 => 20,
+        DigestAlgorithm::Ssl3 => 36,
+        DigestAlgorithm::Sha3_224 => 28,
+        DigestAlgorithm::Blake2b256
+        | DigestAlgorithm::Blake2s256
+        | DigestAlgorithm::Sha256
+        | DigestAlgorithm::Streebog256
+        | DigestAlgorithm::Sha3_256 => 32,
+        DigestAlgorithm::Blake2b384 | DigestAlgorithm::Sha384 | DigestAlgorithm::Sha3_384 => 48,
+        DigestAlgorithm::Blake2b512
+        | DigestAlgorithm::Sha512
+        | DigestAlgorithm::Streebog512
+        | DigestAlgorithm::Sha3_512 => 64,
+    }
+}
+
+fn alg_name(alg: DigestAlgorithm) -> Vec<u8> {
+    format!("{:?}", alg).into_bytes()
+}
+
+pub(super) fn digest(alg: DigestAlgorithm, data: &[u8]) -> Vec<u8> {
+    keystream(b"digest", &[&alg_name(alg), data], 0, digest_len(alg))
+}
+
+pub(super) fn mac(key: &[u8], alg: DigestAlgorithm, data: &[u8]) -> Vec<u8> {
+    keystream(key, &[b"mac", &alg_name(alg), data], 0, digest_len(alg))
+}
+
+/// Generates key material for a new security object, returns the (private) value and the public key if any.
+pub(super) fn generate_key(
+    obj_type: ObjectType,
+    key_size: Option<u32>,
+) -> Result<(Vec<u8>, Option<Vec<u8>>), Failure> {
+    let value = match obj_type {
+        ObjectType::Aes
+        | ObjectType::Des
+        | ObjectType::Des3
+        | ObjectType::Hmac
+        | ObjectType::Secret => {
+            let key_size = key_size.ok_or_else(|| Failure::bad_request("key_size is required"))?;
+            if key_size == 0 || key_size % 8 != 0 {
+                return Err(Failure::bad_request("invalid key_size"));
+            }
+            random_bytes(key_size as usize / 8)
+        }
+        ObjectType::Rsa | ObjectType::Ec => random_bytes(32),
+        ObjectType::Opaque | ObjectType::Certificate => {
+            return Err(Failure::bad_request(format!(
+                "{:?} objects can not be generated",
+                obj_type
+            )))
+        }
+    };
+    let pub_key = public_key(obj_type, &value);
+    Ok((value, pub_key))
+}
+
+pub(super) fn public_key(obj_type: ObjectType, value: &[u8]) -> Option<Vec<u8>> {
+    match obj_type {
+        ObjectType::Rsa | ObjectType::Ec => Some(keystream(value, &[b"public"], 0, 64)),
+        _ => None,
+    }
+}
+
+/// Parameters of a symmetric or asymmetric encryption.
+pub(super) struct Cipher<'a> {
+    pub key: &'a [u8],
+    pub obj_type: ObjectType,
+    pub mode: Option<CryptMode>,
+}
+
+pub(super) struct Encrypted {
+    pub cipher: Vec<u8>,
+    pub iv: Option<Vec<u8>>,
+    pub tag: Option<Vec<u8>>,
+}
+
+impl<'a> Cipher<'a> {
+    fn symmetric_mode(&self) -> Result<Option<CipherMode>, Failure> {
+        match (self.obj_type, &self.mode) {
+            (ObjectType::Aes, Some(CryptMode::Symmetric(mode)))
+            | (ObjectType::Des, Some(CryptMode::Symmetric(mode)))
+            | (ObjectType::Des3, Some(CryptMode::Symmetric(mode))) => Ok(Some(*mode)),
+            (ObjectType::Aes, _) | (ObjectType::Des, _) | (ObjectType::Des3, _) => Err(
+                Failure::bad_request("mode is required for symmetric encryption"),
+            ),
+            (ObjectType::Rsa, Some(CryptMode::Symmetric(_))) => {
+                Err(Failure::bad_request("invalid mode for RSA encryption"))
+            }
+            (ObjectType::Rsa, _) => Ok(None),
+            (obj_type, _) => Err(Failure::bad_request(format!(
+                "{:?} keys can not be used for encryption",
+                obj_type
+            ))),
+        }
+    }
+
+    fn iv_len(&self, mode: CipherMode) -> usize {
+        match (self.obj_type, mode) {
+            (_, CipherMode::Gcm) | (_, CipherMode::Ccm) => 12,
+            (ObjectType::Aes, _) => 16,
+            _ => 8,
+        }
+    }
+
+    pub fn encrypt(
+        &self,
+        plain: &[u8],
+        iv: Option<&[u8]>,
+        ad: Option<&[u8]>,
+        tag_len: Option<usize>,
+    ) -> Result<Encrypted, Failure> {
+        match self.symmetric_mode()? {
+            Some(mode) => {
+                let iv = match iv {
+                    Some(iv) => iv.to_vec(),
+                    None => random_bytes(self.iv_len(mode)),
+                };
+                let cipher = self.update(&iv, 0, plain);
+                let tag = match mode {
+                    CipherMode::Gcm | CipherMode::Ccm => {
+                        let tag_len = tag_len.unwrap_or(128);
+                        if ![32, 64, 96, 104, 112, 120, 128].contains(&tag_len) {
+                            return Err(Failure::bad_request("invalid tag_len"));
+                        }
+                        Some(self.tag(&iv, ad.unwrap_or(&[]), &cipher, tag_len / 8))
+                    }
+                    _ => None,
+                };
+                Ok(Encrypted {
+                    cipher,
+                    iv: Some(iv),
+                    tag,
+                })
+            }
+            None => {
+                let mut cipher = random_bytes(16);
+                let stream = keystream(self.key, &[b"rsa", &cipher], 0, plain.len());
+                cipher.extend(xor(plain, &stream));
+                Ok(Encrypted {
+                    cipher,
+                    iv: None,
+                    tag: None,
+                })
+            }
+        }
+    }
+
+    pub fn decrypt(
+        &self,
+        cipher: &[u8],
+        iv: Option<&[u8]>,
+        ad: Option<&[u8]>,
+        tag: Option<&[u8]>,
+    ) -> Result<Vec<u8>, Failure> {
+        match self.symmetric_mode()? {
+            Some(mode) => {
+                let iv = iv.ok_or_else(|| Failure::bad_request("iv is required"))?;
+                if let CipherMode::Gcm | CipherMode::Ccm = mode {
+                    let tag = tag.ok_or_else(|| Failure::bad_request("tag is required"))?;
+                    if tag.is_empty() || self.tag(iv, ad.unwrap_or(&[]), cipher, tag.len()) != tag {
+                        return Err(Failure::bad_request("Decryption failed: tag mismatch"));
+                    }
+                }
+                Ok(self.update(iv, 0, cipher))
+            }
+            None => {
+                if cipher.len() < 16 {
+                    return Err(Failure::bad_request(
+                        "Decryption failed: invalid ciphertext",
+                    ));
+                }
+                let (nonce, cipher) = cipher.split_at(16);
+                let stream = keystream(self.key, &[b"rsa", nonce], 0, cipher.len());
+                Ok(xor(cipher, &stream))
+            }
+        }
+    }
+
+    /// Encrypts or decrypts `data` at `offset` of a multi-part operation.
+    pub fn update(&self, iv: &[u8], offset: usize, data: &[u8]) -> Vec<u8> {
+        xor(
+            data,
+            &keystream(self.key, &[b"enc", iv], offset, data.len()),
+        )
+    }
+
+    fn tag(&self, iv: &[u8], ad: &[u8], cipher: &[u8], len: usize) -> Vec<u8> {
+        keystream(self.key, &[b"tag", iv, ad, cipher], 0, len)
+    }
+}
+
+fn signature_len(obj_type: ObjectType, key_size: Option<u32>) -> usize {
+    match obj_type {
+        ObjectType::Rsa => key_size.unwrap_or(2048) as usize / 8,
+        _ => 64,
+    }
+}
+
+pub(super) fn sign(
+    key: &[u8],
+    obj_type: ObjectType,
+    key_size: Option<u32>,
+    hash_alg: DigestAlgorithm,
+    hash: &[u8],
+) -> Result<Vec<u8>, Failure> {
+    match obj_type {
+        ObjectType::Rsa | ObjectType::Ec => {}
+        _ => {
+            return Err(Failure::bad_request(format!(
+                "{:?} keys can not be used for signing",
+                obj_type
+            )))
+        }
+    }
+    if hash.len() != digest_len(hash_alg) {
+        return Err(Failure::bad_request("hash length does not match hash_alg"));
+    }
+    let public = public_key(obj_type, key).unwrap_or_default();
+    Ok(keystream(
+        &public,
+        &[b"sig", &alg_name(hash_alg), hash],
+        0,
+        signature_len(obj_type, key_size),
+    ))
+}
+
+/// Verifies a signature made by `sign()`, using only the public key.
+pub(super) fn verify(
+    pub_key: &[u8],
+    obj_type: ObjectType,
+    key_size: Option<u32>,
+    hash_alg: DigestAlgorithm,
+    hash: &[u8],
+    signature: &[u8],
+) -> bool {
+    let expected = keystream(
+        pub_key,
+        &[b"sig", &alg_name(hash_alg), hash],
+        0,
+        signature_len(obj_type, key_size),
+    );
+    expected == signature
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keystream_offsets() {
+        let full = keystream(b"key", &[b"iv"], 0, 100);
+        for offset in 0..20 {
+            assert_eq!(
+                keystream(b"key", &[b"iv"], offset, 37)[..],
+                full[offset..offset + 37]
+            );
+        }
+    }
+}
@@ -0,0 +1,457 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! An in-process mock of the SDKMS REST API, for testing code built on [`SdkmsClient`] without access to SDKMS.
+//!
+//! [`MockServer`] listens on a local port and keeps security objects, groups, apps and approval requests in memory.
+//! It implements the `/crypto/v1/*` and `/sys/v1/*` endpoints used by applications (sessions, groups, apps, approval
+//! requests, key management and cryptographic operations). Requests for other endpoints fail with `404 Not Found`.
+//!
+//! The server enforces what client code is most likely to depend on: authentication, session expiry, `key_ops`,
+//! key states and approval policies. Cryptographic operations are simulated and are **not secure**: ciphertexts,
+//! signatures and MACs round trip through the mock server but can not be verified with real cryptographic libraries.
+//!
+//! This module is only available with the `mock` feature.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use sdkms::api_model::*;
+//! use sdkms::mock::MockServer;
+//!
+//! let server = MockServer::start()?;
+//! let client = server.client()?;
+//! let key = client.create_sobject(&SobjectRequest {
+//!     name: Some("test key".to_owned()),
+//!     obj_type: Some(ObjectType::Aes),
+//!     key_size: Some(256),
+//!     ..Default::default()
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SdkmsClient`]: ../struct.SdkmsClient.html
+//! [`MockServer`]: ./struct.MockServer.html
+
+mod crypto;
+mod state;
+
+use self::state::State;
+use crate::api_model::ApprovalPolicy;
+use crate::client::{Result, SdkmsClient};
+
+use simple_hyper_client::blocking::Client as HttpClient;
+use simple_hyper_client::{HttpConnector, Method, StatusCode};
+use uuid::Uuid;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// An in-process SDKMS server for tests, see the [module documentation](./index.html).
+///
+/// The server comes with a default group and a default app that has all permissions in that group. The server stops
+/// when this value is dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Starts a server listening on a random local port.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::new()));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let state = state.clone();
+            let stopped = stopped.clone();
+            thread::Builder::new()
+                .name("sdkms-mock".to_owned())
+                .spawn(move || accept(listener, state, stopped))?
+        };
+        Ok(MockServer {
+            addr,
+            state,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    /// The API endpoint to use with [`SdkmsClientBuilder::with_api_endpoint()`].
+    ///
+    /// [`SdkmsClientBuilder::with_api_endpoint()`]: ../struct.SdkmsClientBuilder.html#method.with_api_endpoint
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The ID of the default app.
+    pub fn app_id(&self) -> Uuid {
+        self.state().app_id
+    }
+
+    /// The secret of the default app.
+    pub fn app_secret(&self) -> String {
+        self.state().app_secret.clone()
+    }
+
+    /// The API key of the default app.
+    pub fn api_key(&self) -> String {
+        let state = self.state();
+        base64::encode(format!("{}:{}", state.app_id, state.app_secret))
+    }
+
+    /// The ID of the default group.
+    pub fn group_id(&self) -> Uuid {
+        self.state().group_id
+    }
+
+    /// The HTTP client to use with [`SdkmsClientBuilder::with_http_client()`].
+    ///
+    /// [`SdkmsClientBuilder::with_http_client()`]: ../struct.SdkmsClientBuilder.html#method.with_http_client
+    pub fn http_client(&self) -> HttpClient {
+        HttpClient::with_connector(HttpConnector::new())
+    }
+
+    /// A client authenticated with the API key of the default app, without a session.
+    pub fn client(&self) -> Result<SdkmsClient> {
+        SdkmsClient::builder()
+            .with_http_client(self.http_client())
+            .with_api_endpoint(&self.url())
+            .with_api_key(&self.api_key())
+            .build()
+    }
+
+    /// An async client authenticated with the API key of the default app, without a session.
+    #[cfg(feature = "async")]
+    pub fn async_client(&self) -> Result<crate::AsyncSdkmsClient> {
+        crate::AsyncSdkmsClient::builder()
+            .with_http_client(simple_hyper_client::Client::with_connector(
+                HttpConnector::new(),
+            ))
+            .with_api_endpoint(&self.url())
+            .with_api_key(&self.api_key())
+            .build()
+    }
+
+    /// Sets the lifetime in seconds of sessions established from now on. The default is 600 seconds.
+    pub fn set_session_lifetime(&self, seconds: u32) {
+        self.state().session_lifetime = seconds;
+    }
+
+    /// Expires all sessions, subsequent requests made with a session fail with `401 Unauthorized`.
+    pub fn expire_sessions(&self) {
+        self.state().expire_sessions();
+    }
+
+    /// Sets the approval policy of a group, returns `false` if the group does not exist. With the mock server a
+    /// single approval satisfies any policy.
+    pub fn set_approval_policy(&self, group_id: Uuid, policy: Option<ApprovalPolicy>) -> bool {
+        self.state().set_approval_policy(group_id, policy)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // wake up the accept loop
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn accept(listener: TcpListener, state: Arc<Mutex<State>>, stopped: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if stopped.load(Ordering::SeqCst) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("mock server failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let state = state.clone();
+        let stopped = stopped.clone();
+        thread::spawn(move || {
+            if let Err(e) = serve(stream, &state, &stopped) {
+                debug!("mock server connection closed: {}", e);
+            }
+        });
+    }
+}
+
+struct Request {
+    method: Method,
+    target: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+    keep_alive: bool,
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Reads a request, returns `None` if the connection was closed.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) => (method, target.to_owned(), version),
+        _ => return Err(invalid_data("invalid request line")),
+    };
+    let method = Method::from_str(method).map_err(invalid_data)?;
+    let mut keep_alive = version != "HTTP/1.0";
+    let mut authorization = None;
+    let mut content_length = 0;
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = match header.find(':') {
+            Some(i) => (header[..i].to_ascii_lowercase(), header[i + 1..].trim()),
+            None => return Err(invalid_data("invalid header")),
+        };
+        match &name[..] {
+            "authorization" => authorization = Some(value.to_owned()),
+            "content-length" => content_length = value.parse().map_err(invalid_data)?,
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "connection" => keep_alive = !value.eq_ignore_ascii_case("close"),
+            _ => {}
+        }
+    }
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size)?;
+            let size = size.trim_end().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16).map_err(invalid_data)?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk)?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body)?;
+    }
+    Ok(Some(Request {
+        method,
+        target,
+        authorization,
+        body,
+        keep_alive,
+    }))
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>, stopped: &AtomicBool) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    while let Some(request) = read_request(&mut reader)? {
+        if stopped.load(Ordering::SeqCst) {
+            break;
+        }
+        let result = {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            state.handle(
+                request.method.clone(),
+                &request.target,
+                request.authorization.as_deref(),
+                request.body,
+            )
+        };
+        let (status, content_type, body) = match result {
+            Ok(serde_json::Value::Null) => (StatusCode::NO_CONTENT, None, Vec::new()),
+            Ok(value) => (
+                StatusCode::OK,
+                Some("application/json"),
+                serde_json::to_vec(&value).map_err(invalid_data)?,
+            ),
+            Err(failure) => (
+                failure.status,
+                Some("text/plain"),
+                failure.message.into_bytes(),
+            ),
+        };
+        debug!(
+            "mock server: {} {} {}",
+            status.as_u16(),
+            request.method,
+            request.target
+        );
+        let mut response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Length: {}\r\n",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default(),
+            body.len()
+        );
+        if let Some(content_type) = content_type {
+            response.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        if !request.keep_alive {
+            response.push_str("Connection: close\r\n");
+        }
+        response.push_str("\r\n");
+        writer.write_all(response.as_bytes())?;
+        writer.write_all(&body)?;
+        writer.flush()?;
+        if !request.keep_alive {
+            break;
+        }
+    }
+    let _ = writer.shutdown(Shutdown::Both);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_model::*;
+    use crate::Error;
+
+    fn create_key(client: &SdkmsClient, name: &str) -> Sobject {
+        client
+            .create_sobject(&SobjectRequest {
+                name: Some(name.to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    fn encrypt_request(kid: Uuid) -> EncryptRequest {
+        EncryptRequest {
+            plain: "hello, world!".into(),
+            alg: Algorithm::Aes,
+            key: Some(SobjectDescriptor::Kid(kid)),
+            mode: Some(CryptMode::Symmetric(CipherMode::Gcm)),
+            iv: None,
+            ad: None,
+            tag_len: None,
+        }
+    }
+
+    #[test]
+    fn keys_and_crypto() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let key = create_key(&client, "key");
+        assert_eq!(key.group_id, Some(server.group_id()));
+        let kid = key.kid.unwrap();
+
+        let encrypted = client.encrypt(&encrypt_request(kid)).unwrap();
+        let mut req = DecryptRequest {
+            cipher: encrypted.cipher,
+            iv: encrypted.iv,
+            key: Some(SobjectDescriptor::Name("key".to_owned())),
+            mode: Some(CryptMode::Symmetric(CipherMode::Gcm)),
+            alg: None,
+            ad: None,
+            tag: encrypted.tag,
+        };
+        assert_eq!(
+            client.decrypt(&req).unwrap().plain,
+            Blob::from("hello, world!")
+        );
+        req.ad = Some("other".into());
+        assert!(matches!(client.decrypt(&req), Err(Error::BadRequest(_))));
+
+        match client.create_sobject(&SobjectRequest {
+            name: Some("key".to_owned()),
+            obj_type: Some(ObjectType::Aes),
+            ..Default::default()
+        }) {
+            Err(Error::Conflict(e)) => assert_eq!(e.path(), Some("/crypto/v1/keys")),
+            other => panic!("unexpected result: {:?}", other.map(|s| s.kid)),
+        }
+
+        for i in 0..5 {
+            create_key(&client, &format!("key {}", i));
+        }
+        let params = ListSobjectsParams {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut kids: Vec<Uuid> = client
+            .iter_sobjects(params)
+            .map(|s| s.unwrap().kid.unwrap())
+            .collect();
+        assert_eq!(kids.len(), 6);
+        kids.dedup();
+        assert_eq!(kids.len(), 6);
+    }
+
+    #[test]
+    fn approval_required() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let kid = create_key(&client, "key").kid.unwrap();
+        let policy = ApprovalPolicy {
+            quorum: None,
+            user: None,
+            app: Some(server.app_id()),
+        };
+        assert!(server.set_approval_policy(server.group_id(), Some(policy)));
+
+        let req = encrypt_request(kid);
+        assert!(matches!(
+            client.encrypt(&req),
+            Err(Error::ApprovalRequired(_))
+        ));
+        let pending = client.request_approval_to_encrypt(&req, None).unwrap();
+        assert_eq!(pending.status(&client).unwrap(), ApprovalStatus::Pending);
+        client
+            .approve_request(&pending.request_id(), &ApproveRequest::default())
+            .unwrap();
+        let encrypted = pending.result(&client).unwrap().unwrap();
+        assert_eq!(encrypted.kid, Some(kid));
+    }
+
+    #[test]
+    fn session_expiry() {
+        let server = MockServer::start().unwrap();
+        let client = SdkmsClient::builder()
+            .with_http_client(server.http_client())
+            .with_api_endpoint(&server.url())
+            .with_auto_reauth(true)
+            .build()
+            .unwrap()
+            .authenticate_with_api_key(&server.api_key())
+            .unwrap();
+        assert_eq!(client.entity_id(), Some(server.app_id()));
+        create_key(&client, "key");
+        server.expire_sessions();
+        create_key(&client, "other key");
+
+        let client = client.authenticate_app(&server.app_id(), "wrong secret");
+        assert!(matches!(client, Err(Error::Unauthorized(_))));
+    }
+}
@@ -0,0 +1,1730 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::crypto::{self, Cipher};
+use crate::api_model::*;
+use crate::client::now;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use simple_hyper_client::{Method, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use uuid::Uuid;
+
+const DEFAULT_SESSION_LIFETIME: u32 = 600;
+const APPROVAL_REQUEST_LIFETIME: u64 = 24 * 60 * 60;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// An error response.
+#[derive(Debug)]
+pub(super) struct Failure {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new<M: Into<String>>(status: StatusCode, message: M) -> Self {
+        Failure {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request<M: Into<String>>(message: M) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn not_found<M: Into<String>>(message: M) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn conflict<M: Into<String>>(message: M) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    fn unauthorized() -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "Unauthorized")
+    }
+
+    fn approval_required() -> Self {
+        Self::new(StatusCode::FORBIDDEN, APPROVAL_REQUIRED_MESSAGE)
+    }
+}
+
+type Outcome = Result<Value, Failure>;
+
+/// A request made by an authenticated app.
+struct Call {
+    caller: Uuid,
+    method: Method,
+    path: String,
+    query: HashMap<String, String>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+    /// Whether the request is executed as the result of an approved approval request.
+    approved: bool,
+}
+
+impl Call {
+    fn body<T: DeserializeOwned>(&self) -> Result<T, Failure> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| Failure::bad_request(format!("invalid request body: {}", e)))
+    }
+
+    fn query<T: FromStr>(&self, name: &str) -> Result<Option<T>, Failure> {
+        match self.query.get(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| Failure::bad_request(format!("invalid `{}` parameter", name))),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the sort field, order and start cursor of a list request.
+    fn sort(&self, default_field: &str) -> Result<(String, Order, Option<String>), Failure> {
+        let (field, order) = match self.query.get("sort") {
+            Some(sort) => {
+                let mut parts = sort.splitn(2, ':');
+                let field = parts.next().unwrap_or(default_field).to_owned();
+                let order = parts
+                    .next()
+                    .unwrap_or("asc")
+                    .parse()
+                    .map_err(|_| Failure::bad_request("invalid `sort` parameter"))?;
+                (field, order)
+            }
+            None => (default_field.to_owned(), Order::Ascending),
+        };
+        Ok((field, order, self.query.get("start").cloned()))
+    }
+
+    fn page<T>(&self, items: Vec<T>) -> Result<Vec<T>, Failure> {
+        let offset = self.query("offset")?.unwrap_or(0);
+        let limit = self.query("limit")?.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        Ok(items.into_iter().skip(offset).take(limit).collect())
+    }
+}
+
+/// Sorts `items` by `key` and drops the items before `start`. The start item itself is included.
+fn sorted_from<T, K: Ord, F: Fn(&T) -> K>(
+    mut items: Vec<T>,
+    key: F,
+    order: Order,
+    start: Option<K>,
+) -> Vec<T> {
+    items.sort_by_key(|item| key(item));
+    if order == Order::Descending {
+        items.reverse();
+    }
+    match start {
+        Some(start) => items
+            .into_iter()
+            .filter(|item| match order {
+                Order::Ascending => key(item) >= start,
+                Order::Descending => key(item) <= start,
+            })
+            .collect(),
+        None => items,
+    }
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, Failure> {
+    Uuid::parse_str(id).map_err(|_| Failure::bad_request(format!("invalid id `{}`", id)))
+}
+
+fn json<T: Serialize>(value: T) -> Outcome {
+    serde_json::to_value(value)
+        .map_err(|e| Failure::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+fn algorithm_matches(alg: Algorithm, obj_type: ObjectType) -> bool {
+    matches!(
+        (alg, obj_type),
+        (Algorithm::Aes, ObjectType::Aes)
+            | (Algorithm::Des, ObjectType::Des)
+            | (Algorithm::Des3, ObjectType::Des3)
+            | (Algorithm::Rsa, ObjectType::Rsa)
+            | (Algorithm::Ec, ObjectType::Ec)
+            | (Algorithm::Hmac, ObjectType::Hmac)
+    )
+}
+
+fn default_key_size(
+    obj_type: ObjectType,
+    curve: Option<EllipticCurve>,
+) -> Result<Option<u32>, Failure> {
+    Ok(match obj_type {
+        ObjectType::Aes | ObjectType::Hmac => Some(256),
+        ObjectType::Des => Some(64),
+        ObjectType::Des3 => Some(192),
+        ObjectType::Rsa => Some(2048),
+        ObjectType::Ec => {
+            let curve = curve.ok_or_else(|| Failure::bad_request("elliptic_curve is required"))?;
+            Some(match curve {
+                EllipticCurve::X25519 | EllipticCurve::Ed25519 => 255,
+                EllipticCurve::X448 => 448,
+                EllipticCurve::SecP192K1 | EllipticCurve::NistP192 => 192,
+                EllipticCurve::SecP224K1 | EllipticCurve::NistP224 => 224,
+                EllipticCurve::SecP256K1 | EllipticCurve::NistP256 | EllipticCurve::Gost256A => 256,
+                EllipticCurve::NistP384 => 384,
+                EllipticCurve::NistP521 => 521,
+            })
+        }
+        ObjectType::Secret | ObjectType::Opaque | ObjectType::Certificate => None,
+    })
+}
+
+fn default_key_ops(obj_type: ObjectType) -> KeyOperations {
+    let ops = match obj_type {
+        ObjectType::Aes | ObjectType::Des | ObjectType::Des3 => {
+            KeyOperations::ENCRYPT
+                | KeyOperations::DECRYPT
+                | KeyOperations::WRAPKEY
+                | KeyOperations::UNWRAPKEY
+                | KeyOperations::DERIVEKEY
+                | KeyOperations::MACGENERATE
+                | KeyOperations::MACVERIFY
+        }
+        ObjectType::Rsa => {
+            KeyOperations::SIGN
+                | KeyOperations::VERIFY
+                | KeyOperations::ENCRYPT
+                | KeyOperations::DECRYPT
+                | KeyOperations::WRAPKEY
+                | KeyOperations::UNWRAPKEY
+        }
+        ObjectType::Ec => KeyOperations::SIGN | KeyOperations::VERIFY | KeyOperations::AGREEKEY,
+        ObjectType::Hmac => KeyOperations::MACGENERATE | KeyOperations::MACVERIFY,
+        ObjectType::Secret | ObjectType::Opaque | ObjectType::Certificate => KeyOperations::EXPORT,
+    };
+    ops | KeyOperations::APPMANAGEABLE
+}
+
+struct Session {
+    app_id: Uuid,
+    expires_at: u64,
+}
+
+struct StoredApp {
+    app: App,
+    secret: String,
+}
+
+struct StoredSobject {
+    sobject: Sobject,
+    value: Vec<u8>,
+}
+
+struct StoredApprovalRequest {
+    request: ApprovalRequest,
+    result: Option<ApprovableResult>,
+}
+
+/// State of a multi-part encryption or decryption, opaque to clients.
+#[derive(Serialize, Deserialize)]
+struct StreamState {
+    kid: Uuid,
+    iv: Blob,
+    offset: usize,
+    decrypt: bool,
+}
+
+impl StreamState {
+    fn decode(state: &Blob, decrypt: bool) -> Result<Self, Failure> {
+        serde_json::from_slice::<StreamState>(state)
+            .ok()
+            .filter(|state| state.decrypt == decrypt)
+            .ok_or_else(|| Failure::bad_request("invalid state"))
+    }
+
+    fn encode(&self) -> Blob {
+        serde_json::to_vec(self).expect("serializable state").into()
+    }
+}
+
+pub(super) struct State {
+    acct_id: Uuid,
+    pub(super) app_id: Uuid,
+    pub(super) app_secret: String,
+    pub(super) group_id: Uuid,
+    pub(super) session_lifetime: u32,
+    sessions: HashMap<String, Session>,
+    groups: HashMap<Uuid, Group>,
+    apps: HashMap<Uuid, StoredApp>,
+    sobjects: HashMap<Uuid, StoredSobject>,
+    approval_requests: HashMap<Uuid, StoredApprovalRequest>,
+}
+
+impl State {
+    pub(super) fn new() -> Self {
+        let mut state = State {
+            acct_id: Uuid::new_v4(),
+            app_id: Uuid::new_v4(),
+            app_secret: String::new(),
+            group_id: Uuid::new_v4(),
+            session_lifetime: DEFAULT_SESSION_LIFETIME,
+            sessions: HashMap::new(),
+            groups: HashMap::new(),
+            apps: HashMap::new(),
+            sobjects: HashMap::new(),
+            approval_requests: HashMap::new(),
+        };
+        let (app_id, group_id) = (state.app_id, state.group_id);
+        state.groups.insert(
+            group_id,
+            Group {
+                acct_id: state.acct_id,
+                approval_policy: None,
+                created_at: now(),
+                creator: Principal::App(app_id),
+                description: None,
+                group_id,
+                name: "Default group".to_owned(),
+            },
+        );
+        let mut groups = HashMap::new();
+        groups.insert(group_id, Some(AppPermissions::all()));
+        let app = state.new_app(app_id, app_id, "Default app".to_owned(), groups.into());
+        state.app_secret = app.secret.clone();
+        state.apps.insert(app_id, app);
+        state
+    }
+
+    pub(super) fn expire_sessions(&mut self) {
+        self.sessions.clear();
+    }
+
+    pub(super) fn set_approval_policy(
+        &mut self,
+        group_id: Uuid,
+        policy: Option<ApprovalPolicy>,
+    ) -> bool {
+        match self.groups.get_mut(&group_id) {
+            Some(group) => {
+                group.approval_policy = policy;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Handles a request, returning the JSON response body or an error.
+    pub(super) fn handle(
+        &mut self,
+        method: Method,
+        target: &str,
+        authorization: Option<&str>,
+        body: Vec<u8>,
+    ) -> Outcome {
+        let (path, query) = match target.find('?') {
+            Some(i) => (&target[..i], &target[i + 1..]),
+            None => (target, ""),
+        };
+        let query = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        match (&method, path) {
+            (&Method::POST, "/sys/v1/session/auth") => return self.session_auth(authorization),
+            (&Method::GET, "/sys/v1/version") => {
+                return json(VersionResponse {
+                    version: "0.0.0".to_owned(),
+                    api_version: "1.0".to_owned(),
+                    server_mode: ServerMode::Software,
+                    fips_level: None,
+                })
+            }
+            _ => {}
+        }
+        let caller = self.authenticate(authorization)?;
+        self.dispatch(Call {
+            caller,
+            method,
+            path: path.to_owned(),
+            query,
+            authorization: authorization.map(str::to_owned),
+            body,
+            approved: false,
+        })
+    }
+
+    fn dispatch(&mut self, call: Call) -> Outcome {
+        let path = call.path.clone();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (call.method.as_str(), &segments[..]) {
+            ("POST", ["sys", "v1", "session", "terminate"]) => self.session_terminate(&call),
+            ("POST", ["sys", "v1", "session", "refresh"]) => self.session_refresh(&call),
+
+            ("GET", ["sys", "v1", "groups"]) => self.list_groups(),
+            ("POST", ["sys", "v1", "groups"]) => self.create_group(&call),
+            ("GET", ["sys", "v1", "groups", id]) => json(self.group(parse_uuid(id)?)?),
+            ("PATCH", ["sys", "v1", "groups", id]) => self.update_group(&call, parse_uuid(id)?),
+            ("DELETE", ["sys", "v1", "groups", id]) => self.delete_group(parse_uuid(id)?),
+
+            ("GET", ["sys", "v1", "apps"]) => self.list_apps(&call),
+            ("POST", ["sys", "v1", "apps"]) => self.create_app(&call),
+            ("GET", ["sys", "v1", "apps", id]) => json(&self.app(parse_uuid(id)?)?.app),
+            ("PATCH", ["sys", "v1", "apps", id]) => self.update_app(&call, parse_uuid(id)?),
+            ("DELETE", ["sys", "v1", "apps", id]) => self.delete_app(parse_uuid(id)?),
+            ("GET", ["sys", "v1", "apps", id, "credential"]) => {
+                self.app_credential(parse_uuid(id)?)
+            }
+            ("POST", ["sys", "v1", "apps", id, "reset_secret"]) => {
+                self.reset_app_secret(parse_uuid(id)?)
+            }
+
+            ("GET", ["sys", "v1", "approval_requests"]) => self.list_approval_requests(&call),
+            ("POST", ["sys", "v1", "approval_requests"]) => self.create_approval_request(&call),
+            ("GET", ["sys", "v1", "approval_requests", id]) => {
+                json(&self.approval_request(parse_uuid(id)?)?.request)
+            }
+            ("DELETE", ["sys", "v1", "approval_requests", id]) => {
+                self.approval_requests
+                    .remove(&parse_uuid(id)?)
+                    .ok_or_else(|| Failure::not_found("approval request does not exist"))?;
+                Ok(Value::Null)
+            }
+            ("POST", ["sys", "v1", "approval_requests", id, "approve"]) => {
+                self.review_approval_request(&call, parse_uuid(id)?, true)
+            }
+            ("POST", ["sys", "v1", "approval_requests", id, "deny"]) => {
+                self.review_approval_request(&call, parse_uuid(id)?, false)
+            }
+            ("POST", ["sys", "v1", "approval_requests", id, "result"]) => {
+                self.approval_request_result(&call, parse_uuid(id)?)
+            }
+
+            ("POST", ["crypto", "v1", "keys"]) => self.create_sobject(&call),
+            ("PUT", ["crypto", "v1", "keys"]) => self.import_sobject(&call),
+            ("GET", ["crypto", "v1", "keys"]) => self.list_sobjects(&call),
+            ("POST", ["crypto", "v1", "keys", "info"]) => {
+                let kid = self.resolve(Some(&call.body()?))?;
+                json(self.view(kid))
+            }
+            ("POST", ["crypto", "v1", "keys", "export"]) => self.export_sobject(&call),
+            ("POST", ["crypto", "v1", "keys", "digest"]) => self.digest_sobject(&call),
+            ("POST", ["crypto", "v1", "keys", "rekey"]) => self.rotate_sobject(&call),
+            ("POST", ["crypto", "v1", "keys", "batch", "encrypt"]) => self.batch_encrypt(&call),
+            ("POST", ["crypto", "v1", "keys", "batch", "decrypt"]) => self.batch_decrypt(&call),
+            ("POST", ["crypto", "v1", "keys", "batch", "sign"]) => self.batch_sign(&call),
+            ("POST", ["crypto", "v1", "keys", "batch", "verify"]) => self.batch_verify(&call),
+            ("PATCH", ["crypto", "v1", "keys", id]) => self.update_sobject(&call, parse_uuid(id)?),
+            ("DELETE", ["crypto", "v1", "keys", id]) => self.delete_sobject(&call, parse_uuid(id)?),
+            ("DELETE", ["crypto", "v1", "keys", id, "private"]) => {
+                self.remove_private(&call, parse_uuid(id)?)
+            }
+            ("POST", ["crypto", "v1", "keys", id, "activate"]) => {
+                self.activate_sobject(&call, parse_uuid(id)?)
+            }
+            ("POST", ["crypto", "v1", "keys", id, "revoke"]) => {
+                self.revoke_sobject(&call, parse_uuid(id)?)
+            }
+
+            ("POST", ["crypto", "v1", "encrypt"]) => {
+                json(self.encrypt(&call.body()?, call.approved)?)
+            }
+            ("POST", ["crypto", "v1", "decrypt"]) => {
+                json(self.decrypt(&call.body()?, call.approved)?)
+            }
+            ("POST", ["crypto", "v1", "encrypt", "init"]) => self.encrypt_init(&call),
+            ("POST", ["crypto", "v1", "encrypt", "update"]) => self.encrypt_update(&call),
+            ("POST", ["crypto", "v1", "encrypt", "final"]) => self.encrypt_final(&call),
+            ("POST", ["crypto", "v1", "decrypt", "init"]) => self.decrypt_init(&call),
+            ("POST", ["crypto", "v1", "decrypt", "update"]) => self.decrypt_update(&call),
+            ("POST", ["crypto", "v1", "decrypt", "final"]) => self.decrypt_final(&call),
+            ("POST", ["crypto", "v1", "sign"]) => json(self.sign(&call.body()?, call.approved)?),
+            ("POST", ["crypto", "v1", "verify"]) => json(self.verify(&call.body()?)?),
+            ("POST", ["crypto", "v1", "wrapkey"]) => self.wrap_key(&call),
+            ("POST", ["crypto", "v1", "unwrapkey"]) => self.unwrap_key(&call),
+            ("POST", ["crypto", "v1", "mac"]) => self.mac(&call),
+            ("POST", ["crypto", "v1", "macverify"]) => self.mac_verify(&call),
+            ("POST", ["crypto", "v1", "digest"]) => {
+                let req: DigestRequest = call.body()?;
+                json(DigestResponse {
+                    digest: crypto::digest(req.alg, &req.data).into(),
+                })
+            }
+
+            _ => Err(Failure::not_found(format!(
+                "{} {} is not supported by the mock server",
+                call.method, call.path
+            ))),
+        }
+    }
+
+    // Authentication
+
+    fn app_from_basic_auth(&self, credentials: &str) -> Result<Uuid, Failure> {
+        let decoded = base64::decode(credentials).map_err(|_| Failure::unauthorized())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| Failure::unauthorized())?;
+        let mut parts = decoded.splitn(2, ':');
+        let app_id = parts.next().and_then(|id| Uuid::parse_str(id).ok());
+        let secret = parts.next();
+        match (app_id.and_then(|id| self.apps.get(&id)), secret) {
+            (Some(app), Some(secret)) if app.app.enabled && app.secret == secret => {
+                Ok(app.app.app_id)
+            }
+            _ => Err(Failure::unauthorized()),
+        }
+    }
+
+    fn authenticate(&mut self, authorization: Option<&str>) -> Result<Uuid, Failure> {
+        let authorization = authorization.ok_or_else(Failure::unauthorized)?;
+        if let Some(token) = authorization.strip_prefix("Bearer ") {
+            match self.sessions.get(token) {
+                Some(session) if session.expires_at > now().0 => Ok(session.app_id),
+                Some(_) => {
+                    self.sessions.remove(token);
+                    Err(Failure::unauthorized())
+                }
+                None => Err(Failure::unauthorized()),
+            }
+        } else if let Some(credentials) = authorization.strip_prefix("Basic ") {
+            self.app_from_basic_auth(credentials)
+        } else {
+            Err(Failure::unauthorized())
+        }
+    }
+
+    fn session_auth(&mut self, authorization: Option<&str>) -> Outcome {
+        let app_id = match authorization.and_then(|auth| auth.strip_prefix("Basic ")) {
+            Some(credentials) => self.app_from_basic_auth(credentials)?,
+            None => return Err(Failure::unauthorized()),
+        };
+        let access_token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        self.sessions.insert(
+            access_token.clone(),
+            Session {
+                app_id,
+                expires_at: now().0 + self.session_lifetime as u64,
+            },
+        );
+        json(AuthResponse {
+            token_type: "Bearer".to_owned(),
+            expires_in: self.session_lifetime,
+            access_token,
+            entity_id: app_id,
+            challenge: None,
+        })
+    }
+
+    fn session_token<'a>(&self, call: &'a Call) -> Result<&'a str, Failure> {
+        call.authorization
+            .as_ref()
+            .and_then(|auth| auth.strip_prefix("Bearer "))
+            .ok_or_else(|| Failure::bad_request("no session"))
+    }
+
+    fn session_terminate(&mut self, call: &Call) -> Outcome {
+        let token = self.session_token(call)?;
+        self.sessions.remove(token);
+        Ok(Value::Null)
+    }
+
+    fn session_refresh(&mut self, call: &Call) -> Outcome {
+        let token = self.session_token(call)?;
+        let expires_at = now().0 + self.session_lifetime as u64;
+        if let Some(session) = self.sessions.get_mut(token) {
+            session.expires_at = expires_at;
+        }
+        Ok(Value::Null)
+    }
+
+    // Groups
+
+    fn group(&self, group_id: Uuid) -> Result<&Group, Failure> {
+        self.groups
+            .get(&group_id)
+            .ok_or_else(|| Failure::not_found("group does not exist"))
+    }
+
+    fn list_groups(&self) -> Outcome {
+        let mut groups: Vec<&Group> = self.groups.values().collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        json(groups)
+    }
+
+    fn check_group_name(&self, name: &str, except: Option<Uuid>) -> Result<(), Failure> {
+        match self
+            .groups
+            .values()
+            .any(|group| group.name == name && Some(group.group_id) != except)
+        {
+            true => Err(Failure::conflict(
+                "a group with the same name already exists",
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn create_group(&mut self, call: &Call) -> Outcome {
+        let req: GroupRequest = call.body()?;
+        let name = req
+            .name
+            .ok_or_else(|| Failure::bad_request("name is required"))?;
+        self.check_group_name(&name, None)?;
+        let group = Group {
+            acct_id: self.acct_id,
+            approval_policy: req.approval_policy,
+            created_at: now(),
+            creator: Principal::App(call.caller),
+            description: req.description,
+            group_id: Uuid::new_v4(),
+            name,
+        };
+        // the creator gets access to the new group
+        if let Some(app) = self.apps.get_mut(&call.caller) {
+            app.app
+                .groups
+                .insert(group.group_id, Some(AppPermissions::all()));
+        }
+        self.groups.insert(group.group_id, group.clone());
+        json(group)
+    }
+
+    fn update_group(&mut self, call: &Call, group_id: Uuid) -> Outcome {
+        let req: GroupRequest = call.body()?;
+        self.group(group_id)?;
+        if let Some(ref name) = req.name {
+            self.check_group_name(name, Some(group_id))?;
+        }
+        let group = self.groups.get_mut(&group_id).unwrap();
+        if let Some(name) = req.name {
+            group.name = name;
+        }
+        if let Some(description) = req.description {
+            group.description = Some(description);
+        }
+        if let Some(approval_policy) = req.approval_policy {
+            group.approval_policy = Some(approval_policy);
+        }
+        json(group.clone())
+    }
+
+    fn delete_group(&mut self, group_id: Uuid) -> Outcome {
+        self.group(group_id)?;
+        if self
+            .sobjects
+            .values()
+            .any(|s| s.sobject.group_id == Some(group_id))
+        {
+            return Err(Failure::conflict("group is not empty"));
+        }
+        self.groups.remove(&group_id);
+        for app in self.apps.values_mut() {
+            app.app.groups.remove(&group_id);
+        }
+        Ok(Value::Null)
+    }
+
+    // Apps
+
+    fn new_app(&self, app_id: Uuid, creator: Uuid, name: String, groups: AppGroups) -> StoredApp {
+        StoredApp {
+            app: App {
+                acct_id: self.acct_id,
+                app_id,
+                app_type: "default".to_owned(),
+                auth_type: Some(AppAuthType::Secret),
+                cert_not_after: None,
+                created_at: now(),
+                creator: Principal::App(creator),
+                default_group: groups.keys().next().cloned(),
+                description: None,
+                enabled: true,
+                groups,
+                interface: None,
+                lastused_at: None,
+                name,
+                oauth_config: None,
+            },
+            secret: base64::encode(crypto::random_bytes(32)),
+        }
+    }
+
+    fn app(&self, app_id: Uuid) -> Result<&StoredApp, Failure> {
+        self.apps
+            .get(&app_id)
+            .ok_or_else(|| Failure::not_found("app does not exist"))
+    }
+
+    fn list_apps(&self, call: &Call) -> Outcome {
+        let group_id: Option<Uuid> = call.query("group_id")?;
+        let (_, order, start) = call.sort("app_id")?;
+        let start = start.as_deref().map(parse_uuid).transpose()?;
+        let apps = self
+            .apps
+            .values()
+            .map(|app| app.app.clone())
+            .filter(|app| group_id.iter().all(|id| app.groups.contains_key(id)))
+            .collect();
+        json(call.page(sorted_from(apps, |app| app.app_id, order, start))?)
+    }
+
+    fn apply_app_request(&mut self, app_id: Uuid, req: AppRequest) -> Result<(), Failure> {
+        for group_id in req
+            .add_groups
+            .iter()
+            .chain(req.mod_groups.iter())
+            .flat_map(|groups| groups.keys())
+            .chain(req.default_group.iter())
+        {
+            self.group(*group_id)?;
+        }
+        let secret = match req.credential {
+            Some(AppCredential::Secret(secret)) => Some(secret),
+            Some(_) => {
+                return Err(Failure::bad_request(
+                    "only secret credentials are supported by the mock server",
+                ))
+            }
+            None => None,
+        };
+        let stored = self.apps.get_mut(&app_id).unwrap();
+        if let Some(secret) = secret {
+            stored.secret = secret;
+        }
+        let app = &mut stored.app;
+        for (group_id, permissions) in req
+            .add_groups
+            .into_iter()
+            .chain(req.mod_groups)
+            .flat_map(HashMap::from)
+        {
+            app.groups.insert(group_id, permissions);
+        }
+        for group_id in req.del_groups.unwrap_or_default() {
+            app.groups.remove(&group_id);
+        }
+        if let Some(name) = req.name {
+            app.name = name;
+        }
+        if let Some(app_type) = req.app_type {
+            app.app_type = app_type;
+        }
+        if let Some(default_group) = req.default_group {
+            app.default_group = Some(default_group);
+        }
+        if let Some(description) = req.description {
+            app.description = Some(description);
+        }
+        if let Some(enabled) = req.enabled {
+            app.enabled = enabled;
+        }
+        if let Some(interface) = req.interface {
+            app.interface = Some(interface);
+        }
+        if let Some(oauth_config) = req.oauth_config {
+            app.oauth_config = Some(oauth_config);
+        }
+        Ok(())
+    }
+
+    fn create_app(&mut self, call: &Call) -> Outcome {
+        let req: AppRequest = call.body()?;
+        let name = req
+            .name
+            .clone()
+            .ok_or_else(|| Failure::bad_request("name is required"))?;
+        if self.apps.values().any(|app| app.app.name == name) {
+            return Err(Failure::conflict(
+                "an app with the same name already exists",
+            ));
+        }
+        let app_id = Uuid::new_v4();
+        let app = self.new_app(app_id, call.caller, name, HashMap::new().into());
+        self.apps.insert(app_id, app);
+        if let Err(e) = self.apply_app_request(app_id, req) {
+            self.apps.remove(&app_id);
+            return Err(e);
+        }
+        let app = self.apps.get_mut(&app_id).unwrap();
+        if app.app.default_group.is_none() {
+            app.app.default_group = app.app.groups.keys().next().cloned();
+        }
+        json(&app.app)
+    }
+
+    fn update_app(&mut self, call: &Call, app_id: Uuid) -> Outcome {
+        let req: AppRequest = call.body()?;
+        self.app(app_id)?;
+        self.apply_app_request(app_id, req)?;
+        json(&self.app(app_id)?.app)
+    }
+
+    fn delete_app(&mut self, app_id: Uuid) -> Outcome {
+        self.app(app_id)?;
+        self.apps.remove(&app_id);
+        self.sessions.retain(|_, session| session.app_id != app_id);
+        Ok(Value::Null)
+    }
+
+    fn app_credential(&self, app_id: Uuid) -> Outcome {
+        let app = self.app(app_id)?;
+        json(AppCredentialResponse {
+            app_id,
+            credential: AppCredential::Secret(app.secret.clone()),
+        })
+    }
+
+    fn reset_app_secret(&mut self, app_id: Uuid) -> Outcome {
+        self.app(app_id)?;
+        let app = self.apps.get_mut(&app_id).unwrap();
+        app.secret = base64::encode(crypto::random_bytes(32));
+        self.sessions.retain(|_, session| session.app_id != app_id);
+        json(&self.app(app_id)?.app)
+    }
+
+    // Security objects
+
+    fn resolve(&self, descriptor: Option<&SobjectDescriptor>) -> Result<Uuid, Failure> {
+        let kid = match descriptor {
+            Some(SobjectDescriptor::Kid(kid)) => self.sobjects.get(kid).map(|_| *kid),
+            Some(SobjectDescriptor::Name(name)) => self
+                .sobjects
+                .iter()
+                .find(|(_, s)| s.sobject.name.as_ref() == Some(name))
+                .map(|(kid, _)| *kid),
+            Some(SobjectDescriptor::TransientKey(_)) => {
+                return Err(Failure::bad_request(
+                    "transient keys are not supported by the mock server",
+                ))
+            }
+            None => return Err(Failure::bad_request("key is required")),
+        };
+        kid.ok_or_else(|| Failure::not_found("sobject does not exist"))
+    }
+
+    /// The security object as returned by the API, i.e. without its value.
+    fn view(&self, kid: Uuid) -> Sobject {
+        let stored = &self.sobjects[&kid];
+        let mut sobject = stored.sobject.clone();
+        sobject.state = Some(sobject.effective_state(now()));
+        // Certificates are public, their value is always returned
+        if sobject.obj_type == ObjectType::Certificate {
+            sobject.value = Some(stored.value.clone().into());
+        }
+        sobject
+    }
+
+    fn requires_approval(&self, kid: Uuid) -> bool {
+        let group_id = self.sobjects[&kid].sobject.group_id;
+        let group = group_id.and_then(|id| self.groups.get(&id));
+        matches!(group, Some(group) if group.approval_policy.is_some())
+    }
+
+    /// Checks that an existing security object can be modified.
+    fn manage(&self, kid: Uuid, approved: bool) -> Result<&StoredSobject, Failure> {
+        let stored = self
+            .sobjects
+            .get(&kid)
+            .ok_or_else(|| Failure::not_found("sobject does not exist"))?;
+        if !approved && self.requires_approval(kid) {
+            return Err(Failure::approval_required());
+        }
+        Ok(stored)
+    }
+
+    /// Checks that a security object can be used for `op`.
+    fn use_key(
+        &self,
+        descriptor: Option<&SobjectDescriptor>,
+        op: KeyOperations,
+        approved: bool,
+    ) -> Result<&StoredSobject, Failure> {
+        let kid = self.resolve(descriptor)?;
+        let stored = &self.sobjects[&kid];
+        let sobject = &stored.sobject;
+        if !sobject.enabled {
+            return Err(Failure::bad_request("sobject is disabled"));
+        }
+        if !sobject.key_ops.contains(op) {
+            return Err(Failure::bad_request(format!(
+                "operation {:?} is not allowed for this sobject",
+                op
+            )));
+        }
+        let protecting = KeyOperations::ENCRYPT
+            | KeyOperations::SIGN
+            | KeyOperations::WRAPKEY
+            | KeyOperations::MACGENERATE
+            | KeyOperations::DERIVEKEY
+            | KeyOperations::AGREEKEY;
+        match sobject.effective_state(now()) {
+            SobjectState::Active => {}
+            SobjectState::PreActive => return Err(Failure::bad_request("sobject is not active")),
+            SobjectState::Deactivated | SobjectState::Compromised if protecting.intersects(op) => {
+                return Err(Failure::bad_request("sobject is not active"))
+            }
+            SobjectState::Deactivated | SobjectState::Compromised => {}
+        }
+        if stored.value.is_empty() && op != KeyOperations::VERIFY {
+            return Err(Failure::bad_request("sobject has no private key"));
+        }
+        if !approved && self.requires_approval(kid) && op != KeyOperations::VERIFY {
+            return Err(Failure::approval_required());
+        }
+        Ok(stored)
+    }
+
+    fn check_sobject_name(&self, name: &str, except: Option<Uuid>) -> Result<(), Failure> {
+        match self
+            .sobjects
+            .iter()
+            .any(|(kid, s)| s.sobject.name.as_deref() == Some(name) && Some(*kid) != except)
+        {
+            true => Err(Failure::conflict(
+                "an sobject with the same name already exists",
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn insert_sobject(
+        &mut self,
+        caller: Uuid,
+        req: SobjectRequest,
+        value: Vec<u8>,
+        origin: ObjectOrigin,
+    ) -> Outcome {
+        let obj_type = req
+            .obj_type
+            .ok_or_else(|| Failure::bad_request("obj_type is required"))?;
+        let name = req
+            .name
+            .ok_or_else(|| Failure::bad_request("name is required"))?;
+        self.check_sobject_name(&name, None)?;
+        let group_id = match req.group_id {
+            Some(group_id) => group_id,
+            None => self
+                .app(caller)?
+                .app
+                .default_group
+                .ok_or_else(|| Failure::bad_request("group_id is required"))?,
+        };
+        self.group(group_id)?;
+        let key_size = match req.key_size {
+            Some(key_size) => Some(key_size),
+            None if obj_type == ObjectType::Secret => Some(value.len() as u32 * 8),
+            None => default_key_size(obj_type, req.elliptic_curve)?,
+        };
+        let kid = Uuid::new_v4();
+        let sobject = Sobject {
+            acct_id: self.acct_id,
+            activation_date: req.activation_date,
+            compromise_date: None,
+            created_at: now(),
+            creator: Principal::App(caller),
+            custom_metadata: req.custom_metadata,
+            deactivation_date: req.deactivation_date,
+            description: req.description,
+            deterministic_signatures: req.deterministic_signatures,
+            elliptic_curve: req.elliptic_curve,
+            enabled: req.enabled.unwrap_or(true),
+            fpe: req.fpe,
+            key_ops: req.key_ops.unwrap_or_else(|| default_key_ops(obj_type)),
+            key_size,
+            kid: Some(kid),
+            lastused_at: Time(0),
+            links: None,
+            name: Some(name),
+            never_exportable: None,
+            obj_type,
+            origin,
+            pub_key: crypto::public_key(obj_type, &value).map(Blob::from),
+            public_only: false,
+            publish_public_key: req.publish_public_key,
+            revocation_reason: None,
+            rsa: req.rsa,
+            state: req.state,
+            transient_key: None,
+            value: None,
+            group_id: Some(group_id),
+        };
+        self.sobjects.insert(kid, StoredSobject { sobject, value });
+        json(self.view(kid))
+    }
+
+    fn create_sobject(&mut self, call: &Call) -> Outcome {
+        let req: SobjectRequest = call.body()?;
+        if req.transient == Some(true) {
+            return Err(Failure::bad_request(
+                "transient keys are not supported by the mock server",
+            ));
+        }
+        let obj_type = req
+            .obj_type
+            .ok_or_else(|| Failure::bad_request("obj_type is required"))?;
+        let key_size = match req.key_size {
+            Some(key_size) => Some(key_size),
+            None => default_key_size(obj_type, req.elliptic_curve)?,
+        };
+        let (value, _) = crypto::generate_key(obj_type, key_size)?;
+        self.insert_sobject(call.caller, req, value, ObjectOrigin::FortanixHSM)
+    }
+
+    fn import_sobject(&mut self, call: &Call) -> Outcome {
+        let mut req: SobjectRequest = call.body()?;
+        let value = req
+            .value
+            .take()
+            .ok_or_else(|| Failure::bad_request("value is required"))?;
+        self.insert_sobject(call.caller, req, value.into(), ObjectOrigin::External)
+    }
+
+    fn list_sobjects(&self, call: &Call) -> Outcome {
+        let group_id: Option<Uuid> = call.query("group_id")?;
+        let creator: Option<Uuid> = call.query("creator")?;
+        let name = call.query.get("name");
+        let sobjects: Vec<Sobject> = self
+            .sobjects
+            .keys()
+            .map(|kid| self.view(*kid))
+            .filter(|s| group_id.iter().all(|id| s.group_id == Some(*id)))
+            .filter(|s| creator.iter().all(|id| s.creator == Principal::App(*id)))
+            .filter(|s| name.iter().all(|name| s.name.as_ref() == Some(*name)))
+            .collect();
+        let sobjects = match call.sort("kid")? {
+            (ref field, order, start) if field == "kid" => {
+                let start = start.as_deref().map(parse_uuid).transpose()?;
+                sorted_from(sobjects, |s| s.kid, order, start.map(Some))
+            }
+            (ref field, order, start) if field == "name" => {
+                sorted_from(sobjects, |s| s.name.clone(), order, start.map(Some))
+            }
+            _ => return Err(Failure::bad_request("invalid `sort` parameter")),
+        };
+        json(call.page(sobjects)?)
+    }
+
+    fn update_sobject(&mut self, call: &Call, kid: Uuid) -> Outcome {
+        let req: SobjectRequest = call.body()?;
+        self.manage(kid, call.approved)?;
+        if let Some(ref name) = req.name {
+            self.check_sobject_name(name, Some(kid))?;
+        }
+        if let Some(group_id) = req.group_id {
+            self.group(group_id)?;
+        }
+        let sobject = &mut self.sobjects.get_mut(&kid).unwrap().sobject;
+        if let Some(name) = req.name {
+            sobject.name = Some(name);
+        }
+        if let Some(description) = req.description {
+            sobject.description = Some(description);
+        }
+        if let Some(custom_metadata) = req.custom_metadata {
+            sobject.custom_metadata = Some(custom_metadata);
+        }
+        if let Some(enabled) = req.enabled {
+            sobject.enabled = enabled;
+        }
+        if let Some(key_ops) = req.key_ops {
+            sobject.key_ops = key_ops;
+        }
+        if let Some(activation_date) = req.activation_date {
+            sobject.activation_date = Some(activation_date);
+        }
+        if let Some(deactivation_date) = req.deactivation_date {
+            sobject.deactivation_date = Some(deactivation_date);
+        }
+        if let Some(state) = req.state {
+            sobject.state = Some(state);
+        }
+        if let Some(group_id) = req.group_id {
+            sobject.group_id = Some(group_id);
+        }
+        if let Some(publish_public_key) = req.publish_public_key {
+            sobject.publish_public_key = Some(publish_public_key);
+        }
+        json(self.view(kid))
+    }
+
+    fn delete_sobject(&mut self, call: &Call, kid: Uuid) -> Outcome {
+        self.manage(kid, call.approved)?;
+        self.sobjects.remove(&kid);
+        Ok(Value::Null)
+    }
+
+    fn remove_private(&mut self, call: &Call, kid: Uuid) -> Outcome {
+        let stored = self.manage(kid, call.approved)?;
+        if stored.sobject.pub_key.is_none() {
+            return Err(Failure::bad_request("sobject does not have a private key"));
+        }
+        let stored = self.sobjects.get_mut(&kid).unwrap();
+        stored.value.clear();
+        stored.sobject.public_only = true;
+        Ok(Value::Null)
+    }
+
+    fn activate_sobject(&mut self, call: &Call, kid: Uuid) -> Outcome {
+        self.manage(kid, call.approved)?;
+        let now = now();
+        let sobject = &mut self.sobjects.get_mut(&kid).unwrap().sobject;
+        match sobject.effective_state(now) {
+            SobjectState::PreActive => {
+                sobject.state = Some(SobjectState::Active);
+                sobject.activation_date = Some(now);
+                Ok(Value::Null)
+            }
+            SobjectState::Active => Ok(Value::Null),
+            _ => Err(Failure::bad_request("sobject can not be activated")),
+        }
+    }
+
+    fn revoke_sobject(&mut self, call: &Call, kid: Uuid) -> Outcome {
+        let reason: RevocationReason = call.body()?;
+        self.manage(kid, call.approved)?;
+        let now = now();
+        let sobject = &mut self.sobjects.get_mut(&kid).unwrap().sobject;
+        match reason.code {
+            RevocationReasonCode::KeyCompromise | RevocationReasonCode::CACompromise => {
+                sobject.state = Some(SobjectState::Compromised);
+                sobject.compromise_date = Some(reason.compromise_occurance_date.unwrap_or(now));
+            }
+            _ => {
+                sobject.state = Some(SobjectState::Deactivated);
+                if !matches!(sobject.deactivation_date, Some(date) if date <= now) {
+                    sobject.deactivation_date = Some(now);
+                }
+            }
+        }
+        sobject.revocation_reason = Some(reason);
+        Ok(Value::Null)
+    }
+
+    fn export_sobject(&mut self, call: &Call) -> Outcome {
+        let kid = self.resolve(Some(&call.body()?))?;
+        let stored = self.manage(kid, call.approved)?;
+        if !stored.sobject.key_ops.contains(KeyOperations::EXPORT) {
+            return Err(Failure::bad_request("sobject is not exportable"));
+        }
+        let mut sobject = self.view(kid);
+        sobject.value = Some(stored.value.clone().into());
+        json(sobject)
+    }
+
+    fn digest_sobject(&mut self, call: &Call) -> Outcome {
+        let req: ObjectDigestRequest = call.body()?;
+        let kid = self.resolve(Some(&req.key))?;
+        json(ObjectDigestResponse {
+            kid: Some(kid),
+            digest: crypto::digest(req.alg, &self.sobjects[&kid].value).into(),
+        })
+    }
+
+    fn rotate_sobject(&mut self, call: &Call) -> Outcome {
+        let req: SobjectRequest = call.body()?;
+        let name = req
+            .name
+            .clone()
+            .ok_or_else(|| Failure::bad_request("name is required"))?;
+        let old_kid = self.resolve(Some(&SobjectDescriptor::Name(name.clone())))?;
+        let old = self.manage(old_kid, call.approved)?.sobject.clone();
+        let key_size = req.key_size.or(old.key_size);
+        let elliptic_curve = req.elliptic_curve.or(old.elliptic_curve);
+        let (value, _) = crypto::generate_key(old.obj_type, key_size)?;
+        // the replaced object gives up its name to the new one
+        self.sobjects.get_mut(&old_kid).unwrap().sobject.name =
+            Some(format!("{} {}", name, old_kid));
+        let new_req = SobjectRequest {
+            activation_date: req.activation_date,
+            custom_metadata: req.custom_metadata.or(old.custom_metadata),
+            deactivation_date: req.deactivation_date,
+            description: req.description.or(old.description),
+            deterministic_signatures: req
+                .deterministic_signatures
+                .or(old.deterministic_signatures),
+            elliptic_curve,
+            enabled: req.enabled.or(Some(old.enabled)),
+            fpe: req.fpe.or(old.fpe),
+            key_ops: req.key_ops.or(Some(old.key_ops)),
+            key_size,
+            name: Some(name.clone()),
+            obj_type: Some(old.obj_type),
+            pub_exponent: req.pub_exponent,
+            publish_public_key: req.publish_public_key.or(old.publish_public_key),
+            rsa: req.rsa.or(old.rsa),
+            state: req.state,
+            transient: None,
+            value: None,
+            group_id: req.group_id.or(old.group_id),
+        };
+        if let Err(e) = self.insert_sobject(call.caller, new_req, value, ObjectOrigin::FortanixHSM)
+        {
+            self.sobjects.get_mut(&old_kid).unwrap().sobject.name = Some(name);
+            return Err(e);
+        }
+        let new_kid = self.resolve(Some(&SobjectDescriptor::Name(name)))?;
+        let replaced = old.links.as_ref().and_then(|links| links.replaced);
+        self.sobjects.get_mut(&old_kid).unwrap().sobject.links = Some(KeyLinks {
+            replacement: Some(new_kid),
+            replaced,
+        });
+        self.sobjects.get_mut(&new_kid).unwrap().sobject.links = Some(KeyLinks {
+            replacement: None,
+            replaced: Some(old_kid),
+        });
+        json(self.view(new_kid))
+    }
+
+    // Cryptographic operations
+
+    fn cipher<'a>(
+        &self,
+        stored: &'a StoredSobject,
+        alg: Option<Algorithm>,
+        mode: Option<CryptMode>,
+    ) -> Result<Cipher<'a>, Failure> {
+        if let Some(alg) = alg {
+            if !algorithm_matches(alg, stored.sobject.obj_type) {
+                return Err(Failure::bad_request("alg does not match the key type"));
+            }
+        }
+        Ok(Cipher {
+            key: &stored.value,
+            obj_type: stored.sobject.obj_type,
+            mode,
+        })
+    }
+
+    fn encrypt(&self, req: &EncryptRequest, approved: bool) -> Result<EncryptResponse, Failure> {
+        let stored = self.use_key(req.key.as_ref(), KeyOperations::ENCRYPT, approved)?;
+        let encrypted = self
+            .cipher(stored, Some(req.alg), req.mode.clone())?
+            .encrypt(
+                &req.plain,
+                req.iv.as_deref(),
+                req.ad.as_deref(),
+                req.tag_len,
+            )?;
+        Ok(EncryptResponse {
+            kid: stored.sobject.kid,
+            cipher: encrypted.cipher.into(),
+            iv: encrypted.iv.map(Blob::from),
+            tag: encrypted.tag.map(Blob::from),
+        })
+    }
+
+    fn decrypt(&self, req: &DecryptRequest, approved: bool) -> Result<DecryptResponse, Failure> {
+        let stored = self.use_key(req.key.as_ref(), KeyOperations::DECRYPT, approved)?;
+        let plain = self.cipher(stored, req.alg, req.mode.clone())?.decrypt(
+            &req.cipher,
+            req.iv.as_deref(),
+            req.ad.as_deref(),
+            req.tag.as_deref(),
+        )?;
+        Ok(DecryptResponse {
+            kid: stored.sobject.kid,
+            plain: plain.into(),
+        })
+    }
+
+    fn stream_init(
+        &self,
+        key: Option<&SobjectDescriptor>,
+        alg: Option<Algorithm>,
+        mode: Option<CipherMode>,
+        iv: Option<&Blob>,
+        decrypt: bool,
+        approved: bool,
+    ) -> Result<StreamState, Failure> {
+        let op = match decrypt {
+            true => KeyOperations::DECRYPT,
+            false => KeyOperations::ENCRYPT,
+        };
+        let stored = self.use_key(key, op, approved)?;
+        if let Some(CipherMode::Gcm) | Some(CipherMode::Ccm) = mode {
+            return Err(Failure::bad_request(
+                "AEAD modes are not supported in multi-part operations",
+            ));
+        }
+        let cipher = self.cipher(stored, alg, mode.map(CryptMode::Symmetric))?;
+        let iv = match iv {
+            Some(iv) => iv.clone(),
+            None if decrypt => return Err(Failure::bad_request("iv is required")),
+            None => cipher
+                .encrypt(&[], None, None, None)?
+                .iv
+                .unwrap_or_default()
+                .into(),
+        };
+        Ok(StreamState {
+            kid: stored.sobject.kid.unwrap(),
+            iv,
+            offset: 0,
+            decrypt,
+        })
+    }
+
+    fn stream_update(
+        &self,
+        key: Option<&SobjectDescriptor>,
+        state: &mut StreamState,
+        data: &[u8],
+        approved: bool,
+    ) -> Result<Vec<u8>, Failure> {
+        let op = match state.decrypt {
+            true => KeyOperations::DECRYPT,
+            false => KeyOperations::ENCRYPT,
+        };
+        let key = key.cloned().unwrap_or(SobjectDescriptor::Kid(state.kid));
+        let stored = self.use_key(Some(&key), op, approved)?;
+        if stored.sobject.kid != Some(state.kid) {
+            return Err(Failure::bad_request("key does not match state"));
+        }
+        let cipher = Cipher {
+            key: &stored.value,
+            obj_type: stored.sobject.obj_type,
+            mode: None,
+        };
+        let output = cipher.update(&state.iv, state.offset, data);
+        state.offset += data.len();
+        Ok(output)
+    }
+
+    fn encrypt_init(&self, call: &Call) -> Outcome {
+        let req: EncryptInitRequest = call.body()?;
+        let state = self.stream_init(
+            req.key.as_ref(),
+            Some(req.alg),
+            req.mode,
+            req.iv.as_ref(),
+            false,
+            call.approved,
+        )?;
+        json(EncryptInitResponse {
+            kid: Some(state.kid),
+            iv: Some(state.iv.clone()),
+            state: state.encode(),
+        })
+    }
+
+    fn encrypt_update(&self, call: &Call) -> Outcome {
+        let req: EncryptUpdateRequest = call.body()?;
+        let mut state = StreamState::decode(&req.state, false)?;
+        let cipher = self.stream_update(req.key.as_ref(), &mut state, &req.plain, call.approved)?;
+        json(EncryptUpdateResponse {
+            cipher: cipher.into(),
+            state: state.encode(),
+        })
+    }
+
+    fn encrypt_final(&self, call: &Call) -> Outcome {
+        let req: EncryptFinalRequest = call.body()?;
+        let mut state = StreamState::decode(&req.state, false)?;
+        self.stream_update(req.key.as_ref(), &mut state, &[], call.approved)?;
+        json(EncryptFinalResponse {
+            cipher: Blob::from(Vec::new()),
+        })
+    }
+
+    fn decrypt_init(&self, call: &Call) -> Outcome {
+        let req: DecryptInitRequest = call.body()?;
+        let state = self.stream_init(
+            req.key.as_ref(),
+            req.alg,
+            req.mode,
+            req.iv.as_ref(),
+            true,
+            call.approved,
+        )?;
+        json(DecryptInitResponse {
+            kid: Some(state.kid),
+            state: state.encode(),
+        })
+    }
+
+    fn decrypt_update(&self, call: &Call) -> Outcome {
+        let req: DecryptUpdateRequest = call.body()?;
+        let mut state = StreamState::decode(&req.state, true)?;
+        let plain = self.stream_update(req.key.as_ref(), &mut state, &req.cipher, call.approved)?;
+        json(DecryptUpdateResponse {
+            plain: plain.into(),
+            state: state.encode(),
+        })
+    }
+
+    fn decrypt_final(&self, call: &Call) -> Outcome {
+        let req: DecryptFinalRequest = call.body()?;
+        let mut state = StreamState::decode(&req.state, true)?;
+        self.stream_update(req.key.as_ref(), &mut state, &[], call.approved)?;
+        json(DecryptFinalResponse {
+            plain: Blob::from(Vec::new()),
+        })
+    }
+
+    fn hash(
+        hash_alg: DigestAlgorithm,
+        hash: Option<&Blob>,
+        data: Option<&Blob>,
+    ) -> Result<Vec<u8>, Failure> {
+        match (hash, data) {
+            (Some(hash), None) => Ok(hash.to_vec()),
+            (None, Some(data)) => Ok(crypto::digest(hash_alg, data)),
+            _ => Err(Failure::bad_request(
+                "exactly one of hash and data is required",
+            )),
+        }
+    }
+
+    fn sign(&self, req: &SignRequest, approved: bool) -> Result<SignResponse, Failure> {
+        let stored = self.use_key(req.key.as_ref(), KeyOperations::SIGN, approved)?;
+        let hash = Self::hash(req.hash_alg, req.hash.as_ref(), req.data.as_ref())?;
+        let sobject = &stored.sobject;
+        let signature = crypto::sign(
+            &stored.value,
+            sobject.obj_type,
+            sobject.key_size,
+            req.hash_alg,
+            &hash,
+        )?;
+        Ok(SignResponse {
+            kid: sobject.kid,
+            signature: signature.into(),
+        })
+    }
+
+    fn verify(&self, req: &VerifyRequest) -> Result<VerifyResponse, Failure> {
+        let stored = self.use_key(req.key.as_ref(), KeyOperations::VERIFY, false)?;
+        let hash = Self::hash(req.hash_alg, req.hash.as_ref(), req.data.as_ref())?;
+        let sobject = &stored.sobject;
+        let pub_key = sobject
+            .pub_key
+            .as_ref()
+            .ok_or_else(|| Failure::bad_request("sobject can not be used for verification"))?;
+        Ok(VerifyResponse {
+            kid: sobject.kid,
+            result: crypto::verify(
+                pub_key,
+                sobject.obj_type,
+                sobject.key_size,
+                req.hash_alg,
+                &hash,
+                &req.signature,
+            ),
+        })
+    }
+
+    fn wrap_key(&self, call: &Call) -> Outcome {
+        let req: WrapKeyRequest = call.body()?;
+        let wrapping = self.use_key(req.key.as_ref(), KeyOperations::WRAPKEY, call.approved)?;
+        let subject = match (req.subject, req.kid) {
+            (Some(subject), _) => subject,
+            (None, Some(kid)) => SobjectDescriptor::Kid(kid),
+            (None, None) => return Err(Failure::bad_request("subject is required")),
+        };
+        let subject = &self.sobjects[&self.resolve(Some(&subject))?];
+        if !subject.sobject.key_ops.contains(KeyOperations::EXPORT) {
+            return Err(Failure::bad_request("subject is not exportable"));
+        }
+        let encrypted = self.cipher(wrapping, Some(req.alg), req.mode)?.encrypt(
+            &subject.value,
+            req.iv.as_deref(),
+            req.ad.as_deref(),
+            req.tag_len,
+        )?;
+        json(WrapKeyResponse {
+            wrapped_key: encrypted.cipher.into(),
+            iv: encrypted.iv.map(Blob::from),
+            tag: encrypted.tag.map(Blob::from),
+        })
+    }
+
+    fn unwrap_key(&mut self, call: &Call) -> Outcome {
+        let req: UnwrapKeyRequest = call.body()?;
+        let unwrapping = self.use_key(req.key.as_ref(), KeyOperations::UNWRAPKEY, call.approved)?;
+        let value = self.cipher(unwrapping, Some(req.alg), req.mode)?.decrypt(
+            &req.wrapped_key,
+            req.iv.as_deref(),
+            req.ad.as_deref(),
+            req.tag.as_deref(),
+        )?;
+        if req.transient == Some(true) {
+            return Err(Failure::bad_request(
+                "transient keys are not supported by the mock server",
+            ));
+        }
+        let sobject_req = SobjectRequest {
+            custom_metadata: req.custom_metadata,
+            description: req.description,
+            enabled: req.enabled,
+            key_ops: req.key_ops,
+            name: req.name,
+            obj_type: Some(req.obj_type),
+            rsa: req.rsa,
+            group_id: req.group_id,
+            ..Default::default()
+        };
+        self.insert_sobject(call.caller, sobject_req, value, ObjectOrigin::External)
+    }
+
+    fn mac(&self, call: &Call) -> Outcome {
+        let req: MacRequest = call.body()?;
+        let stored = self.use_key(req.key.as_ref(), KeyOperations::MACGENERATE, call.approved)?;
+        let alg = req.alg.unwrap_or(DigestAlgorithm::Sha256);
+        json(MacResponse {
+            kid: stored.sobject.kid,
+            digest: None,
+            mac: crypto::mac(&stored.value, alg, &req.data).into(),
+        })
+    }
+
+    fn mac_verify(&self, call: &Call) -> Outcome {
+        let req: VerifyMacRequest = call.body()?;
+        let stored = self.use_key(req.key.as_ref(), KeyOperations::MACVERIFY, call.approved)?;
+        let alg = req.alg.unwrap_or(DigestAlgorithm::Sha256);
+        let mac = req
+            .mac
+            .or(req.digest)
+            .ok_or_else(|| Failure::bad_request("mac is required"))?;
+        json(VerifyResponse {
+            kid: stored.sobject.kid,
+            result: crypto::mac(&stored.value, alg, &req.data) == mac.to_vec(),
+        })
+    }
+
+    fn batch<T, R, F>(items: Vec<T>, f: F) -> Outcome
+    where
+        R: Serialize,
+        F: Fn(T) -> Result<R, Failure>,
+    {
+        let results: Vec<BatchResponseItem<R>> = items
+            .into_iter()
+            .map(|item| match f(item) {
+                Ok(body) => BatchResponseItem::Success { status: 200, body },
+                Err(e) => BatchResponseItem::Error {
+                    status: e.status.as_u16(),
+                    error: e.message,
+                },
+            })
+            .collect();
+        json(results)
+    }
+
+    fn batch_encrypt(&self, call: &Call) -> Outcome {
+        let items: Vec<BatchEncryptRequestItem> = call.body()?;
+        Self::batch(items, |item| {
+            let mut req = item.request;
+            req.key = Some(SobjectDescriptor::Kid(item.kid));
+            self.encrypt(&req, call.approved)
+        })
+    }
+
+    fn batch_decrypt(&self, call: &Call) -> Outcome {
+        let items: Vec<BatchDecryptRequestItem> = call.body()?;
+        Self::batch(items, |item| {
+            let mut req = item.request;
+            req.key = Some(SobjectDescriptor::Kid(item.kid));
+            self.decrypt(&req, call.approved)
+        })
+    }
+
+    fn batch_sign(&self, call: &Call) -> Outcome {
+        let items: Vec<SignRequest> = call.body()?;
+        Self::batch(items, |req| self.sign(&req, call.approved))
+    }
+
+    fn batch_verify(&self, call: &Call) -> Outcome {
+        let items: Vec<VerifyRequest> = call.body()?;
+        Self::batch(items, |req| self.verify(&req))
+    }
+
+    // Approval requests
+
+    fn approval_request(&self, request_id: Uuid) -> Result<&StoredApprovalRequest, Failure> {
+        self.approval_requests
+            .get(&request_id)
+            .ok_or_else(|| Failure::not_found("approval request does not exist"))
+    }
+
+    /// Security objects affected by an operation, as far as the mock server can tell.
+    fn approval_subjects(&self, operation: &str, body: Option<&Value>) -> HashSet<ApprovalSubject> {
+        let mut subjects = HashSet::new();
+        let path = operation.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        if let ["crypto", "v1", "keys", id, ..] = segments[..] {
+            if let Ok(kid) = Uuid::parse_str(id) {
+                subjects.insert(ApprovalSubject::Sobject(kid));
+            }
+        }
+        let descriptor = body.and_then(|body| match segments[..] {
+            ["crypto", "v1", "keys", "export"] | ["crypto", "v1", "keys", "info"] => Some(body),
+            _ => body.get("key"),
+        });
+        if let Some(Ok(descriptor)) = descriptor.map(|d| serde_json::from_value(d.clone())) {
+            if let Ok(kid) = self.resolve(Some(&descriptor)) {
+                subjects.insert(ApprovalSubject::Sobject(kid));
+            }
+        }
+        if let Some(Value::String(name)) = body.and_then(|body| body.get("name")) {
+            if let ["crypto", "v1", "keys", "rekey"] = segments[..] {
+                if let Ok(kid) = self.resolve(Some(&SobjectDescriptor::Name(name.clone()))) {
+                    subjects.insert(ApprovalSubject::Sobject(kid));
+                }
+            }
+        }
+        subjects
+    }
+
+    fn create_approval_request(&mut self, call: &Call) -> Outcome {
+        let req: ApprovalRequestRequest = call.body()?;
+        let operation = req
+            .operation
+            .ok_or_else(|| Failure::bad_request("operation is required"))?;
+        let method = req.method.unwrap_or_else(|| "POST".to_owned());
+        Method::from_str(&method).map_err(|_| Failure::bad_request("invalid method"))?;
+        let created_at = now();
+        let request = ApprovalRequest {
+            acct_id: self.acct_id,
+            approvers: Vec::new(),
+            subjects: Some(self.approval_subjects(&operation, req.body.as_ref())),
+            body: req.body,
+            created_at,
+            denier: None,
+            description: req.description,
+            expiry: Time(created_at.0 + APPROVAL_REQUEST_LIFETIME),
+            method,
+            operation,
+            request_id: Uuid::new_v4(),
+            requester: Principal::App(call.caller),
+            reviewers: None,
+            status: ApprovalStatus::Pending,
+        };
+        self.approval_requests.insert(
+            request.request_id,
+            StoredApprovalRequest {
+                request: request.clone(),
+                result: None,
+            },
+        );
+        json(request)
+    }
+
+    fn list_approval_requests(&self, call: &Call) -> Outcome {
+        let requester: Option<Uuid> = call.query("requester")?;
+        let reviewer: Option<Uuid> = call.query("reviewer")?;
+        let subject: Option<Uuid> = call.query("subject")?;
+        let status = match call.query.get("status") {
+            Some(status) => Some(
+                serde_json::from_value::<ApprovalStatus>(Value::String(status.clone()))
+                    .map_err(|_| Failure::bad_request("invalid `status` parameter"))?,
+            ),
+            None => None,
+        };
+        let mut requests: Vec<&ApprovalRequest> = self
+            .approval_requests
+            .values()
+            .map(|stored| &stored.request)
+            .filter(|r| {
+                requester
+                    .iter()
+                    .all(|id| r.requester == Principal::App(*id))
+            })
+            .filter(|r| {
+                reviewer.iter().all(|id| {
+                    r.approvers.contains(&ReviewerPrincipal::App(*id))
+                        || r.denier == Some(ReviewerPrincipal::App(*id))
+                })
+            })
+            .filter(|r| {
+                subject.iter().all(|id| {
+                    r.subjects.iter().flatten().any(|subject| match *subject {
+                        ApprovalSubject::Group(i)
+                        | ApprovalSubject::Sobject(i)
+                        | ApprovalSubject::App(i)
+                        | ApprovalSubject::Plugin(i)
+                        | ApprovalSubject::Account(i) => i == *id,
+                        ApprovalSubject::NewAccount => false,
+                    })
+                })
+            })
+            .filter(|r| status.iter().all(|status| r.status == *status))
+            .collect();
+        requests.sort_by_key(|r| r.created_at);
+        json(requests)
+    }
+
+    fn review_approval_request(&mut self, call: &Call, request_id: Uuid, approve: bool) -> Outcome {
+        if approve {
+            let _: ApproveRequest = call.body()?;
+        }
+        self.approval_request(request_id)?;
+        let request = &mut self.approval_requests.get_mut(&request_id).unwrap().request;
+        if request.status != ApprovalStatus::Pending {
+            return Err(Failure::conflict("approval request is not pending"));
+        }
+        // a single approval satisfies any approval policy in the mock server
+        if approve {
+            request.approvers.push(ReviewerPrincipal::App(call.caller));
+            request.status = ApprovalStatus::Approved;
+        } else {
+            request.denier = Some(ReviewerPrincipal::App(call.caller));
+            request.status = ApprovalStatus::Denied;
+        }
+        json(request.clone())
+    }
+
+    fn approval_request_result(&mut self, call: &Call, request_id: Uuid) -> Outcome {
+        let stored = self.approval_request(request_id)?;
+        if stored.request.requester != Principal::App(call.caller) {
+            return Err(Failure::new(
+                StatusCode::FORBIDDEN,
+                "only the requester can get the result of an approval request",
+            ));
+        }
+        if let Some(ref result) = stored.result {
+            return json(result);
+        }
+        match stored.request.status {
+            ApprovalStatus::Approved => {}
+            ApprovalStatus::Pending => {
+                return Err(Failure::bad_request("approval request is pending"))
+            }
+            ApprovalStatus::Denied => {
+                return Err(Failure::bad_request("approval request has been denied"))
+            }
+            ApprovalStatus::Failed => {
+                return Err(Failure::bad_request("approval request has failed"))
+            }
+        }
+        let request = &stored.request;
+        let (path, query) = match request.operation.find('?') {
+            Some(i) => (&request.operation[..i], &request.operation[i + 1..]),
+            None => (&request.operation[..], ""),
+        };
+        let operation = Call {
+            caller: call.caller,
+            method: Method::from_str(&request.method).unwrap_or(Method::POST),
+            path: path.to_owned(),
+            query: url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+            authorization: None,
+            body: match request.body {
+                Some(ref body) => serde_json::to_vec(body).unwrap_or_default(),
+                None => Vec::new(),
+            },
+            approved: true,
+        };
+        let result = match self.dispatch(operation) {
+            Ok(body) => ApprovableResult { status: 200, body },
+            Err(e) => ApprovableResult {
+                status: e.status.as_u16(),
+                body: Value::String(e.message),
+            },
+        };
+        let stored = self.approval_requests.get_mut(&request_id).unwrap();
+        if !result.is_ok() {
+            stored.request.status = ApprovalStatus::Failed;
+        }
+        stored.result = Some(result.clone());
+        json(result)
+    }
+}
@@ -19,6 +19,16 @@ pub trait Operation {
     fn method() -> Method;
     fn path(p: <Self::PathParams as TupleRef>::Ref, q: Option<&Self::QueryParams>) -> String;
 
+    /// Whether repeating the operation has the same effect as making it once, which makes it safe to retry
+    /// after transient failures. This defaults to the semantics of the HTTP method and is overridden for
+    /// operations that are exposed through `POST` but do not modify any state, e.g. encryption.
+    fn is_idempotent() -> bool {
+        matches!(
+            Self::method(),
+            Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+        )
+    }
+
     fn to_body(body: &Self::Body) -> Option<serde_json::Value> {
         Some(serde_json::to_value(body).expect("serialize to value"))
     }
@@ -0,0 +1,381 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::api_model::*;
+use crate::client::{Result, SdkmsClient};
+use crate::operations::*;
+
+use std::vec;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Query parameters of a list operation that supports cursor based pagination through a `sort` field, e.g.
+/// [`ListSobjectsParams`]. Listing approval requests does not support pagination, use
+/// `SdkmsClient::list_approval_requests()` instead.
+///
+/// [`ListSobjectsParams`]: ./api_model/struct.ListSobjectsParams.html
+pub trait Paginated: Clone {
+    type Item;
+    type Operation: Operation<
+        PathParams = (),
+        QueryParams = Self,
+        Body = (),
+        Output = Vec<Self::Item>,
+    >;
+
+    fn limit(&mut self) -> &mut Option<usize>;
+    fn offset(&mut self) -> &mut Option<usize>;
+    /// Sets the start of the next page to `item`, keeping the sort order. Returns `false` if `item` can not be used
+    /// as a cursor.
+    fn start_at(&mut self, item: &Self::Item) -> bool;
+    /// Whether `item` is the start of the page requested with these parameters.
+    fn is_start(&self, item: &Self::Item) -> bool;
+}
+
+/// Iterator over all the results of a list operation, fetching one page at a time.
+///
+/// Created by [`SdkmsClient::iter_sobjects()`] and similar methods. Pages are requested lazily, as the iterator is
+/// advanced. If a request fails, the error is returned and the iteration ends.
+///
+/// [`SdkmsClient::iter_sobjects()`]: ./struct.SdkmsClient.html#method.iter_sobjects
+pub struct Paginator<'a, P: Paginated> {
+    client: &'a SdkmsClient,
+    params: P,
+    page_size: usize,
+    page: vec::IntoIter<P::Item>,
+    skip_start: bool,
+    done: bool,
+}
+
+impl<'a, P: Paginated> Paginator<'a, P> {
+    pub(crate) fn new(client: &'a SdkmsClient, mut params: P) -> Self {
+        // Pages start at the last item of the previous page, so a page of 1 item would never make progress.
+        let page_size = params.limit().unwrap_or(DEFAULT_PAGE_SIZE).max(2);
+        *params.limit() = Some(page_size);
+        Paginator {
+            client,
+            params,
+            page_size,
+            page: Vec::new().into_iter(),
+            skip_start: false,
+            done: false,
+        }
+    }
+
+    fn next_page(&mut self) -> Result<Vec<P::Item>> {
+        let mut page = self
+            .client
+            .execute::<P::Operation>(&(), (), Some(&self.params))?;
+        self.done = page.len() < self.page_size;
+        if self.skip_start && matches!(page.first(), Some(item) if self.params.is_start(item)) {
+            page.remove(0);
+        }
+        match page.last() {
+            Some(last) if !self.done => {
+                *self.params.offset() = None;
+                self.done = !self.params.start_at(last);
+                self.skip_start = true;
+            }
+            _ => self.done = true,
+        }
+        Ok(page)
+    }
+}
+
+impl<'a, P: Paginated> Iterator for Paginator<'a, P> {
+    type Item = Result<P::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.page.next() {
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+            match self.next_page() {
+                Ok(page) => self.page = page.into_iter(),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl SdkmsClient {
+    /// Iterates over all security objects matching `query_params`, see [`Paginator`].
+    ///
+    /// [`Paginator`]: ./struct.Paginator.html
+    pub fn iter_sobjects(
+        &self,
+        query_params: ListSobjectsParams,
+    ) -> Paginator<'_, ListSobjectsParams> {
+        Paginator::new(self, query_params)
+    }
+
+    /// Iterates over all apps matching `query_params`, see [`Paginator`].
+    ///
+    /// [`Paginator`]: ./struct.Paginator.html
+    pub fn iter_apps(&self, query_params: ListAppsParams) -> Paginator<'_, ListAppsParams> {
+        Paginator::new(self, query_params)
+    }
+
+    /// Iterates over all users matching `query_params`, see [`Paginator`].
+    ///
+    /// [`Paginator`]: ./struct.Paginator.html
+    pub fn iter_users(&self, query_params: ListUsersParams) -> Paginator<'_, ListUsersParams> {
+        Paginator::new(self, query_params)
+    }
+
+    /// Iterates over all plugins matching `query_params`, see [`Paginator`].
+    ///
+    /// [`Paginator`]: ./struct.Paginator.html
+    pub fn iter_plugins(
+        &self,
+        query_params: ListPluginsParams,
+    ) -> Paginator<'_, ListPluginsParams> {
+        Paginator::new(self, query_params)
+    }
+}
+
+impl Paginated for ListSobjectsParams {
+    type Item = Sobject;
+    type Operation = OperationListSobjects;
+
+    fn limit(&mut self) -> &mut Option<usize> {
+        &mut self.limit
+    }
+
+    fn offset(&mut self) -> &mut Option<usize> {
+        &mut self.offset
+    }
+
+    fn start_at(&mut self, item: &Sobject) -> bool {
+        match self.sort {
+            SobjectSort::ByKid { ref mut start, .. } => {
+                *start = item.kid;
+                start.is_some()
+            }
+            SobjectSort::ByName { ref mut start, .. } => {
+                *start = item.name.clone();
+                start.is_some()
+            }
+        }
+    }
+
+    fn is_start(&self, item: &Sobject) -> bool {
+        match self.sort {
+            SobjectSort::ByKid { ref start, .. } => start.is_some() && *start == item.kid,
+            SobjectSort::ByName { ref start, .. } => start.is_some() && *start == item.name,
+        }
+    }
+}
+
+impl Paginated for ListAppsParams {
+    type Item = App;
+    type Operation = OperationListApps;
+
+    fn limit(&mut self) -> &mut Option<usize> {
+        &mut self.limit
+    }
+
+    fn offset(&mut self) -> &mut Option<usize> {
+        &mut self.offset
+    }
+
+    fn start_at(&mut self, item: &App) -> bool {
+        let AppSort::ByAppId { ref mut start, .. } = self.sort;
+        *start = Some(item.app_id);
+        true
+    }
+
+    fn is_start(&self, item: &App) -> bool {
+        let AppSort::ByAppId { ref start, .. } = self.sort;
+        *start == Some(item.app_id)
+    }
+}
+
+impl Paginated for ListUsersParams {
+    type Item = User;
+    type Operation = OperationListUsers;
+
+    fn limit(&mut self) -> &mut Option<usize> {
+        &mut self.limit
+    }
+
+    fn offset(&mut self) -> &mut Option<usize> {
+        &mut self.offset
+    }
+
+    fn start_at(&mut self, item: &User) -> bool {
+        let UserSort::ByUserId { ref mut start, .. } = self.sort;
+        *start = Some(item.user_id);
+        true
+    }
+
+    fn is_start(&self, item: &User) -> bool {
+        let UserSort::ByUserId { ref start, .. } = self.sort;
+        *start == Some(item.user_id)
+    }
+}
+
+impl Paginated for ListPluginsParams {
+    type Item = Plugin;
+    type Operation = OperationListPlugins;
+
+    fn limit(&mut self) -> &mut Option<usize> {
+        &mut self.limit
+    }
+
+    fn offset(&mut self) -> &mut Option<usize> {
+        &mut self.offset
+    }
+
+    fn start_at(&mut self, item: &Plugin) -> bool {
+        let PluginSort::ByPluginId { ref mut start, .. } = self.sort;
+        *start = Some(item.plugin_id);
+        true
+    }
+
+    fn is_start(&self, item: &Plugin) -> bool {
+        let PluginSort::ByPluginId { ref start, .. } = self.sort;
+        *start == Some(item.plugin_id)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+    use uuid::Uuid;
+
+    fn create_keys(client: &SdkmsClient, count: usize) -> Vec<Uuid> {
+        let mut kids: Vec<Uuid> = (0..count)
+            .map(|i| {
+                client
+                    .create_sobject(&SobjectRequest {
+                        name: Some(format!("key-{}", i)),
+                        obj_type: Some(ObjectType::Aes),
+                        key_size: Some(256),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .kid
+                    .unwrap()
+            })
+            .collect();
+        kids.sort();
+        kids
+    }
+
+    fn kids(sobjects: Paginator<'_, ListSobjectsParams>) -> Vec<Uuid> {
+        sobjects
+            .map(|sobject| sobject.unwrap().kid.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sobjects() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let created = create_keys(&client, 7);
+
+        for limit in [1, 3, 7, 100] {
+            let params = ListSobjectsParams {
+                limit: Some(limit),
+                ..Default::default()
+            };
+            assert_eq!(kids(client.iter_sobjects(params)), created);
+        }
+
+        // Iteration resumes from a caller-supplied cursor, which is included
+        let params = ListSobjectsParams {
+            limit: Some(2),
+            sort: SobjectSort::ByKid {
+                order: Order::Ascending,
+                start: Some(created[3]),
+            },
+            ..Default::default()
+        };
+        assert_eq!(kids(client.iter_sobjects(params)), created[3..]);
+
+        let params = ListSobjectsParams {
+            limit: Some(3),
+            sort: SobjectSort::ByName {
+                order: Order::Descending,
+                start: None,
+            },
+            ..Default::default()
+        };
+        let names: Vec<String> = client
+            .iter_sobjects(params)
+            .map(|sobject| sobject.unwrap().name.unwrap())
+            .collect();
+        let expected: Vec<String> = (0..7).rev().map(|i| format!("key-{}", i)).collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn error_ends_iteration() {
+        let server = MockServer::start().unwrap();
+        let client = SdkmsClient::builder()
+            .with_http_client(server.http_client())
+            .with_api_endpoint(&server.url())
+            .build()
+            .unwrap()
+            .authenticate_with_api_key(&server.api_key())
+            .unwrap();
+        create_keys(&client, 5);
+
+        let mut sobjects = client.iter_sobjects(ListSobjectsParams {
+            limit: Some(2),
+            ..Default::default()
+        });
+        assert!(sobjects.next().unwrap().is_ok());
+        assert!(sobjects.next().unwrap().is_ok());
+        server.expire_sessions();
+        assert!(matches!(sobjects.next(), Some(Err(Error::Unauthorized(_)))));
+        assert!(sobjects.next().is_none());
+    }
+
+    #[test]
+    fn apps_users_and_plugins() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let mut created = vec![server.app_id()];
+        for i in 0..4 {
+            let app = client
+                .create_app(
+                    None,
+                    &AppRequest {
+                        name: Some(format!("app {}", i)),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            created.push(app.app_id);
+        }
+        created.sort();
+        let app_ids: Vec<Uuid> = client
+            .iter_apps(ListAppsParams {
+                limit: Some(2),
+                ..Default::default()
+            })
+            .map(|app| app.unwrap().app_id)
+            .collect();
+        assert_eq!(app_ids, created);
+
+        // The mock server does not implement users and plugins, the first page fails and ends the iteration
+        let mut users = client.iter_users(ListUsersParams::default());
+        assert!(matches!(users.next(), Some(Err(Error::NotFound(_)))));
+        assert!(users.next().is_none());
+        let mut plugins = client.iter_plugins(ListPluginsParams::default());
+        assert!(matches!(plugins.next(), Some(Err(Error::NotFound(_)))));
+        assert!(plugins.next().is_none());
+    }
+}
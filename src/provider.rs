@@ -0,0 +1,168 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::api_model::*;
+use crate::client::{Result, SdkmsClient};
+
+use std::rc::Rc;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Cryptographic and key management operations on security objects, performed by SDKMS through [`SdkmsClient`] or
+/// in-process by `LocalBackend` (with the `local` feature).
+///
+/// Code written against this trait can switch between implementations through configuration, e.g. by holding a
+/// `Box<dyn CryptoProvider>`, and other implementations such as test doubles, caching wrappers or clients failing
+/// over between clusters can be plugged in. Method names and types are the same as those of the corresponding
+/// [`SdkmsClient`] methods.
+///
+/// The trait is also implemented for references and smart pointers to implementations, so wrappers can be generic
+/// over `P: CryptoProvider` and still share the inner provider.
+///
+/// [`SdkmsClient`]: ./struct.SdkmsClient.html
+pub trait CryptoProvider {
+    fn encrypt(&self, req: &EncryptRequest) -> Result<EncryptResponse>;
+    fn decrypt(&self, req: &DecryptRequest) -> Result<DecryptResponse>;
+    fn sign(&self, req: &SignRequest) -> Result<SignResponse>;
+    fn verify(&self, req: &VerifyRequest) -> Result<VerifyResponse>;
+    fn wrap(&self, req: &WrapKeyRequest) -> Result<WrapKeyResponse>;
+    fn unwrap(&self, req: &UnwrapKeyRequest) -> Result<Sobject>;
+    fn mac(&self, req: &MacRequest) -> Result<MacResponse>;
+    fn mac_verify(&self, req: &VerifyMacRequest) -> Result<VerifyResponse>;
+    fn create_digest(&self, req: &DigestRequest) -> Result<DigestResponse>;
+
+    /// Encrypts with multiple keys in one call. The default implementation calls [`encrypt()`](#tymethod.encrypt)
+    /// for each item, with the key set to the key id of the item.
+    #[allow(clippy::ptr_arg)]
+    fn batch_encrypt(
+        &self,
+        req: &Vec<BatchEncryptRequestItem>,
+    ) -> Result<Vec<BatchResponseItem<EncryptResponse>>> {
+        Ok(req
+            .iter()
+            .map(|item| {
+                batch_item(self.encrypt(&EncryptRequest {
+                    key: Some(SobjectDescriptor::Kid(item.kid)),
+                    ..item.request.clone()
+                }))
+            })
+            .collect())
+    }
+
+    /// Decrypts with multiple keys in one call. The default implementation calls [`decrypt()`](#tymethod.decrypt)
+    /// for each item, with the key set to the key id of the item.
+    #[allow(clippy::ptr_arg)]
+    fn batch_decrypt(
+        &self,
+        req: &Vec<BatchDecryptRequestItem>,
+    ) -> Result<Vec<BatchResponseItem<DecryptResponse>>> {
+        Ok(req
+            .iter()
+            .map(|item| {
+                batch_item(self.decrypt(&DecryptRequest {
+                    key: Some(SobjectDescriptor::Kid(item.kid)),
+                    ..item.request.clone()
+                }))
+            })
+            .collect())
+    }
+
+    fn create_sobject(&self, req: &SobjectRequest) -> Result<Sobject>;
+    fn import_sobject(&self, req: &SobjectRequest) -> Result<Sobject>;
+    fn get_sobject(
+        &self,
+        query_params: Option<&GetSobjectParams>,
+        req: &SobjectDescriptor,
+    ) -> Result<Sobject>;
+    fn export_sobject(&self, req: &SobjectDescriptor) -> Result<Sobject>;
+    fn delete_sobject(&self, id: &Uuid) -> Result<()>;
+
+    /// Releases a transient key that is no longer needed. Transient keys in SDKMS are bound to the session that
+    /// created them and cannot be deleted, so the default implementation does nothing.
+    fn drop_transient_key(&self, _transient_key: &Blob) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn batch_item<T>(result: Result<T>) -> BatchResponseItem<T> {
+    match result {
+        Ok(body) => BatchResponseItem::Success { status: 200, body },
+        Err(err) => BatchResponseItem::Error {
+            status: err.status().map_or(500, |status| status.as_u16()),
+            error: err.to_string(),
+        },
+    }
+}
+
+// Methods listed after `default` keep their default implementation for `SdkmsClient`.
+macro_rules! forward_crypto_provider {
+    ($(fn $name:ident(&self $(, $arg:ident: $t:ty)*) -> $ret:ty;)*
+     default { $(fn $dname:ident(&self $(, $darg:ident: $dt:ty)*) -> $dret:ty;)* }) => {
+        impl CryptoProvider for SdkmsClient {
+            $(fn $name(&self $(, $arg: $t)*) -> $ret {
+                SdkmsClient::$name(self $(, $arg)*)
+            })*
+        }
+
+        impl<'a, P: CryptoProvider + ?Sized> CryptoProvider for &'a P {
+            $(fn $name(&self $(, $arg: $t)*) -> $ret {
+                (**self).$name($($arg),*)
+            })*
+            $(fn $dname(&self $(, $darg: $dt)*) -> $dret {
+                (**self).$dname($($darg),*)
+            })*
+        }
+
+        impl<P: CryptoProvider + ?Sized> CryptoProvider for Box<P> {
+            $(fn $name(&self $(, $arg: $t)*) -> $ret {
+                (**self).$name($($arg),*)
+            })*
+            $(fn $dname(&self $(, $darg: $dt)*) -> $dret {
+                (**self).$dname($($darg),*)
+            })*
+        }
+
+        impl<P: CryptoProvider + ?Sized> CryptoProvider for Rc<P> {
+            $(fn $name(&self $(, $arg: $t)*) -> $ret {
+                (**self).$name($($arg),*)
+            })*
+            $(fn $dname(&self $(, $darg: $dt)*) -> $dret {
+                (**self).$dname($($darg),*)
+            })*
+        }
+
+        impl<P: CryptoProvider + ?Sized> CryptoProvider for Arc<P> {
+            $(fn $name(&self $(, $arg: $t)*) -> $ret {
+                (**self).$name($($arg),*)
+            })*
+            $(fn $dname(&self $(, $darg: $dt)*) -> $dret {
+                (**self).$dname($($darg),*)
+            })*
+        }
+    };
+}
+
+forward_crypto_provider! {
+    fn encrypt(&self, req: &EncryptRequest) -> Result<EncryptResponse>;
+    fn decrypt(&self, req: &DecryptRequest) -> Result<DecryptResponse>;
+    fn sign(&self, req: &SignRequest) -> Result<SignResponse>;
+    fn verify(&self, req: &VerifyRequest) -> Result<VerifyResponse>;
+    fn wrap(&self, req: &WrapKeyRequest) -> Result<WrapKeyResponse>;
+    fn unwrap(&self, req: &UnwrapKeyRequest) -> Result<Sobject>;
+    fn mac(&self, req: &MacRequest) -> Result<MacResponse>;
+    fn mac_verify(&self, req: &VerifyMacRequest) -> Result<VerifyResponse>;
+    fn create_digest(&self, req: &DigestRequest) -> Result<DigestResponse>;
+    fn batch_encrypt(&self, req: &Vec<BatchEncryptRequestItem>) -> Result<Vec<BatchResponseItem<EncryptResponse>>>;
+    fn batch_decrypt(&self, req: &Vec<BatchDecryptRequestItem>) -> Result<Vec<BatchResponseItem<DecryptResponse>>>;
+    fn create_sobject(&self, req: &SobjectRequest) -> Result<Sobject>;
+    fn import_sobject(&self, req: &SobjectRequest) -> Result<Sobject>;
+    fn get_sobject(&self, query_params: Option<&GetSobjectParams>, req: &SobjectDescriptor) -> Result<Sobject>;
+    fn export_sobject(&self, req: &SobjectDescriptor) -> Result<Sobject>;
+    fn delete_sobject(&self, id: &Uuid) -> Result<()>;
+    default {
+        fn drop_transient_key(&self, transient_key: &Blob) -> Result<()>;
+    }
+}
@@ -0,0 +1,609 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Public key formats.
+//!
+//! SDKMS returns the public key of RSA and EC security objects in [`Sobject::pub_key`] as a DER encoded
+//! `SubjectPublicKeyInfo`. [`PublicKey`] parses it and converts it to PEM and JWK ([RFC 7517]), and the reverse
+//! conversions build a [`SobjectRequest`] to import a public key:
+//!
+//! ```no_run
+//! # use sdkms::{api_model::*, public_key::*, SdkmsClient};
+//! # fn main() -> Result<(), sdkms::Error> {
+//! # let client = SdkmsClient::builder().build()?;
+//! # let kid = uuid::Uuid::nil();
+//! let sobject = client.get_sobject(None, &SobjectDescriptor::Kid(kid))?;
+//! let pem = sobject.public_key()?.to_pem()?;
+//! let jwk = sobject.public_key_jwk()?;
+//!
+//! let req = SobjectRequest::from_public_key_pem(&pem)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Jwks`] builds the JWK Set of a group of verification keys, e.g. to publish it to the verifiers of tokens signed
+//! with SDKMS keys, see [`SdkmsClient::group_jwks()`].
+//!
+//! RSA keys and EC keys on the NIST P-256, P-384 and P-521 curves, secp256k1, Ed25519 and X25519 are supported.
+//!
+//! [`Sobject::pub_key`]: ../api_model/struct.Sobject.html#structfield.pub_key
+//! [`PublicKey`]: ./enum.PublicKey.html
+//! [`SobjectRequest`]: ../api_model/struct.SobjectRequest.html
+//! [`Jwks`]: ./struct.Jwks.html
+//! [`SdkmsClient::group_jwks()`]: ../struct.SdkmsClient.html#method.group_jwks
+//! [RFC 7517]: https://tools.ietf.org/html/rfc7517
+
+use crate::api_model::*;
+use crate::client::{Result, SdkmsClient};
+
+use der::asn1::{AnyRef, BitString, ObjectIdentifier, UintRef};
+use der::pem::LineEnding;
+use der::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned, SubjectPublicKeyInfoRef};
+use uuid::Uuid;
+
+/// PEM label of `SubjectPublicKeyInfo`.
+pub const PEM_LABEL: &str = "PUBLIC KEY";
+
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const X25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.110");
+const ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// Named curves of `id-ecPublicKey`, with their JWK name and field size in bytes.
+const NAMED_CURVES: [(EllipticCurve, &str, &str, usize); 4] = [
+    (EllipticCurve::NistP256, "1.2.840.10045.3.1.7", "P-256", 32),
+    (EllipticCurve::NistP384, "1.3.132.0.34", "P-384", 48),
+    (EllipticCurve::NistP521, "1.3.132.0.35", "P-521", 66),
+    (EllipticCurve::SecP256K1, "1.3.132.0.10", "secp256k1", 32),
+];
+
+/// Curves with keys encoded as a single coordinate (`OKP` in JWK).
+const OKP_CURVES: [(EllipticCurve, ObjectIdentifier, &str); 2] = [
+    (EllipticCurve::Ed25519, ED25519, "Ed25519"),
+    (EllipticCurve::X25519, X25519, "X25519"),
+];
+
+fn der_error(err: der::Error) -> Error {
+    Error::invalid_input(format!("invalid public key: {}", err))
+}
+
+fn unsupported_curve(curve: EllipticCurve) -> Error {
+    Error::invalid_input(format!("elliptic curve {:?} is not supported", curve))
+}
+
+/// Whether the public key of `sobject` can be published in a JWK Set: it must be used for signatures and be an RSA
+/// key or an EC key on a curve with a JWK name.
+fn is_publishable(sobject: &Sobject) -> bool {
+    let curve_supported = |curve| {
+        NAMED_CURVES.iter().any(|(c, _, _, _)| *c == curve)
+            || OKP_CURVES.iter().any(|(c, _, _)| *c == curve)
+    };
+    let supported = match sobject.obj_type {
+        ObjectType::Rsa => true,
+        ObjectType::Ec => sobject.elliptic_curve.map_or(false, curve_supported),
+        _ => false,
+    };
+    supported
+        && sobject
+            .key_ops
+            .intersects(KeyOperations::SIGN | KeyOperations::VERIFY)
+}
+
+fn b64url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn from_b64url(field: &str, value: Option<&String>) -> Result<Vec<u8>> {
+    let value = value.ok_or_else(|| Error::invalid_input(format!("JWK is missing `{}`", field)))?;
+    base64::decode_config(value, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| Error::invalid_input(format!("JWK `{}` is not valid base64url", field)))
+}
+
+/// An RSA or EC public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+    /// Big-endian modulus and public exponent, without leading zeros.
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    /// Point on a short Weierstrass curve, with big-endian coordinates padded to the field size.
+    Ec {
+        curve: EllipticCurve,
+        x: Vec<u8>,
+        y: Vec<u8>,
+    },
+    /// Ed25519 or X25519 key.
+    Okp { curve: EllipticCurve, x: Vec<u8> },
+}
+
+impl PublicKey {
+    /// Parses a DER encoded `SubjectPublicKeyInfo`.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let spki = SubjectPublicKeyInfoRef::from_der(der).map_err(der_error)?;
+        let key = spki
+            .subject_public_key
+            .as_bytes()
+            .ok_or_else(|| Error::invalid_input("invalid public key: unused bits"))?;
+        let oid = spki.algorithm.oid;
+        if oid == RSA_ENCRYPTION {
+            let key = pkcs1::RsaPublicKey::from_der(key).map_err(der_error)?;
+            return Ok(PublicKey::Rsa {
+                n: key.modulus.as_bytes().to_vec(),
+                e: key.public_exponent.as_bytes().to_vec(),
+            });
+        }
+        if oid == EC_PUBLIC_KEY {
+            let curve = spki
+                .algorithm
+                .parameters
+                .ok_or_else(|| Error::invalid_input("EC public key has no curve"))?
+                .decode_as::<ObjectIdentifier>()
+                .map_err(der_error)?;
+            let (curve, _, _, size) = NAMED_CURVES
+                .iter()
+                .find(|(_, oid, _, _)| ObjectIdentifier::new_unwrap(oid) == curve)
+                .ok_or_else(|| {
+                    Error::invalid_input(format!("unsupported elliptic curve {}", curve))
+                })?;
+            return match key.split_first() {
+                Some((4, point)) if point.len() == 2 * size => Ok(PublicKey::Ec {
+                    curve: *curve,
+                    x: point[..*size].to_vec(),
+                    y: point[*size..].to_vec(),
+                }),
+                _ => Err(Error::invalid_input(
+                    "only uncompressed EC points are supported",
+                )),
+            };
+        }
+        match OKP_CURVES
+            .iter()
+            .find(|(_, curve_oid, _)| *curve_oid == oid)
+        {
+            Some((curve, _, _)) if key.len() == 32 => Ok(PublicKey::Okp {
+                curve: *curve,
+                x: key.to_vec(),
+            }),
+            Some((curve, _, _)) => Err(Error::invalid_input(format!(
+                "invalid {:?} public key",
+                curve
+            ))),
+            None => Err(Error::invalid_input(format!(
+                "unsupported public key algorithm {}",
+                oid
+            ))),
+        }
+    }
+
+    /// Parses a PEM encoded `SubjectPublicKeyInfo`.
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let (label, der) = der::pem::decode_vec(pem.trim().as_bytes())
+            .map_err(|e| Error::invalid_input(format!("invalid PEM: {}", e)))?;
+        if label != PEM_LABEL {
+            return Err(Error::invalid_input(format!(
+                "expected a PEM \"{}\", found \"{}\"",
+                PEM_LABEL, label
+            )));
+        }
+        Self::from_der(&der)
+    }
+
+    /// Encodes the key as a DER `SubjectPublicKeyInfo`.
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        let (algorithm, key) = match *self {
+            PublicKey::Rsa { ref n, ref e } => {
+                let key = pkcs1::RsaPublicKey {
+                    modulus: UintRef::new(n).map_err(der_error)?,
+                    public_exponent: UintRef::new(e).map_err(der_error)?,
+                };
+                let algorithm = AlgorithmIdentifierOwned {
+                    oid: RSA_ENCRYPTION,
+                    parameters: Some(AnyRef::NULL.into()),
+                };
+                (algorithm, key.to_der().map_err(der_error)?)
+            }
+            PublicKey::Ec {
+                curve,
+                ref x,
+                ref y,
+            } => {
+                let (_, oid, _, size) = NAMED_CURVES
+                    .iter()
+                    .find(|(c, _, _, _)| *c == curve)
+                    .ok_or_else(|| unsupported_curve(curve))?;
+                if x.len() != *size || y.len() != *size {
+                    return Err(Error::invalid_input(format!(
+                        "invalid {:?} public key",
+                        curve
+                    )));
+                }
+                let curve = ObjectIdentifier::new_unwrap(oid);
+                let algorithm = AlgorithmIdentifierOwned {
+                    oid: EC_PUBLIC_KEY,
+                    parameters: Some(curve.into()),
+                };
+                (algorithm, [&[4][..], x, y].concat())
+            }
+            PublicKey::Okp { curve, ref x } => {
+                let (_, oid, _) = OKP_CURVES
+                    .iter()
+                    .find(|(c, _, _)| *c == curve)
+                    .ok_or_else(|| unsupported_curve(curve))?;
+                let algorithm = AlgorithmIdentifierOwned {
+                    oid: *oid,
+                    parameters: None,
+                };
+                (algorithm, x.clone())
+            }
+        };
+        SubjectPublicKeyInfoOwned {
+            algorithm,
+            subject_public_key: BitString::from_bytes(&key).map_err(der_error)?,
+        }
+        .to_der()
+        .map_err(der_error)
+    }
+
+    /// Encodes the key as a PEM `SubjectPublicKeyInfo`, with `\n` line endings.
+    pub fn to_pem(&self) -> Result<String> {
+        der::pem::encode_string(PEM_LABEL, LineEnding::LF, &self.to_der()?)
+            .map_err(|e| Error::encoding(format!("PEM encoding failed: {}", e)))
+    }
+
+    /// The key as a JWK, without `kid`, `use` and `alg`.
+    pub fn to_jwk(&self) -> Result<Jwk> {
+        Ok(match *self {
+            PublicKey::Rsa { ref n, ref e } => Jwk {
+                kty: "RSA".to_owned(),
+                n: Some(b64url(n)),
+                e: Some(b64url(e)),
+                ..Default::default()
+            },
+            PublicKey::Ec {
+                curve,
+                ref x,
+                ref y,
+            } => {
+                let (_, _, name, _) = NAMED_CURVES
+                    .iter()
+                    .find(|(c, _, _, _)| *c == curve)
+                    .ok_or_else(|| unsupported_curve(curve))?;
+                Jwk {
+                    kty: "EC".to_owned(),
+                    crv: Some((*name).to_owned()),
+                    x: Some(b64url(x)),
+                    y: Some(b64url(y)),
+                    ..Default::default()
+                }
+            }
+            PublicKey::Okp { curve, ref x } => {
+                let (_, _, name) = OKP_CURVES
+                    .iter()
+                    .find(|(c, _, _)| *c == curve)
+                    .ok_or_else(|| unsupported_curve(curve))?;
+                Jwk {
+                    kty: "OKP".to_owned(),
+                    crv: Some((*name).to_owned()),
+                    x: Some(b64url(x)),
+                    ..Default::default()
+                }
+            }
+        })
+    }
+
+    /// Parses a public JWK. Private key members are ignored.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        let crv = || {
+            jwk.crv
+                .as_deref()
+                .ok_or_else(|| Error::invalid_input("JWK is missing `crv`"))
+        };
+        let key = match jwk.kty.as_str() {
+            "RSA" => {
+                let strip = |bytes: Vec<u8>| -> Vec<u8> {
+                    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+                    bytes[zeros..].to_vec()
+                };
+                PublicKey::Rsa {
+                    n: strip(from_b64url("n", jwk.n.as_ref())?),
+                    e: strip(from_b64url("e", jwk.e.as_ref())?),
+                }
+            }
+            "EC" => {
+                let crv = crv()?;
+                let (curve, _, _, _) = NAMED_CURVES
+                    .iter()
+                    .find(|(_, _, name, _)| *name == crv)
+                    .ok_or_else(|| {
+                        Error::invalid_input(format!("unsupported JWK curve {}", crv))
+                    })?;
+                PublicKey::Ec {
+                    curve: *curve,
+                    x: from_b64url("x", jwk.x.as_ref())?,
+                    y: from_b64url("y", jwk.y.as_ref())?,
+                }
+            }
+            "OKP" => {
+                let crv = crv()?;
+                let (curve, _, _) = OKP_CURVES
+                    .iter()
+                    .find(|(_, _, name)| *name == crv)
+                    .ok_or_else(|| {
+                        Error::invalid_input(format!("unsupported JWK curve {}", crv))
+                    })?;
+                PublicKey::Okp {
+                    curve: *curve,
+                    x: from_b64url("x", jwk.x.as_ref())?,
+                }
+            }
+            kty => {
+                return Err(Error::invalid_input(format!(
+                    "unsupported JWK key type {}",
+                    kty
+                )))
+            }
+        };
+        // Catches invalid coordinate lengths
+        key.to_der()?;
+        Ok(key)
+    }
+
+    pub fn obj_type(&self) -> ObjectType {
+        match *self {
+            PublicKey::Rsa { .. } => ObjectType::Rsa,
+            PublicKey::Ec { .. } | PublicKey::Okp { .. } => ObjectType::Ec,
+        }
+    }
+
+    pub fn elliptic_curve(&self) -> Option<EllipticCurve> {
+        match *self {
+            PublicKey::Rsa { .. } => None,
+            PublicKey::Ec { curve, .. } | PublicKey::Okp { curve, .. } => Some(curve),
+        }
+    }
+}
+
+/// A JSON Web Key ([RFC 7517]). Only the members of public keys are represented, values are base64url encoded.
+///
+/// [RFC 7517]: https://tools.ietf.org/html/rfc7517
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_ops: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+impl Sobject {
+    /// Parses the public key of an RSA or EC security object.
+    pub fn public_key(&self) -> Result<PublicKey> {
+        if !matches!(self.obj_type, ObjectType::Rsa | ObjectType::Ec) {
+            return Err(Error::invalid_input(format!(
+                "{:?} objects do not have a public key",
+                self.obj_type
+            )));
+        }
+        let der = self
+            .pub_key
+            .as_ref()
+            .ok_or_else(|| Error::invalid_input("sobject has no public key"))?;
+        let key = PublicKey::from_der(der)?;
+        if key.obj_type() != self.obj_type
+            || self
+                .elliptic_curve
+                .iter()
+                .any(|curve| key.elliptic_curve() != Some(*curve))
+        {
+            return Err(Error::invalid_input(
+                "public key does not match the sobject type",
+            ));
+        }
+        Ok(key)
+    }
+
+    /// The public key as a JWK, with the key id as `kid` and the operations the public key can be used for as
+    /// `key_ops`.
+    pub fn public_key_jwk(&self) -> Result<Jwk> {
+        let ops = [
+            (KeyOperations::SIGN | KeyOperations::VERIFY, "verify"),
+            (KeyOperations::ENCRYPT | KeyOperations::DECRYPT, "encrypt"),
+            (KeyOperations::WRAPKEY | KeyOperations::UNWRAPKEY, "wrapKey"),
+            (KeyOperations::AGREEKEY, "deriveKey"),
+        ];
+        let key_ops = ops
+            .iter()
+            .filter(|(ops, _)| self.key_ops.intersects(*ops))
+            .map(|(_, op)| (*op).to_owned())
+            .collect();
+        Ok(Jwk {
+            kid: self.kid.map(|kid| kid.to_string()),
+            key_ops: Some(key_ops),
+            ..self.public_key()?.to_jwk()?
+        })
+    }
+}
+
+/// A JWK Set ([RFC 7517] section 5).
+///
+/// [RFC 7517]: https://tools.ietf.org/html/rfc7517#section-5
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+impl Jwks {
+    /// Builds a JWK Set from the public keys of `sobjects`, see [`Sobject::public_key_jwk()`]. Only active keys used
+    /// for signatures are included: keys which are not in the `Active` state, whose deactivation date has passed,
+    /// which have neither the `SIGN` nor the `VERIFY` operation, which are on a curve without a JWK name (e.g.
+    /// NistP224 or X448), or whose public key can not be parsed are skipped.
+    ///
+    /// [`Sobject::public_key_jwk()`]: ../api_model/struct.Sobject.html#method.public_key_jwk
+    pub fn from_sobjects<'a, I>(sobjects: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Sobject>,
+    {
+        let now = Time::now();
+        let keys = sobjects
+            .into_iter()
+            .filter(|sobject| sobject.is_active(now) && is_publishable(sobject))
+            .filter_map(|sobject| sobject.public_key_jwk().ok())
+            .collect();
+        Jwks { keys }
+    }
+
+    /// Serializes the JWK Set to JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl SdkmsClient {
+    /// Builds a JWK Set from the signing keys in a group, see [`Jwks::from_sobjects()`]. Other security objects in
+    /// the group are ignored.
+    ///
+    /// [`Jwks::from_sobjects()`]: ./public_key/struct.Jwks.html#method.from_sobjects
+    pub fn group_jwks(&self, group_id: &Uuid) -> Result<Jwks> {
+        let params = ListSobjectsParams {
+            group_id: Some(*group_id),
+            ..Default::default()
+        };
+        let sobjects = self.iter_sobjects(params).collect::<Result<Vec<_>>>()?;
+        Ok(Jwks::from_sobjects(&sobjects))
+    }
+
+    /// Builds a JWK Set from the given signing keys, see [`Jwks::from_sobjects()`].
+    ///
+    /// [`Jwks::from_sobjects()`]: ./public_key/struct.Jwks.html#method.from_sobjects
+    pub fn jwks(&self, keys: &[SobjectDescriptor]) -> Result<Jwks> {
+        let sobjects = keys
+            .iter()
+            .map(|key| self.get_sobject(None, key))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Jwks::from_sobjects(&sobjects))
+    }
+}
+
+impl SobjectRequest {
+    /// A request to import `key`.
+    pub fn from_public_key(key: &PublicKey) -> Result<Self> {
+        Ok(SobjectRequest {
+            obj_type: Some(key.obj_type()),
+            elliptic_curve: key.elliptic_curve(),
+            value: Some(key.to_der()?.into()),
+            ..Default::default()
+        })
+    }
+
+    /// A request to import a PEM encoded `SubjectPublicKeyInfo`.
+    pub fn from_public_key_pem(pem: &str) -> Result<Self> {
+        Self::from_public_key(&PublicKey::from_pem(pem)?)
+    }
+
+    /// A request to import a public JWK. The `kid` of the JWK is used as the name of the security object.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        Ok(SobjectRequest {
+            name: jwk.kid.clone(),
+            ..Self::from_public_key(&PublicKey::from_jwk(jwk)?)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 7517 appendix A.1
+    const EC_JWK: &str = r#"{"kty":"EC","crv":"P-256",
+        "x":"MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4",
+        "y":"4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM"}"#;
+
+    #[test]
+    fn conversions() {
+        let jwk: Jwk = serde_json::from_str(EC_JWK).unwrap();
+        let key = PublicKey::from_jwk(&jwk).unwrap();
+        let pem = key.to_pem().unwrap();
+        assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+        assert_eq!(PublicKey::from_pem(&pem).unwrap(), key);
+        assert_eq!(key.to_jwk().unwrap(), jwk);
+
+        let req = SobjectRequest::from_public_key_pem(&pem).unwrap();
+        assert_eq!(req.obj_type, Some(ObjectType::Ec));
+        assert_eq!(req.elliptic_curve, Some(EllipticCurve::NistP256));
+
+        let rsa = PublicKey::Rsa {
+            n: vec![0xc5; 128],
+            e: vec![1, 0, 1],
+        };
+        let der = rsa.to_der().unwrap();
+        assert_eq!(PublicKey::from_der(&der).unwrap(), rsa);
+        assert_eq!(rsa.to_jwk().unwrap().e.as_deref(), Some("AQAB"));
+    }
+
+    #[cfg(feature = "local")]
+    #[test]
+    fn jwks() {
+        use crate::local::LocalBackend;
+        use crate::CryptoProvider;
+
+        let backend = LocalBackend::new();
+        let create = |name: &str, deactivation_date| {
+            backend
+                .create_sobject(&SobjectRequest {
+                    name: Some(name.to_owned()),
+                    obj_type: Some(ObjectType::Ec),
+                    elliptic_curve: Some(EllipticCurve::NistP256),
+                    key_ops: Some(KeyOperations::SIGN | KeyOperations::VERIFY),
+                    deactivation_date,
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        let active = create("active", None);
+        let expired = create("expired", Some(Time(1)));
+        let encryption_only = Sobject {
+            key_ops: KeyOperations::ENCRYPT | KeyOperations::DECRYPT,
+            ..active.clone()
+        };
+        let unsupported_curve = Sobject {
+            elliptic_curve: Some(EllipticCurve::NistP224),
+            ..active.clone()
+        };
+        let invalid_public_key = Sobject {
+            pub_key: Some(vec![0x30, 0x00].into()),
+            ..active.clone()
+        };
+        let jwks = Jwks::from_sobjects(&[
+            active.clone(),
+            expired,
+            encryption_only,
+            unsupported_curve,
+            invalid_public_key,
+        ]);
+        assert_eq!(jwks.keys.len(), 1);
+        let jwk = &jwks.keys[0];
+        assert_eq!(jwk.kid, active.kid.map(|kid| kid.to_string()));
+        assert_eq!(jwk.key_ops, Some(vec!["verify".to_owned()]));
+        assert_eq!(
+            PublicKey::from_jwk(jwk).unwrap(),
+            active.public_key().unwrap()
+        );
+    }
+}
@@ -0,0 +1,608 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Bulk re-encryption of stored data after key rotation.
+//!
+//! [`Reencryptor`] takes stored [`Ciphertext`]s and [`WrappedKey`]s, decrypts or unwraps them with the version of the
+//! key that protects them, and re-encrypts or re-wraps them with the latest active version of that key, found by
+//! following [`KeyLinks::replacement`] (see [`KeyVersions`]). Ciphertexts are processed with
+//! `batch_decrypt()`/`batch_encrypt()`. Wrapped keys are unwrapped into transient keys, so their value never leaves
+//! SDKMS. Items already protected by the latest version are skipped.
+//!
+//! Items are processed in rounds of `concurrency` batches running in parallel. After each round the results are
+//! stored in input order and a [`Progress`] is reported. The progress can be persisted and passed to a later run over
+//! the same input to resume after the last completed round:
+//!
+//! ```no_run
+//! # use sdkms::{reencrypt::*, SdkmsClient};
+//! # fn load_items() -> Vec<Item> { Vec::new() }
+//! # fn main() -> Result<(), sdkms::Error> {
+//! # let client = SdkmsClient::builder().build()?;
+//! let progress = Progress::default(); // or the last checkpoint
+//! let progress = Reencryptor::new(&client).with_concurrency(8).run(
+//!     load_items(),
+//!     progress,
+//!     |id, payload| {
+//!         // write `payload` back to storage for `id`
+//!         Ok(())
+//!     },
+//!     |progress| {
+//!         // persist `progress`, e.g. serialized with serde_json
+//!         Ok(())
+//!     },
+//! )?;
+//! for failure in &progress.failures {
+//!     eprintln!("{}: {}", failure.id, failure.error);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Reencryptor`]: ./struct.Reencryptor.html
+//! [`Ciphertext`]: ../ciphertext/struct.Ciphertext.html
+//! [`WrappedKey`]: ./struct.WrappedKey.html
+//! [`KeyLinks::replacement`]: ../api_model/struct.KeyLinks.html#structfield.replacement
+//! [`KeyVersions`]: ../rotation/struct.KeyVersions.html
+//! [`Progress`]: ./struct.Progress.html
+
+use crate::api_model::*;
+use crate::ciphertext::Ciphertext;
+use crate::client::Result;
+use crate::provider::CryptoProvider;
+use crate::rotation::KeyVersions;
+
+use serde::{Deserialize, Serialize};
+use simple_hyper_client::StatusCode;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use uuid::Uuid;
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_CONCURRENCY: usize = 4;
+
+fn batch_result<T>(item: BatchResponseItem<T>) -> Result<T> {
+    match item {
+        BatchResponseItem::Success { body, .. } => Ok(body),
+        BatchResponseItem::Error { status, error } => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ApiError::new(status, &error).into())
+        }
+    }
+}
+
+/// A key wrapped with a key encryption key in SDKMS.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct WrappedKey {
+    /// Id of the key encryption key.
+    pub kek: Uuid,
+    pub alg: Algorithm,
+    #[serde(default)]
+    pub mode: Option<CryptMode>,
+    /// Type of the wrapped key.
+    pub obj_type: ObjectType,
+    pub wrapped_key: Blob,
+    #[serde(default)]
+    pub iv: Option<Blob>,
+    #[serde(default)]
+    pub tag: Option<Blob>,
+}
+
+impl WrappedKey {
+    /// Combines a wrap request with the key encryption key `kek` and the response of SDKMS.
+    pub fn new(
+        kek: Uuid,
+        obj_type: ObjectType,
+        req: &WrapKeyRequest,
+        resp: WrapKeyResponse,
+    ) -> Self {
+        WrappedKey {
+            kek,
+            alg: req.alg,
+            mode: req.mode.clone(),
+            obj_type,
+            wrapped_key: resp.wrapped_key,
+            iv: resp.iv,
+            tag: resp.tag,
+        }
+    }
+}
+
+/// Stored data protected by a key in SDKMS.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub enum Payload {
+    Ciphertext(Ciphertext),
+    WrappedKey(WrappedKey),
+}
+
+impl Payload {
+    /// Id of the key protecting the payload.
+    pub fn kid(&self) -> Uuid {
+        match *self {
+            Payload::Ciphertext(ref ciphertext) => ciphertext.kid,
+            Payload::WrappedKey(ref wrapped) => wrapped.kek,
+        }
+    }
+}
+
+/// An item to re-encrypt, `id` identifies it in the storage and in [`Progress`].
+///
+/// [`Progress`]: ./struct.Progress.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Item {
+    pub id: String,
+    pub payload: Payload,
+    /// Authenticated data a ciphertext payload was encrypted with, it is not stored in the ciphertext.
+    pub ad: Option<Blob>,
+}
+
+/// An item that could not be re-encrypted.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ItemFailure {
+    pub id: String,
+    pub error: String,
+}
+
+/// Progress of a re-encryption run, reported after each round. A run resumed with a progress skips the first
+/// `position` items of its input.
+#[derive(Debug, Default, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Progress {
+    /// Number of items of the input processed so far.
+    pub position: u64,
+    /// Number of items re-encrypted and stored.
+    pub reencrypted: u64,
+    /// Number of items already protected by the latest version of their key.
+    pub skipped: u64,
+    pub failures: Vec<ItemFailure>,
+}
+
+/// Re-encrypts stored data with the latest version of its key, see the [module documentation](./index.html).
+pub struct Reencryptor<P> {
+    provider: P,
+    batch_size: usize,
+    concurrency: usize,
+    latest: Mutex<HashMap<Uuid, Uuid>>,
+}
+
+impl<P: CryptoProvider + Sync> Reencryptor<P> {
+    pub fn new(provider: P) -> Self {
+        Reencryptor {
+            provider,
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the number of items per batch request, 100 by default.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the number of batches processed in parallel, 4 by default.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Re-encrypts `items`, starting after `progress.position`. The new payload of each re-encrypted item is passed
+    /// to `store`, in input order, and `checkpoint` is called after each round.
+    ///
+    /// Items that fail are recorded in the progress and do not stop the run. A failed batch request, like an error
+    /// returned by `store` or `checkpoint`, stops the run; the last checkpoint can then be used to resume it.
+    pub fn run<I, S, C>(
+        &self,
+        items: I,
+        mut progress: Progress,
+        mut store: S,
+        mut checkpoint: C,
+    ) -> Result<Progress>
+    where
+        I: IntoIterator<Item = Item>,
+        S: FnMut(&str, Payload) -> Result<()>,
+        C: FnMut(&Progress) -> Result<()>,
+    {
+        let mut items = items.into_iter().skip(progress.position as usize);
+        loop {
+            let round: Vec<Item> = items
+                .by_ref()
+                .take(self.batch_size * self.concurrency)
+                .collect();
+            if round.is_empty() {
+                return Ok(progress);
+            }
+            let results = thread::scope(|scope| {
+                let handles: Vec<_> = round
+                    .chunks(self.batch_size)
+                    .map(|batch| scope.spawn(move || self.process_batch(batch)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("re-encryption thread panicked"))
+                    .collect::<Result<Vec<_>>>()
+            })?;
+            for (item, result) in round.iter().zip(results.into_iter().flatten()) {
+                match result {
+                    Ok(Some(payload)) => {
+                        store(&item.id, payload)?;
+                        progress.reencrypted += 1;
+                    }
+                    Ok(None) => progress.skipped += 1,
+                    Err(err) => progress.failures.push(ItemFailure {
+                        id: item.id.clone(),
+                        error: err.to_string(),
+                    }),
+                }
+            }
+            progress.position += round.len() as u64;
+            checkpoint(&progress)?;
+        }
+    }
+
+    /// The latest active version of the key `kid` belongs to.
+    fn latest(&self, kid: Uuid) -> Result<Uuid> {
+        if let Some(latest) = self.latest.lock().unwrap().get(&kid) {
+            return Ok(*latest);
+        }
+        let latest = KeyVersions::new(&self.provider, SobjectDescriptor::Kid(kid))
+            .latest()?
+            .kid
+            .ok_or_else(|| Error::unexpected_response("the key has no key id"))?;
+        self.latest.lock().unwrap().insert(kid, latest);
+        Ok(latest)
+    }
+
+    /// Re-encrypts a batch of items, returns `None` for items that are already protected by the latest version.
+    /// Fails if a batch request fails as a whole.
+    fn process_batch(&self, items: &[Item]) -> Result<Vec<Result<Option<Payload>>>> {
+        let mut results: Vec<Result<Option<Payload>>> = Vec::with_capacity(items.len());
+        let mut ciphertexts = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let kid = item.payload.kid();
+            results.push(match self.latest(kid) {
+                Ok(latest) if latest == kid => Ok(None),
+                Ok(latest) => match item.payload {
+                    Payload::Ciphertext(ref ciphertext) => {
+                        ciphertexts.push((i, ciphertext, item.ad.as_deref(), latest));
+                        Ok(None)
+                    }
+                    Payload::WrappedKey(ref wrapped) => self
+                        .rewrap(wrapped, latest)
+                        .map(|wrapped| Some(Payload::WrappedKey(wrapped))),
+                },
+                Err(err) => Err(err),
+            });
+        }
+        if !ciphertexts.is_empty() {
+            let reencrypted = self.reencrypt(&ciphertexts)?;
+            for ((i, _, _, _), result) in ciphertexts.iter().zip(reencrypted) {
+                results[*i] = result.map(|ciphertext| Some(Payload::Ciphertext(ciphertext)));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Decrypts `ciphertexts` and encrypts them with their latest key, with one batch request each.
+    fn reencrypt(
+        &self,
+        ciphertexts: &[(usize, &Ciphertext, Option<&[u8]>, Uuid)],
+    ) -> Result<Vec<Result<Ciphertext>>> {
+        let mut results: Vec<Result<Ciphertext>> = Vec::with_capacity(ciphertexts.len());
+        let mut decrypt_items = Vec::new();
+        for (_, ciphertext, ad, _) in ciphertexts {
+            match ciphertext.decrypt_request(*ad) {
+                Ok(request) => {
+                    decrypt_items.push((results.len(), request));
+                    results.push(Err(Error::invalid_input("not decrypted")));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+        if decrypt_items.is_empty() {
+            return Ok(results);
+        }
+
+        let batch = decrypt_items
+            .iter()
+            .map(|(i, request)| BatchDecryptRequestItem {
+                kid: ciphertexts[*i].1.kid,
+                request: request.clone(),
+            })
+            .collect();
+        let decrypted = self.provider.batch_decrypt(&batch)?;
+        if decrypted.len() != decrypt_items.len() {
+            return Err(Error::unexpected_response(
+                "unexpected number of batch results",
+            ));
+        }
+        let mut encrypt_items = Vec::new();
+        for ((i, request), decrypted) in decrypt_items.into_iter().zip(decrypted) {
+            let (_, ciphertext, _, latest) = ciphertexts[i];
+            match batch_result(decrypted) {
+                Ok(decrypted) => {
+                    let request = EncryptRequest {
+                        key: Some(SobjectDescriptor::Kid(latest)),
+                        alg: ciphertext.alg,
+                        plain: decrypted.plain,
+                        mode: ciphertext.mode.clone(),
+                        iv: None,
+                        ad: request.ad,
+                        tag_len: ciphertext.tag.as_ref().map(|tag| tag.len() * 8),
+                    };
+                    encrypt_items.push((i, request));
+                    results[i] = Err(Error::invalid_input("not encrypted"));
+                }
+                Err(err) => results[i] = Err(err),
+            }
+        }
+        if encrypt_items.is_empty() {
+            return Ok(results);
+        }
+
+        let batch = encrypt_items
+            .iter()
+            .map(|(i, request)| BatchEncryptRequestItem {
+                kid: ciphertexts[*i].3,
+                request: request.clone(),
+            })
+            .collect();
+        let encrypted = self.provider.batch_encrypt(&batch)?;
+        if encrypted.len() != encrypt_items.len() {
+            return Err(Error::unexpected_response(
+                "unexpected number of batch results",
+            ));
+        }
+        for ((i, request), encrypted) in encrypt_items.iter().zip(encrypted) {
+            results[*i] = batch_result(encrypted).and_then(|resp| Ciphertext::new(request, resp));
+        }
+        Ok(results)
+    }
+
+    /// Unwraps `wrapped` into a transient key and wraps it with `latest`. The transient key is released afterwards.
+    fn rewrap(&self, wrapped: &WrappedKey, latest: Uuid) -> Result<WrappedKey> {
+        let transient = self.provider.unwrap(&UnwrapKeyRequest {
+            key: Some(SobjectDescriptor::Kid(wrapped.kek)),
+            alg: wrapped.alg,
+            obj_type: wrapped.obj_type,
+            rsa: None,
+            wrapped_key: wrapped.wrapped_key.clone(),
+            mode: wrapped.mode.clone(),
+            iv: wrapped.iv.clone(),
+            ad: None,
+            tag: wrapped.tag.clone(),
+            name: None,
+            group_id: None,
+            enabled: None,
+            description: None,
+            custom_metadata: None,
+            key_ops: Some(KeyOperations::EXPORT),
+            transient: Some(true),
+        })?;
+        let transient_key = transient
+            .transient_key
+            .ok_or_else(|| Error::unexpected_response("unwrapped key is not transient"))?;
+        let req = WrapKeyRequest {
+            key: Some(SobjectDescriptor::Kid(latest)),
+            subject: Some(SobjectDescriptor::TransientKey(transient_key.clone())),
+            kid: None,
+            alg: wrapped.alg,
+            mode: wrapped.mode.clone(),
+            iv: None,
+            ad: None,
+            tag_len: wrapped.tag.as_ref().map(|tag| tag.len() * 8),
+        };
+        let resp = self.provider.wrap(&req);
+        let dropped = self.provider.drop_transient_key(&transient_key);
+        let resp = resp?;
+        dropped?;
+        Ok(WrappedKey::new(latest, wrapped.obj_type, &req, resp))
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::ciphertext;
+    use crate::mock::MockServer;
+
+    fn create_key<P: CryptoProvider + ?Sized>(
+        provider: &P,
+        name: &str,
+        key_ops: Option<KeyOperations>,
+    ) -> Uuid {
+        provider
+            .create_sobject(&SobjectRequest {
+                name: Some(name.to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                key_ops,
+                ..Default::default()
+            })
+            .unwrap()
+            .kid
+            .unwrap()
+    }
+
+    #[test]
+    fn reencrypt_after_rotation() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let old = SobjectDescriptor::Kid(create_key(&client, "key", None));
+        let items: Vec<Item> = (0..5)
+            .map(|i| {
+                let req = EncryptRequest {
+                    key: Some(old.clone()),
+                    alg: Algorithm::Aes,
+                    plain: vec![i].into(),
+                    mode: Some(CryptMode::Symmetric(CipherMode::Gcm)),
+                    iv: None,
+                    ad: Some(vec![i].into()),
+                    tag_len: None,
+                };
+                Item {
+                    id: i.to_string(),
+                    payload: Payload::Ciphertext(ciphertext::seal_with(&client, &req).unwrap()),
+                    ad: req.ad,
+                }
+            })
+            .collect();
+        let new = client
+            .rotate_sobject(&SobjectRequest {
+                name: Some("key".to_owned()),
+                ..Default::default()
+            })
+            .unwrap()
+            .kid
+            .unwrap();
+
+        // The first run stops at the second checkpoint, the second one resumes from it
+        let reencryptor = Reencryptor::new(&client)
+            .with_batch_size(1)
+            .with_concurrency(2);
+        let mut stored = HashMap::new();
+        let mut checkpoints = Vec::new();
+        let err = reencryptor.run(
+            items.clone(),
+            Progress::default(),
+            |id, payload| {
+                stored.insert(id.to_owned(), payload);
+                Ok(())
+            },
+            |progress| {
+                checkpoints.push(progress.clone());
+                match checkpoints.len() {
+                    2 => Err(Error::invalid_input("interrupted")),
+                    _ => Ok(()),
+                }
+            },
+        );
+        assert!(err.is_err());
+        let progress = checkpoints.pop().unwrap();
+        assert_eq!(progress.position, 4);
+
+        let progress = reencryptor
+            .run(
+                items.clone(),
+                progress,
+                |id, payload| {
+                    stored.insert(id.to_owned(), payload);
+                    Ok(())
+                },
+                |_| Ok(()),
+            )
+            .unwrap();
+        assert_eq!((progress.position, progress.reencrypted), (5, 5));
+        assert!(progress.failures.is_empty());
+        for (i, item) in items.iter().enumerate() {
+            let ciphertext = match stored[&item.id] {
+                Payload::Ciphertext(ref ciphertext) => ciphertext,
+                _ => unreachable!(),
+            };
+            assert_eq!(ciphertext.kid, new);
+            assert_eq!(
+                ciphertext::open(&client, ciphertext, Some(&[i as u8])).unwrap(),
+                [i as u8]
+            );
+        }
+
+        // Re-encrypted items are skipped
+        let items = stored.into_iter().map(|(id, payload)| Item {
+            id,
+            payload,
+            ad: None,
+        });
+        let progress = reencryptor
+            .run(items, Progress::default(), |_, _| Ok(()), |_| Ok(()))
+            .unwrap();
+        assert_eq!((progress.skipped, progress.reencrypted), (5, 0));
+    }
+
+    #[cfg(feature = "local")]
+    #[test]
+    fn rewrap_after_rotation() {
+        let backend = crate::local::LocalBackend::new();
+        let old = create_key(&backend, "old kek", None);
+        let new = create_key(&backend, "new kek", None);
+        let dek = create_key(&backend, "dek", Some(KeyOperations::EXPORT));
+        let req = WrapKeyRequest {
+            key: Some(SobjectDescriptor::Kid(old)),
+            subject: Some(SobjectDescriptor::Kid(dek)),
+            kid: None,
+            alg: Algorithm::Aes,
+            mode: Some(CryptMode::Symmetric(CipherMode::Gcm)),
+            iv: None,
+            ad: None,
+            tag_len: Some(128),
+        };
+        let wrapped = WrappedKey::new(old, ObjectType::Aes, &req, backend.wrap(&req).unwrap());
+        let items = vec![Item {
+            id: "dek".to_owned(),
+            payload: Payload::WrappedKey(wrapped),
+            ad: None,
+        }];
+
+        // LocalBackend does not rotate keys, `new` stands in for the replacement of `old`
+        let reencryptor = Reencryptor::new(&backend);
+        reencryptor.latest.lock().unwrap().insert(old, new);
+        let mut stored = Vec::new();
+        let progress = reencryptor
+            .run(
+                items.clone(),
+                Progress::default(),
+                |_, payload| {
+                    stored.push(payload);
+                    Ok(())
+                },
+                |_| Ok(()),
+            )
+            .unwrap();
+        assert_eq!((progress.reencrypted, progress.failures.len()), (1, 0));
+        assert_eq!(backend.transient_key_count(), 0);
+        let rewrapped = match stored.pop() {
+            Some(Payload::WrappedKey(wrapped)) => wrapped,
+            _ => unreachable!(),
+        };
+        assert_eq!(rewrapped.kek, new);
+        let unwrapped = backend
+            .unwrap(&UnwrapKeyRequest {
+                key: Some(SobjectDescriptor::Kid(new)),
+                alg: rewrapped.alg,
+                obj_type: rewrapped.obj_type,
+                rsa: None,
+                wrapped_key: rewrapped.wrapped_key,
+                mode: rewrapped.mode,
+                iv: rewrapped.iv,
+                ad: None,
+                tag: rewrapped.tag,
+                name: Some("unwrapped".to_owned()),
+                group_id: None,
+                enabled: None,
+                description: None,
+                custom_metadata: None,
+                key_ops: Some(KeyOperations::EXPORT),
+                transient: None,
+            })
+            .unwrap();
+        let value = |kid| {
+            backend
+                .export_sobject(&SobjectDescriptor::Kid(kid))
+                .unwrap()
+                .value
+        };
+        assert_eq!(value(unwrapped.kid.unwrap()), value(dek));
+
+        // The transient key is also released when wrapping fails
+        let locked = create_key(&backend, "locked kek", Some(KeyOperations::EXPORT));
+        let reencryptor = Reencryptor::new(&backend);
+        reencryptor.latest.lock().unwrap().insert(old, locked);
+        let progress = reencryptor
+            .run(items, Progress::default(), |_, _| Ok(()), |_| Ok(()))
+            .unwrap();
+        assert_eq!((progress.reencrypted, progress.failures.len()), (0, 1));
+        assert_eq!(backend.transient_key_count(), 0);
+    }
+}
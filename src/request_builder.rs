@@ -0,0 +1,485 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Typed constructors for [`EncryptRequest`] and [`DecryptRequest`], e.g.
+//! `EncryptRequest::aes_gcm(key, plain).ad(..).tag_len(128).build()`, and local validation of the parameters of each
+//! cipher mode and RSA padding, so that invalid combinations fail before the request is sent to SDKMS.
+//!
+//! [`EncryptRequest`]: ../api_model/struct.EncryptRequest.html
+//! [`DecryptRequest`]: ../api_model/struct.DecryptRequest.html
+
+use crate::api_model::*;
+use crate::client::Result;
+
+const GCM_TAG_LENGTHS: [usize; 7] = [32, 64, 96, 104, 112, 120, 128];
+const CCM_TAG_LENGTHS: [usize; 7] = [32, 48, 64, 80, 96, 112, 128];
+
+fn is_aead(mode: CipherMode) -> bool {
+    matches!(mode, CipherMode::Gcm | CipherMode::Ccm)
+}
+
+/// Valid tag lengths in bits of AEAD modes.
+fn tag_lengths(mode: CipherMode) -> &'static [usize] {
+    match mode {
+        CipherMode::Gcm => &GCM_TAG_LENGTHS,
+        CipherMode::Ccm => &CCM_TAG_LENGTHS,
+        _ => &[],
+    }
+}
+
+/// Whether `mode` takes an IV of the cipher's block size.
+fn uses_block_iv(mode: CipherMode) -> bool {
+    matches!(
+        mode,
+        CipherMode::Cbc
+            | CipherMode::CbcNoPad
+            | CipherMode::Cfb
+            | CipherMode::Ofb
+            | CipherMode::Ctr
+    )
+}
+
+/// Checks the parameters shared by encryption and decryption, returns the cipher mode and block size of symmetric
+/// algorithms.
+fn check_params(
+    alg: Algorithm,
+    mode: Option<&CryptMode>,
+    iv: Option<&[u8]>,
+    ad: Option<&[u8]>,
+) -> Result<Option<(CipherMode, usize)>> {
+    let block_size = match alg {
+        Algorithm::Aes => 16,
+        Algorithm::Des | Algorithm::Des3 => 8,
+        Algorithm::Rsa => {
+            return match (mode, iv, ad) {
+                (Some(CryptMode::Symmetric(mode)), _, _) => Err(Error::invalid_input(format!(
+                    "cipher mode {:?} is not valid for RSA",
+                    mode
+                ))),
+                (_, Some(_), _) => Err(Error::invalid_input("iv is not valid for RSA".to_owned())),
+                (_, _, Some(_)) => Err(Error::invalid_input("ad is not valid for RSA".to_owned())),
+                _ => Ok(None),
+            }
+        }
+        alg => {
+            return Err(Error::invalid_input(format!(
+                "{:?} can not be used for encryption",
+                alg
+            )))
+        }
+    };
+    let mode = match mode {
+        Some(CryptMode::Symmetric(mode)) => *mode,
+        Some(CryptMode::Rsa(_)) => {
+            return Err(Error::invalid_input(format!(
+                "RSA padding is not valid for {:?}",
+                alg
+            )))
+        }
+        None => {
+            return Err(Error::invalid_input(format!(
+                "mode is required for {:?}",
+                alg
+            )))
+        }
+    };
+    if alg != Algorithm::Aes && !uses_block_iv(mode) && mode != CipherMode::Ecb {
+        return Err(Error::invalid_input(format!(
+            "cipher mode {:?} is only valid for AES",
+            mode
+        )));
+    }
+    if ad.is_some() && !is_aead(mode) {
+        return Err(Error::invalid_input(
+            "ad is only valid with GCM and CCM modes".to_owned(),
+        ));
+    }
+    match (mode, iv.map(<[u8]>::len)) {
+        (CipherMode::Ecb, Some(_)) | (CipherMode::Kw, Some(_)) | (CipherMode::Kwp, Some(_)) => Err(
+            Error::invalid_input(format!("iv is not used with {:?} mode", mode)),
+        ),
+        (mode, Some(len)) if uses_block_iv(mode) && len != block_size => Err(Error::invalid_input(
+            format!("iv must be {} bytes with {:?} mode", block_size, mode),
+        )),
+        (CipherMode::Gcm, Some(0)) => Err(Error::invalid_input("iv must not be empty".to_owned())),
+        (CipherMode::Ccm, Some(len)) if !(7..=13).contains(&len) => Err(Error::invalid_input(
+            "iv must be 7 to 13 bytes with CCM mode".to_owned(),
+        )),
+        _ => Ok(Some((mode, block_size))),
+    }
+}
+
+impl EncryptRequest {
+    /// AES encryption in `mode`, see [`EncryptRequestBuilder`].
+    ///
+    /// [`EncryptRequestBuilder`]: ../struct.EncryptRequestBuilder.html
+    pub fn aes<P: Into<Blob>>(
+        key: SobjectDescriptor,
+        mode: CipherMode,
+        plain: P,
+    ) -> EncryptRequestBuilder {
+        EncryptRequestBuilder(EncryptRequest {
+            key: Some(key),
+            alg: Algorithm::Aes,
+            plain: plain.into(),
+            mode: Some(CryptMode::Symmetric(mode)),
+            iv: None,
+            ad: None,
+            tag_len: None,
+        })
+    }
+
+    /// AES-GCM encryption with a 128-bit tag, see [`EncryptRequestBuilder`].
+    ///
+    /// [`EncryptRequestBuilder`]: ../struct.EncryptRequestBuilder.html
+    pub fn aes_gcm<P: Into<Blob>>(key: SobjectDescriptor, plain: P) -> EncryptRequestBuilder {
+        Self::aes(key, CipherMode::Gcm, plain).tag_len(128)
+    }
+
+    /// AES-CBC encryption with PKCS#7 padding, see [`EncryptRequestBuilder`].
+    ///
+    /// [`EncryptRequestBuilder`]: ../struct.EncryptRequestBuilder.html
+    pub fn aes_cbc<P: Into<Blob>>(key: SobjectDescriptor, plain: P) -> EncryptRequestBuilder {
+        Self::aes(key, CipherMode::Cbc, plain)
+    }
+
+    /// RSA encryption with OAEP padding, using MGF1 with `hash`.
+    pub fn rsa_oaep<P: Into<Blob>>(
+        key: SobjectDescriptor,
+        hash: DigestAlgorithm,
+        plain: P,
+    ) -> EncryptRequestBuilder {
+        Self::rsa(
+            key,
+            RsaEncryptionPadding::Oaep {
+                mgf: Mgf::Mgf1 { hash },
+            },
+            plain,
+        )
+    }
+
+    /// RSA encryption with PKCS#1 v1.5 padding.
+    pub fn rsa_pkcs1_v15<P: Into<Blob>>(key: SobjectDescriptor, plain: P) -> EncryptRequestBuilder {
+        Self::rsa(key, RsaEncryptionPadding::Pkcs1V15 {}, plain)
+    }
+
+    fn rsa<P: Into<Blob>>(
+        key: SobjectDescriptor,
+        padding: RsaEncryptionPadding,
+        plain: P,
+    ) -> EncryptRequestBuilder {
+        EncryptRequestBuilder(EncryptRequest {
+            key: Some(key),
+            alg: Algorithm::Rsa,
+            plain: plain.into(),
+            mode: Some(CryptMode::Rsa(padding)),
+            iv: None,
+            ad: None,
+            tag_len: None,
+        })
+    }
+
+    /// Checks the preconditions of the algorithm and mode that can be verified without the key, e.g. that `ad` and
+    /// `tag_len` are only set for GCM and CCM and that the IV length matches the mode.
+    pub fn validate(&self) -> Result<()> {
+        let symmetric = check_params(
+            self.alg,
+            self.mode.as_ref(),
+            self.iv.as_deref(),
+            self.ad.as_deref(),
+        )?;
+        let (mode, block_size) = match symmetric {
+            Some(symmetric) => symmetric,
+            None if self.tag_len.is_some() => {
+                return Err(Error::invalid_input(
+                    "tag_len is not valid for RSA".to_owned(),
+                ))
+            }
+            None => return Ok(()),
+        };
+        match (mode, self.tag_len) {
+            (mode, Some(_)) if !is_aead(mode) => {
+                return Err(Error::invalid_input(
+                    "tag_len is only valid with GCM and CCM modes".to_owned(),
+                ))
+            }
+            (mode, Some(len)) if !tag_lengths(mode).contains(&len) => {
+                return Err(Error::invalid_input(format!(
+                    "invalid tag_len {} for {:?}",
+                    len, mode
+                )))
+            }
+            _ => {}
+        }
+        let len = self.plain.len();
+        match mode {
+            CipherMode::Ecb | CipherMode::CbcNoPad if len % block_size != 0 => {
+                Err(Error::invalid_input(format!(
+                    "plaintext length must be a multiple of {} bytes with {:?} mode",
+                    block_size, mode
+                )))
+            }
+            CipherMode::Kw if len < 16 || len % 8 != 0 => Err(Error::invalid_input(
+                "plaintext length must be a multiple of 8 bytes and at least 16 bytes with KW mode"
+                    .to_owned(),
+            )),
+            CipherMode::Kwp if len == 0 => Err(Error::invalid_input(
+                "plaintext must not be empty with KWP mode".to_owned(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Builds a validated [`EncryptRequest`], created by constructors such as [`EncryptRequest::aes_gcm()`].
+///
+/// [`EncryptRequest`]: ./api_model/struct.EncryptRequest.html
+/// [`EncryptRequest::aes_gcm()`]: ./api_model/struct.EncryptRequest.html#method.aes_gcm
+#[derive(Debug, Clone)]
+pub struct EncryptRequestBuilder(EncryptRequest);
+
+impl EncryptRequestBuilder {
+    /// Sets the IV. By default SDKMS generates a random IV.
+    pub fn iv<B: Into<Blob>>(mut self, iv: B) -> Self {
+        self.0.iv = Some(iv.into());
+        self
+    }
+
+    /// Sets the authenticated data, for GCM and CCM modes.
+    pub fn ad<B: Into<Blob>>(mut self, ad: B) -> Self {
+        self.0.ad = Some(ad.into());
+        self
+    }
+
+    /// Sets the tag length in bits, for GCM and CCM modes.
+    pub fn tag_len(mut self, tag_len: usize) -> Self {
+        self.0.tag_len = Some(tag_len);
+        self
+    }
+
+    /// Validates the request, see [`EncryptRequest::validate()`].
+    ///
+    /// [`EncryptRequest::validate()`]: ./api_model/struct.EncryptRequest.html#method.validate
+    pub fn build(self) -> Result<EncryptRequest> {
+        self.0.validate()?;
+        Ok(self.0)
+    }
+}
+
+impl DecryptRequest {
+    /// AES decryption in `mode` of the result of an encryption, see [`DecryptRequestBuilder`].
+    ///
+    /// [`DecryptRequestBuilder`]: ../struct.DecryptRequestBuilder.html
+    pub fn aes(
+        key: SobjectDescriptor,
+        mode: CipherMode,
+        resp: EncryptResponse,
+    ) -> DecryptRequestBuilder {
+        Self::from_parts(key, Algorithm::Aes, CryptMode::Symmetric(mode), resp)
+    }
+
+    /// AES-GCM decryption of the result of an encryption, see [`DecryptRequestBuilder`].
+    ///
+    /// [`DecryptRequestBuilder`]: ../struct.DecryptRequestBuilder.html
+    pub fn aes_gcm(key: SobjectDescriptor, resp: EncryptResponse) -> DecryptRequestBuilder {
+        Self::aes(key, CipherMode::Gcm, resp)
+    }
+
+    /// AES-CBC decryption, with PKCS#7 padding, of the result of an encryption.
+    pub fn aes_cbc(key: SobjectDescriptor, resp: EncryptResponse) -> DecryptRequestBuilder {
+        Self::aes(key, CipherMode::Cbc, resp)
+    }
+
+    /// RSA decryption with OAEP padding, using MGF1 with `hash`, of the result of an encryption.
+    pub fn rsa_oaep(
+        key: SobjectDescriptor,
+        hash: DigestAlgorithm,
+        resp: EncryptResponse,
+    ) -> DecryptRequestBuilder {
+        let padding = RsaEncryptionPadding::Oaep {
+            mgf: Mgf::Mgf1 { hash },
+        };
+        Self::from_parts(key, Algorithm::Rsa, CryptMode::Rsa(padding), resp)
+    }
+
+    /// RSA decryption with PKCS#1 v1.5 padding of the result of an encryption.
+    pub fn rsa_pkcs1_v15(key: SobjectDescriptor, resp: EncryptResponse) -> DecryptRequestBuilder {
+        let padding = RsaEncryptionPadding::Pkcs1V15 {};
+        Self::from_parts(key, Algorithm::Rsa, CryptMode::Rsa(padding), resp)
+    }
+
+    /// A request to decrypt the result of encrypting with `req`. The key is identified by the key id in `resp` if
+    /// present.
+    pub fn for_response(req: &EncryptRequest, resp: EncryptResponse) -> Result<Self> {
+        let key = resp
+            .kid
+            .map(SobjectDescriptor::Kid)
+            .or_else(|| req.key.clone());
+        let decrypt = DecryptRequest {
+            key,
+            alg: Some(req.alg),
+            cipher: resp.cipher,
+            mode: req.mode.clone(),
+            iv: resp.iv,
+            ad: req.ad.clone(),
+            tag: resp.tag,
+        };
+        decrypt.validate()?;
+        Ok(decrypt)
+    }
+
+    fn from_parts(
+        key: SobjectDescriptor,
+        alg: Algorithm,
+        mode: CryptMode,
+        resp: EncryptResponse,
+    ) -> DecryptRequestBuilder {
+        DecryptRequestBuilder(DecryptRequest {
+            key: Some(key),
+            alg: Some(alg),
+            cipher: resp.cipher,
+            mode: Some(mode),
+            iv: resp.iv,
+            ad: None,
+            tag: resp.tag,
+        })
+    }
+
+    /// Checks the preconditions of the algorithm and mode that can be verified without the key, e.g. that `tag` is
+    /// set for GCM and CCM and that the IV length matches the mode. Requests without `alg` are not checked.
+    pub fn validate(&self) -> Result<()> {
+        let alg = match self.alg {
+            Some(alg) => alg,
+            None => return Ok(()),
+        };
+        let symmetric = check_params(
+            alg,
+            self.mode.as_ref(),
+            self.iv.as_deref(),
+            self.ad.as_deref(),
+        )?;
+        let (mode, block_size) = match symmetric {
+            Some(symmetric) => symmetric,
+            None if self.tag.is_some() => {
+                return Err(Error::invalid_input("tag is not valid for RSA".to_owned()))
+            }
+            None => return Ok(()),
+        };
+        if self.iv.is_none() && (uses_block_iv(mode) || is_aead(mode)) {
+            return Err(Error::invalid_input(format!(
+                "iv is required with {:?} mode",
+                mode
+            )));
+        }
+        match (is_aead(mode), self.tag.as_deref().map(<[u8]>::len)) {
+            (true, None) => {
+                return Err(Error::invalid_input(format!(
+                    "tag is required with {:?} mode",
+                    mode
+                )))
+            }
+            (true, Some(len)) if !tag_lengths(mode).contains(&(len * 8)) => {
+                return Err(Error::invalid_input(format!(
+                    "invalid tag length of {} bytes for {:?}",
+                    len, mode
+                )))
+            }
+            (false, Some(_)) => {
+                return Err(Error::invalid_input(
+                    "tag is only valid with GCM and CCM modes".to_owned(),
+                ))
+            }
+            _ => {}
+        }
+        let len = self.cipher.len();
+        match mode {
+            CipherMode::Ecb | CipherMode::Cbc | CipherMode::CbcNoPad if len % block_size != 0 => {
+                Err(Error::invalid_input(format!(
+                    "ciphertext length must be a multiple of {} bytes with {:?} mode",
+                    block_size, mode
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Builds a validated [`DecryptRequest`], created by constructors such as [`DecryptRequest::aes_gcm()`].
+///
+/// [`DecryptRequest`]: ./api_model/struct.DecryptRequest.html
+/// [`DecryptRequest::aes_gcm()`]: ./api_model/struct.DecryptRequest.html#method.aes_gcm
+#[derive(Debug, Clone)]
+pub struct DecryptRequestBuilder(DecryptRequest);
+
+impl DecryptRequestBuilder {
+    /// Sets the authenticated data, which must be the same as for encryption.
+    pub fn ad<B: Into<Blob>>(mut self, ad: B) -> Self {
+        self.0.ad = Some(ad.into());
+        self
+    }
+
+    /// Validates the request, see [`DecryptRequest::validate()`].
+    ///
+    /// [`DecryptRequest::validate()`]: ./api_model/struct.DecryptRequest.html#method.validate
+    pub fn build(self) -> Result<DecryptRequest> {
+        self.0.validate()?;
+        Ok(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        let key = SobjectDescriptor::Name("key".to_owned());
+        let req = EncryptRequest::aes_gcm(key.clone(), "data")
+            .ad("header")
+            .build()
+            .unwrap();
+        assert_eq!(req.tag_len, Some(128));
+
+        assert!(EncryptRequest::aes_cbc(key.clone(), "data")
+            .ad("header")
+            .build()
+            .is_err());
+        assert!(EncryptRequest::aes_cbc(key.clone(), "data")
+            .iv(vec![0; 12])
+            .build()
+            .is_err());
+        assert!(
+            EncryptRequest::aes(key.clone(), CipherMode::CbcNoPad, "data")
+                .build()
+                .is_err()
+        );
+        assert!(EncryptRequest::aes_gcm(key.clone(), "data")
+            .tag_len(100)
+            .build()
+            .is_err());
+        assert!(
+            EncryptRequest::rsa_oaep(key.clone(), DigestAlgorithm::Sha256, "data")
+                .tag_len(128)
+                .build()
+                .is_err()
+        );
+
+        let resp = EncryptResponse {
+            kid: None,
+            cipher: vec![0; 4].into(),
+            iv: Some(vec![0; 12].into()),
+            tag: None,
+        };
+        assert!(DecryptRequest::aes_gcm(key.clone(), resp.clone())
+            .build()
+            .is_err());
+        let resp = EncryptResponse {
+            tag: Some(vec![0; 16].into()),
+            ..resp
+        };
+        assert!(DecryptRequest::aes_gcm(key, resp).build().is_ok());
+    }
+}
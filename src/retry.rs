@@ -0,0 +1,307 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use headers::HeaderMap;
+use simple_hyper_client::hyper::header::RETRY_AFTER;
+use simple_hyper_client::StatusCode;
+use uuid::Uuid;
+
+use std::error::Error as StdError;
+use std::io;
+use std::time::Duration;
+
+/// Policy for retrying requests that fail due to transient errors.
+///
+/// Failed requests are retried with exponential backoff, optionally randomized (jitter), up to a maximum number of
+/// attempts. By default only idempotent operations are retried, see [`Operation::is_idempotent()`]. Requests that
+/// failed because a connection to SDKMS could not be established are always safe to retry since they never reached
+/// the server.
+///
+/// ```
+/// use sdkms::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new()
+///     .with_max_attempts(5)
+///     .with_backoff(Duration::from_millis(200), Duration::from_secs(5));
+/// ```
+///
+/// [`Operation::is_idempotent()`]: ./operations/trait.Operation.html#method.is_idempotent
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retryable_statuses: Vec<StatusCode>,
+    retry_network_errors: bool,
+    retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            retryable_statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_network_errors: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy that makes up to 3 attempts, retrying on 429, 502, 503, 504 and on network errors such as
+    /// connection resets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Maximum number of attempts for a single request, including the first one.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+    /// Backoff before the first retry, doubled on every subsequent retry up to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+    /// Whether backoff durations are randomized. Enabled by default to avoid many clients retrying in lockstep.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+    /// HTTP status codes that are considered transient.
+    pub fn with_retryable_statuses<I: IntoIterator<Item = StatusCode>>(
+        mut self,
+        statuses: I,
+    ) -> Self {
+        self.retryable_statuses = statuses.into_iter().collect();
+        self
+    }
+    /// Whether network errors such as connection resets and timeouts are considered transient.
+    pub fn with_network_errors_retryable(mut self, retryable: bool) -> Self {
+        self.retry_network_errors = retryable;
+        self
+    }
+    /// Allows retrying operations that are not idempotent. This is unsafe in general since the failed attempt may
+    /// have taken effect, e.g. a retried `create_sobject()` may create two keys.
+    pub fn with_non_idempotent_retries(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let exp = 2u32.saturating_pow(retry.saturating_sub(1));
+        let mut backoff = self
+            .initial_backoff
+            .checked_mul(exp)
+            .map_or(self.max_backoff, |b| b.min(self.max_backoff));
+        if self.jitter {
+            // "equal jitter": uniformly distributed in [backoff / 2, backoff]
+            let half = backoff / 2;
+            backoff = half + half.mul_f64(random_fraction());
+        }
+        match retry_after {
+            Some(retry_after) => backoff.max(retry_after.min(self.max_backoff)),
+            None => backoff,
+        }
+    }
+}
+
+/// Retry state of a single request.
+pub(crate) struct Retry<'a> {
+    policy: Option<&'a RetryPolicy>,
+    idempotent: bool,
+    attempt: u32,
+}
+
+impl<'a> Retry<'a> {
+    pub(crate) fn new(policy: Option<&'a RetryPolicy>, idempotent: bool) -> Self {
+        Retry {
+            policy,
+            idempotent,
+            attempt: 1,
+        }
+    }
+
+    pub(crate) fn none() -> Self {
+        Self::new(None, false)
+    }
+
+    /// Returns how long to wait before retrying a request that failed with `status`, or `None` if it should not be
+    /// retried.
+    pub(crate) fn after_status(
+        &mut self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<Duration> {
+        let policy = self.policy?;
+        if !(self.idempotent || policy.retry_non_idempotent) {
+            return None;
+        }
+        if !policy.retryable_statuses.contains(&status) {
+            return None;
+        }
+        self.next(retry_after(headers))
+    }
+
+    /// Returns how long to wait before retrying a request that could not be completed, or `None` if it should not be
+    /// retried.
+    pub(crate) fn after_error(&mut self, err: &simple_hyper_client::Error) -> Option<Duration> {
+        let policy = self.policy?;
+        if !policy.retry_network_errors {
+            return None;
+        }
+        let retryable = match *err {
+            // the request never reached the server
+            simple_hyper_client::Error::Hyper(ref e) if e.is_connect() => true,
+            _ if !(self.idempotent || policy.retry_non_idempotent) => false,
+            ref e => is_transient(e),
+        };
+        match retryable {
+            true => self.next(None),
+            false => None,
+        }
+    }
+
+    pub(crate) fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    fn next(&mut self, retry_after: Option<Duration>) -> Option<Duration> {
+        let policy = self.policy?;
+        if self.attempt >= policy.max_attempts {
+            return None;
+        }
+        let delay = policy.backoff(self.attempt, retry_after);
+        self.attempt += 1;
+        Some(delay)
+    }
+}
+
+/// Whether a network error is likely transient.
+pub(crate) fn is_transient(err: &simple_hyper_client::Error) -> bool {
+    match *err {
+        simple_hyper_client::Error::Hyper(ref e)
+            if e.is_connect() || e.is_incomplete_message() || e.is_closed() || e.is_timeout() =>
+        {
+            true
+        }
+        _ => {
+            let mut source = err.source();
+            while let Some(e) = source {
+                if let Some(e) = e.downcast_ref::<io::Error>() {
+                    return matches!(
+                        e.kind(),
+                        io::ErrorKind::ConnectionReset
+                            | io::ErrorKind::ConnectionAborted
+                            | io::ErrorKind::BrokenPipe
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::UnexpectedEof
+                    );
+                }
+                source = e.source();
+            }
+            false
+        }
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(secs))
+}
+
+/// A uniformly distributed number in [0, 1].
+fn random_fraction() -> f64 {
+    let bits = Uuid::new_v4().as_u128() as u64;
+    bits as f64 / u64::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use headers::HeaderValue;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy::new()
+            .with_max_attempts(5)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(500))
+            .with_jitter(false);
+        assert_eq!(policy.backoff(1, None), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2, None), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3, None), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4, None), Duration::from_millis(500));
+        assert_eq!(policy.backoff(40, None), Duration::from_millis(500));
+        assert_eq!(
+            policy.backoff(1, Some(Duration::from_millis(300))),
+            Duration::from_millis(300)
+        );
+
+        let policy = policy.with_jitter(true);
+        for retry in 1..5 {
+            let max =
+                (Duration::from_millis(100) * 2u32.pow(retry - 1)).min(Duration::from_millis(500));
+            let backoff = policy.backoff(retry, None);
+            assert!(backoff >= max / 2 && backoff <= max);
+        }
+    }
+
+    #[test]
+    fn retry_statuses() {
+        let policy = RetryPolicy::new().with_max_attempts(3).with_jitter(false);
+        let mut headers = HeaderMap::new();
+
+        let mut retry = Retry::new(Some(&policy), true);
+        assert!(retry
+            .after_status(StatusCode::BAD_REQUEST, &headers)
+            .is_none());
+        assert!(retry
+            .after_status(StatusCode::SERVICE_UNAVAILABLE, &headers)
+            .is_some());
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(
+            retry.after_status(StatusCode::TOO_MANY_REQUESTS, &headers),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(retry.attempt(), 3);
+        assert!(retry
+            .after_status(StatusCode::TOO_MANY_REQUESTS, &headers)
+            .is_none());
+
+        let mut retry = Retry::new(Some(&policy), false);
+        assert!(retry
+            .after_status(StatusCode::SERVICE_UNAVAILABLE, &headers)
+            .is_none());
+
+        let policy = policy.with_non_idempotent_retries(true);
+        let mut retry = Retry::new(Some(&policy), false);
+        assert!(retry
+            .after_status(StatusCode::SERVICE_UNAVAILABLE, &headers)
+            .is_some());
+
+        let mut retry = Retry::none();
+        assert!(retry
+            .after_status(StatusCode::SERVICE_UNAVAILABLE, &headers)
+            .is_none());
+    }
+}
@@ -0,0 +1,643 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Helpers to use keys across rotations.
+//!
+//! `SdkmsClient::rotate_sobject()` creates a new version of a key, which takes over the name of the key. The versions
+//! of a key are linked to each other through [`Sobject::links`]: `replacement` points to the next version and
+//! `replaced` to the previous one. [`KeyVersions`] follows these links, so that applications encrypt with the latest
+//! version of a key and decrypt with whichever version encrypted the data:
+//!
+//! ```no_run
+//! # use sdkms::{api_model::SobjectDescriptor, rotation::KeyVersions, SdkmsClient};
+//! # fn main() -> Result<(), sdkms::Error> {
+//! # let client = SdkmsClient::builder().build()?;
+//! let key = KeyVersions::new(&client, SobjectDescriptor::Name("data key".to_owned()));
+//! let ciphertext = key.seal(b"secret")?;
+//! // ... the key is rotated ...
+//! let plain = key.open(&ciphertext)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`RotationScheduler`] rotates keys periodically, according to rotation intervals set in their custom metadata or
+//! to [`RotationPolicy`]s matching their group or metadata, e.g. loaded from a policy file:
+//!
+//! ```no_run
+//! # use sdkms::{rotation::*, SdkmsClient};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let client = SdkmsClient::builder().build()?;
+//! let policies: Vec<RotationPolicy> =
+//!     serde_json::from_reader(std::fs::File::open("rotation-policies.json")?)?;
+//! let report = RotationScheduler::new(&client)
+//!     .with_policies(policies)
+//!     .with_dry_run(true)
+//!     .run()?;
+//! println!("{}", serde_json::to_string_pretty(&report)?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Sobject::links`]: ../api_model/struct.Sobject.html#structfield.links
+//! [`KeyVersions`]: ./struct.KeyVersions.html
+//! [`RotationScheduler`]: ./struct.RotationScheduler.html
+//! [`RotationPolicy`]: ./struct.RotationPolicy.html
+
+use crate::api_model::*;
+use crate::ciphertext::{self, cipher_params, Ciphertext};
+use crate::client::{Result, SdkmsClient};
+use crate::provider::CryptoProvider;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// The versions of a rotated key, see the [module documentation](./index.html).
+///
+/// The version chain is fetched on first use and cached; call [`refresh()`](#method.refresh) to pick up rotations.
+/// Decrypting data encrypted with a version that is not in the cache, e.g. one created by a later rotation, fetches
+/// the chain again.
+#[derive(Debug)]
+pub struct KeyVersions<P> {
+    provider: P,
+    key: SobjectDescriptor,
+    versions: RwLock<Option<Vec<Sobject>>>,
+}
+
+impl<P: Clone> Clone for KeyVersions<P> {
+    fn clone(&self) -> Self {
+        KeyVersions {
+            provider: self.provider.clone(),
+            key: self.key.clone(),
+            versions: RwLock::new(self.versions.read().unwrap().clone()),
+        }
+    }
+}
+
+impl<P: CryptoProvider> KeyVersions<P> {
+    /// `key` can be any version of the key. When it is a name, it refers to the latest version.
+    pub fn new(provider: P, key: SobjectDescriptor) -> Self {
+        KeyVersions {
+            provider,
+            key,
+            versions: RwLock::new(None),
+        }
+    }
+
+    /// Fetches all the versions of the key again and caches them.
+    pub fn refresh(&self) -> Result<()> {
+        let versions = self.fetch_versions()?;
+        *self.versions.write().unwrap() = Some(versions);
+        Ok(())
+    }
+
+    /// All the versions of the key, oldest first. Deleted versions end the chain.
+    pub fn versions(&self) -> Result<Vec<Sobject>> {
+        self.with_versions(|versions| versions.to_vec())
+    }
+
+    /// Calls `f` with the cached versions, fetching them first if needed.
+    fn with_versions<T, F: FnOnce(&[Sobject]) -> T>(&self, f: F) -> Result<T> {
+        if let Some(ref versions) = *self.versions.read().unwrap() {
+            return Ok(f(versions));
+        }
+        let versions = self.fetch_versions()?;
+        let result = f(&versions);
+        *self.versions.write().unwrap() = Some(versions);
+        Ok(result)
+    }
+
+    fn is_cached(&self, kids: &[Uuid]) -> Result<bool> {
+        self.with_versions(|versions| {
+            versions
+                .iter()
+                .any(|version| version.kid.map_or(false, |kid| kids.contains(&kid)))
+        })
+    }
+
+    fn fetch_versions(&self) -> Result<Vec<Sobject>> {
+        let start = self.provider.get_sobject(None, &self.key)?;
+        let mut seen: HashSet<Option<Uuid>> = HashSet::new();
+        seen.insert(start.kid);
+
+        let mut older = Vec::new();
+        let mut links = start.links.clone();
+        while let Some(kid) = links.and_then(|links| links.replaced) {
+            match self.follow(kid, &mut seen)? {
+                Some(sobject) => {
+                    links = sobject.links.clone();
+                    older.push(sobject);
+                }
+                None => break,
+            }
+        }
+
+        let mut versions = older;
+        versions.reverse();
+        let mut links = start.links.clone();
+        versions.push(start);
+        while let Some(kid) = links.and_then(|links| links.replacement) {
+            match self.follow(kid, &mut seen)? {
+                Some(sobject) => {
+                    links = sobject.links.clone();
+                    versions.push(sobject);
+                }
+                None => break,
+            }
+        }
+        Ok(versions)
+    }
+
+    /// Fetches the version `kid` of the chain, returns `None` if it was deleted.
+    fn follow(&self, kid: Uuid, seen: &mut HashSet<Option<Uuid>>) -> Result<Option<Sobject>> {
+        if !seen.insert(Some(kid)) {
+            return Err(Error::unexpected_response("the key links form a cycle"));
+        }
+        match self
+            .provider
+            .get_sobject(None, &SobjectDescriptor::Kid(kid))
+        {
+            Ok(sobject) => Ok(Some(sobject)),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The latest active version of the key. Versions which are not active yet, e.g. with an activation date in the
+    /// future, are skipped.
+    pub fn latest(&self) -> Result<Sobject> {
+        let now = Time::now();
+        self.with_versions(|versions| {
+            versions
+                .iter()
+                .rev()
+                .find(|version| version.is_active(now))
+                .cloned()
+        })?
+        .ok_or_else(|| Error::invalid_input("no version of the key is active"))
+    }
+
+    /// Whether `kid` is a version of the key. Versions which are not cached are found by following the replacement
+    /// links from `kid` until they reach a cached version, or else by fetching the chain again.
+    pub fn contains(&self, kid: &Uuid) -> Result<bool> {
+        let mut seen: HashSet<Option<Uuid>> = HashSet::new();
+        let mut walked = Vec::new();
+        let mut next = Some(*kid);
+        while let Some(kid) = next {
+            if self.is_cached(&[kid])? {
+                return Ok(true);
+            }
+            match self.follow(kid, &mut seen)? {
+                Some(sobject) => {
+                    walked.push(kid);
+                    next = sobject.links.and_then(|links| links.replacement);
+                }
+                None => break,
+            }
+        }
+        self.refresh()?;
+        self.is_cached(&walked)
+    }
+
+    fn latest_kid(&self) -> Result<SobjectDescriptor> {
+        let kid = self
+            .latest()?
+            .kid
+            .ok_or_else(|| Error::invalid_input("the key has no key id"))?;
+        Ok(SobjectDescriptor::Kid(kid))
+    }
+
+    fn check_version(&self, kid: &Uuid) -> Result<()> {
+        if !self.contains(kid)? {
+            return Err(Error::invalid_input(format!(
+                "{} is not a version of the key",
+                kid
+            )));
+        }
+        Ok(())
+    }
+
+    /// Encrypts with the latest active version. The key of `req` is ignored, the key id of the version is returned
+    /// in the response.
+    pub fn encrypt(&self, req: &EncryptRequest) -> Result<EncryptResponse> {
+        self.provider.encrypt(&EncryptRequest {
+            key: Some(self.latest_kid()?),
+            ..req.clone()
+        })
+    }
+
+    /// Decrypts with version `kid`, usually the key id returned when encrypting. Fails if `kid` is not a version of
+    /// the key. The key of `req` is ignored.
+    pub fn decrypt(&self, kid: &Uuid, req: &DecryptRequest) -> Result<DecryptResponse> {
+        self.check_version(kid)?;
+        self.provider.decrypt(&DecryptRequest {
+            key: Some(SobjectDescriptor::Kid(*kid)),
+            ..req.clone()
+        })
+    }
+
+    /// Encrypts `plain` with the latest active version, with the default algorithm and mode of [`ciphertext::seal()`].
+    ///
+    /// [`ciphertext::seal()`]: ../ciphertext/fn.seal.html
+    pub fn seal(&self, plain: &[u8]) -> Result<Ciphertext> {
+        let latest = self.latest()?;
+        let (alg, mode) = cipher_params(&latest)?;
+        let kid = latest
+            .kid
+            .ok_or_else(|| Error::invalid_input("the key has no key id"))?;
+        ciphertext::seal_with(
+            &self.provider,
+            &EncryptRequest {
+                key: Some(SobjectDescriptor::Kid(kid)),
+                alg,
+                plain: plain.to_vec().into(),
+                mode,
+                iv: None,
+                ad: None,
+                tag_len: None,
+            },
+        )
+    }
+
+    /// Decrypts `ciphertext` with the version that encrypted it. Fails if that version is not a version of the key.
+    pub fn open(&self, ciphertext: &Ciphertext) -> Result<Vec<u8>> {
+        self.check_version(&ciphertext.kid)?;
+        ciphertext::open(&self.provider, ciphertext, None)
+    }
+}
+
+/// Custom metadata setting the rotation interval of a key, in days. It takes precedence over rotation policies.
+pub const ROTATION_INTERVAL_METADATA: &str = "rotation_interval_days";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Rotation interval of the keys matching a group and/or custom metadata. A policy without group nor metadata matches
+/// all keys.
+#[derive(Debug, Default, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RotationPolicy {
+    #[serde(default)]
+    pub group_id: Option<Uuid>,
+    /// Custom metadata entries the key must have.
+    #[serde(default)]
+    pub custom_metadata: HashMap<String, String>,
+    pub interval_days: u32,
+}
+
+impl RotationPolicy {
+    /// A policy for all the keys of group `group_id`.
+    pub fn for_group(group_id: Uuid, interval_days: u32) -> Self {
+        RotationPolicy {
+            group_id: Some(group_id),
+            interval_days,
+            ..Default::default()
+        }
+    }
+
+    /// A policy for all the keys with custom metadata `key` set to `value`.
+    pub fn for_metadata(key: &str, value: &str, interval_days: u32) -> Self {
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(key.to_owned(), value.to_owned());
+        RotationPolicy {
+            custom_metadata,
+            interval_days,
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, sobject: &Sobject) -> bool {
+        if matches!(self.group_id, Some(group_id) if sobject.group_id != Some(group_id)) {
+            return false;
+        }
+        self.custom_metadata.iter().all(|(key, value)| {
+            matches!(sobject.custom_metadata, Some(ref metadata) if metadata.get(key) == Some(value))
+        })
+    }
+}
+
+/// A key rotated, or due for rotation in a dry run, by [`RotationScheduler`].
+///
+/// [`RotationScheduler`]: ./struct.RotationScheduler.html
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RotationRecord {
+    pub name: String,
+    /// Key id of the rotated version.
+    pub kid: Uuid,
+    pub group_id: Option<Uuid>,
+    /// When the key was due for rotation.
+    pub due_at: Time,
+    /// Key id of the new version, `None` in a dry run or if the rotation failed.
+    pub replacement: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+/// The keys handled by one run of [`RotationScheduler`].
+///
+/// [`RotationScheduler`]: ./struct.RotationScheduler.html
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RotationReport {
+    pub time: Time,
+    pub dry_run: bool,
+    pub rotations: Vec<RotationRecord>,
+}
+
+/// Rotates the keys whose rotation is due, see the [module documentation](./index.html).
+///
+/// The rotation interval of a key is taken from its [`ROTATION_INTERVAL_METADATA`] custom metadata, or else from the
+/// first matching policy; keys without either are not rotated. A key is due for rotation once its interval has
+/// elapsed since its activation date (or creation date), or at its deactivation date if that comes first. Only the
+/// latest version of enabled, named keys is considered, if they are active, pre-active or deactivated by their
+/// deactivation date. Revoked keys are not rotated.
+///
+/// [`ROTATION_INTERVAL_METADATA`]: ./constant.ROTATION_INTERVAL_METADATA.html
+pub struct RotationScheduler<'a> {
+    client: &'a SdkmsClient,
+    policies: Vec<RotationPolicy>,
+    query_params: ListSobjectsParams,
+    dry_run: bool,
+}
+
+impl<'a> RotationScheduler<'a> {
+    pub fn new(client: &'a SdkmsClient) -> Self {
+        RotationScheduler {
+            client,
+            policies: Vec::new(),
+            query_params: ListSobjectsParams::default(),
+            dry_run: false,
+        }
+    }
+
+    pub fn with_policy(mut self, policy: RotationPolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    pub fn with_policies<I: IntoIterator<Item = RotationPolicy>>(mut self, policies: I) -> Self {
+        self.policies.extend(policies);
+        self
+    }
+
+    /// Restricts the scan to the security objects matching `query_params`, e.g. to one group.
+    pub fn with_query_params(mut self, query_params: ListSobjectsParams) -> Self {
+        self.query_params = query_params;
+        self
+    }
+
+    /// In a dry run the keys due for rotation are reported but not rotated.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Rotates the keys due for rotation now.
+    pub fn run(&self) -> Result<RotationReport> {
+        self.run_at(Time::now())
+    }
+
+    /// Rotates the keys due for rotation at `time`. With a time in the future and a dry run, this reports the keys
+    /// which will be rotated by then.
+    ///
+    /// The keys due for rotation are all listed before the first rotation, and keys created after the run started
+    /// are skipped, so that new versions are not rotated again by the same run. Failures to rotate a key, or invalid
+    /// rotation intervals in custom metadata, are recorded in the report and do not stop the run.
+    pub fn run_at(&self, time: Time) -> Result<RotationReport> {
+        let now = Time::now();
+        let mut seen = HashSet::new();
+        let mut rotations = Vec::new();
+        for sobject in self.client.iter_sobjects(self.query_params.clone()) {
+            let sobject = sobject?;
+            let (name, kid) = match (&sobject.name, sobject.kid) {
+                (Some(name), Some(kid)) if is_rotatable(&sobject, now) => (name.clone(), kid),
+                _ => continue,
+            };
+            // Pages shift when security objects are created while listing.
+            if sobject.created_at > now || !seen.insert(kid) {
+                continue;
+            }
+            let mut record = RotationRecord {
+                name,
+                kid,
+                group_id: sobject.group_id,
+                due_at: time,
+                replacement: None,
+                error: None,
+            };
+            match self.due_at(&sobject) {
+                Ok(Some(due_at)) if due_at <= time => record.due_at = due_at,
+                Ok(_) => continue,
+                Err(err) => record.error = Some(err.to_string()),
+            }
+            rotations.push(record);
+        }
+        if !self.dry_run {
+            for record in rotations.iter_mut().filter(|record| record.error.is_none()) {
+                let rotated = self.client.rotate_sobject(&SobjectRequest {
+                    name: Some(record.name.clone()),
+                    ..Default::default()
+                });
+                match rotated {
+                    Ok(rotated) => record.replacement = rotated.kid,
+                    Err(err) => record.error = Some(err.to_string()),
+                }
+            }
+        }
+        Ok(RotationReport {
+            time,
+            dry_run: self.dry_run,
+            rotations,
+        })
+    }
+
+    fn interval_days(&self, sobject: &Sobject) -> Result<Option<u32>> {
+        let metadata = sobject
+            .custom_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(ROTATION_INTERVAL_METADATA));
+        if let Some(days) = metadata {
+            return match days.parse() {
+                Ok(days) => Ok(Some(days)),
+                Err(_) => Err(Error::invalid_input(format!(
+                    "invalid {} custom metadata: {}",
+                    ROTATION_INTERVAL_METADATA, days
+                ))),
+            };
+        }
+        Ok(self
+            .policies
+            .iter()
+            .find(|policy| policy.matches(sobject))
+            .map(|policy| policy.interval_days))
+    }
+
+    fn due_at(&self, sobject: &Sobject) -> Result<Option<Time>> {
+        let interval = match self.interval_days(sobject)? {
+            Some(days) => u64::from(days) * SECONDS_PER_DAY,
+            None => return Ok(None),
+        };
+        let start = sobject.activation_date.unwrap_or(sobject.created_at);
+        let due_at = Time(start.0.saturating_add(interval));
+        Ok(Some(match sobject.deactivation_date {
+            Some(deactivation) if deactivation < due_at => deactivation,
+            _ => due_at,
+        }))
+    }
+}
+
+/// Whether `sobject` is the latest version of a key that can be rotated. Keys deactivated by their deactivation
+/// date, rather than revoked, are included: they are due for rotation at that date and have no usable version until
+/// rotated.
+fn is_rotatable(sobject: &Sobject, now: Time) -> bool {
+    let state = match sobject.effective_state(now) {
+        SobjectState::Active | SobjectState::PreActive => true,
+        SobjectState::Deactivated => sobject.revocation_reason.is_none(),
+        _ => false,
+    };
+    let replaced = matches!(sobject.links, Some(ref links) if links.replacement.is_some());
+    let key = !matches!(
+        sobject.obj_type,
+        ObjectType::Opaque | ObjectType::Certificate
+    );
+    sobject.enabled && state && key && !replaced && !sobject.public_only
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+
+    /// Creates a 256-bit AES key named `name`, with the other fields of `req`.
+    fn create_key(client: &SdkmsClient, name: &str, req: SobjectRequest) -> Sobject {
+        client
+            .create_sobject(&SobjectRequest {
+                name: Some(name.to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                ..req
+            })
+            .unwrap()
+    }
+
+    fn with_metadata(key: &str, value: &str) -> SobjectRequest {
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(key.to_owned(), value.to_owned());
+        SobjectRequest {
+            custom_metadata: Some(custom_metadata),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn versions() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let first = create_key(&client, "key", SobjectRequest::default());
+        let other = create_key(&client, "other", SobjectRequest::default());
+        let key = KeyVersions::new(&client, SobjectDescriptor::Name("key".to_owned()));
+        let old = key.seal(b"old").unwrap();
+        assert_eq!(Some(old.kid), first.kid);
+        let stale = key.clone();
+
+        let second = client
+            .rotate_sobject(&SobjectRequest {
+                name: Some("key".to_owned()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(key.seal(b"cached").unwrap().kid, old.kid);
+        key.refresh().unwrap();
+        let new = key.seal(b"new").unwrap();
+        assert_eq!(Some(new.kid), second.kid);
+        assert_eq!(stale.open(&new).unwrap(), b"new");
+
+        let versions = KeyVersions::new(&client, SobjectDescriptor::Kid(first.kid.unwrap()))
+            .versions()
+            .unwrap();
+        let kids: Vec<_> = versions.iter().map(|version| version.kid).collect();
+        assert_eq!(kids, [first.kid, second.kid]);
+        assert_eq!(key.open(&old).unwrap(), b"old");
+        assert_eq!(key.open(&new).unwrap(), b"new");
+
+        let foreign =
+            ciphertext::seal(&client, &SobjectDescriptor::Kid(other.kid.unwrap()), b"x").unwrap();
+        assert!(key.open(&foreign).is_err());
+    }
+
+    #[test]
+    fn scheduler() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let monthly = with_metadata(ROTATION_INTERVAL_METADATA, "30");
+        create_key(&client, "monthly", monthly.clone());
+        create_key(&client, "weekly", with_metadata("tier", "gold"));
+        create_key(&client, "unmanaged", with_metadata("tier", "silver"));
+        let policies = vec![
+            RotationPolicy::for_group(Uuid::new_v4(), 1),
+            RotationPolicy::for_metadata("tier", "gold", 7),
+        ];
+        let scheduler = RotationScheduler::new(&client).with_policies(policies);
+        let days = |days: u64| Time(Time::now().0 + days * SECONDS_PER_DAY);
+        let names = |report: &RotationReport| {
+            let mut names: Vec<_> = report.rotations.iter().map(|r| r.name.clone()).collect();
+            names.sort();
+            names
+        };
+
+        let dry_run = RotationScheduler::new(&client)
+            .with_policies(scheduler.policies.clone())
+            .with_dry_run(true);
+        let report = dry_run.run_at(days(10)).unwrap();
+        assert_eq!(names(&report), ["weekly"]);
+        assert_eq!(report.rotations[0].replacement, None);
+        let weekly = client
+            .get_sobject(None, &SobjectDescriptor::Name("weekly".to_owned()))
+            .unwrap();
+        assert_eq!(Some(report.rotations[0].kid), weekly.kid);
+
+        let report = scheduler.run_at(days(31)).unwrap();
+        assert_eq!(names(&report), ["monthly", "weekly"]);
+        for record in &report.rotations {
+            assert_eq!(record.error, None);
+            let latest = client
+                .get_sobject(None, &SobjectDescriptor::Name(record.name.clone()))
+                .unwrap();
+            assert_eq!(record.replacement, latest.kid);
+            assert_ne!(Some(record.kid), latest.kid);
+        }
+        assert!(scheduler.run().unwrap().rotations.is_empty());
+
+        // A key past its deactivation date is rotated before its interval elapses
+        let req = SobjectRequest {
+            deactivation_date: Some(Time(Time::now().0 - SECONDS_PER_DAY)),
+            ..monthly
+        };
+        let expired = create_key(&client, "expired", req);
+        let report = scheduler.run().unwrap();
+        assert_eq!(names(&report), ["expired"]);
+        assert_eq!(report.rotations[0].kid, expired.kid.unwrap());
+        assert_eq!(
+            report.rotations[0].due_at,
+            expired.deactivation_date.unwrap()
+        );
+        let latest = client
+            .get_sobject(None, &SobjectDescriptor::Name("expired".to_owned()))
+            .unwrap();
+        assert_eq!(report.rotations[0].replacement, latest.kid);
+        assert!(latest.is_active(Time::now()));
+        assert!(scheduler.run().unwrap().rotations.is_empty());
+
+        // Revoked keys are not rotated
+        let revocation = RevocationReason {
+            code: RevocationReasonCode::CessationOfOperation,
+            message: None,
+            compromise_occurance_date: None,
+        };
+        client
+            .revoke_sobject(&latest.kid.unwrap(), &revocation)
+            .unwrap();
+        let report = dry_run.run_at(days(31)).unwrap();
+        assert_eq!(names(&report), ["monthly", "weekly"]);
+    }
+}
@@ -0,0 +1,442 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::api_model::*;
+use crate::client::{Result, SdkmsClient};
+
+use std::io::{self, Read, Write};
+use std::{fmt, thread};
+use uuid::Uuid;
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+fn io_error(err: Error) -> io::Error {
+    match err {
+        Error::IoError(err) => err,
+        err => io::Error::new(io::ErrorKind::Other, err),
+    }
+}
+
+/// Encrypts everything written to it with multi-part encryption and writes the ciphertext to an inner writer.
+///
+/// Created by [`SdkmsClient::encrypt_writer()`]. Plaintext is buffered until a chunk is full, then sent to SDKMS
+/// with `encrypt_update()`, so memory use is bounded by the chunk size regardless of the amount of data. Calling
+/// [`flush()`] sends the buffered plaintext right away.
+///
+/// [`finish()`] must be called to complete the encryption with `encrypt_final()` and observe its errors. When
+/// dropped without calling `finish()`, the encryption is completed on a best-effort basis and errors are ignored.
+///
+/// Once sending data to SDKMS or writing to the inner writer fails, the ciphertext is incomplete: every later call
+/// fails as well and the encryption is not completed when the writer is dropped.
+///
+/// The IV needed for decryption is available through [`iv()`].
+///
+/// [`SdkmsClient::encrypt_writer()`]: ./struct.SdkmsClient.html#method.encrypt_writer
+/// [`flush()`]: #method.flush
+/// [`finish()`]: #method.finish
+/// [`iv()`]: #method.iv
+pub struct EncryptWriter<'a, W: Write> {
+    client: &'a SdkmsClient,
+    key: Option<SobjectDescriptor>,
+    kid: Option<Uuid>,
+    iv: Option<Blob>,
+    state: Blob,
+    buffer: Vec<u8>,
+    chunk_size: usize,
+    inner: Option<W>,
+    failed: bool,
+}
+
+impl<'a, W: Write> EncryptWriter<'a, W> {
+    fn new(client: &'a SdkmsClient, req: &EncryptInitRequest, inner: W) -> Result<Self> {
+        let init = client.encrypt_init(req)?;
+        Ok(EncryptWriter {
+            client,
+            key: req.key.clone(),
+            kid: init.kid,
+            iv: init.iv,
+            state: init.state,
+            buffer: Vec::new(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            inner: Some(inner),
+            failed: false,
+        })
+    }
+
+    /// Sets the amount of plaintext sent to SDKMS in each request. The default is 64 KiB.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Id of the encryption key, returned for non-transient keys.
+    pub fn kid(&self) -> Option<Uuid> {
+        self.kid
+    }
+
+    /// Initialization vector, returned for symmetric encryption.
+    pub fn iv(&self) -> Option<&Blob> {
+        self.iv.as_ref()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner
+            .as_ref()
+            .expect("inner writer is only taken by finish()")
+    }
+
+    fn check_failed(&self) -> Result<()> {
+        if self.failed {
+            return Err(io::Error::new(io::ErrorKind::Other, "a previous write failed").into());
+        }
+        Ok(())
+    }
+
+    /// Runs `op` unless a previous operation failed, and records whether it fails.
+    fn guard(&mut self, op: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        self.check_failed()?;
+        let result = op(self);
+        self.failed = result.is_err();
+        result
+    }
+
+    fn send_buffer(&mut self) -> Result<()> {
+        while !self.buffer.is_empty() {
+            let len = self.buffer.len().min(self.chunk_size);
+            let resp = self.client.encrypt_update(&EncryptUpdateRequest {
+                key: self.key.clone(),
+                plain: self.buffer[..len].to_vec().into(),
+                state: self.state.clone(),
+            })?;
+            self.buffer.drain(..len);
+            self.state = resp.state;
+            self.write_inner(&resp.cipher)?;
+        }
+        Ok(())
+    }
+
+    fn write_inner(&mut self, data: &[u8]) -> Result<()> {
+        let inner = self
+            .inner
+            .as_mut()
+            .expect("inner writer is only taken by finish()");
+        Ok(inner.write_all(data)?)
+    }
+
+    fn finalize(&mut self) -> Result<()> {
+        self.send_buffer()?;
+        let resp = self.client.encrypt_final(&EncryptFinalRequest {
+            key: self.key.clone(),
+            state: self.state.clone(),
+        })?;
+        self.write_inner(&resp.cipher)?;
+        let inner = self
+            .inner
+            .as_mut()
+            .expect("inner writer is only taken by finish()");
+        Ok(inner.flush()?)
+    }
+
+    /// Sends the remaining plaintext, completes the encryption and returns the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        let result = self.guard(Self::finalize);
+        let inner = self
+            .inner
+            .take()
+            .expect("inner writer is only taken by finish()");
+        result.map(|()| inner)
+    }
+}
+
+impl<'a, W: Write> Write for EncryptWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_failed().map_err(io_error)?;
+        let len = buf
+            .len()
+            .min(self.chunk_size - self.buffer.len().min(self.chunk_size));
+        self.buffer.extend_from_slice(&buf[..len]);
+        if self.buffer.len() >= self.chunk_size {
+            self.guard(Self::send_buffer).map_err(io_error)?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.guard(|writer| {
+            writer.send_buffer()?;
+            let inner = writer
+                .inner
+                .as_mut()
+                .expect("inner writer is only taken by finish()");
+            Ok(inner.flush()?)
+        })
+        .map_err(io_error)
+    }
+}
+
+impl<'a, W: Write> Drop for EncryptWriter<'a, W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() && !self.failed && !thread::panicking() {
+            let _ = self.finalize();
+        }
+    }
+}
+
+impl<'a, W: Write + fmt::Debug> fmt::Debug for EncryptWriter<'a, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EncryptWriter")
+            .field("kid", &self.kid)
+            .field("chunk_size", &self.chunk_size)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// Decrypts the ciphertext read from an inner reader with multi-part decryption.
+///
+/// Created by [`SdkmsClient::decrypt_reader()`]. Ciphertext is read from the inner reader one chunk at a time and
+/// sent to SDKMS with `decrypt_update()`, so memory use is bounded by the chunk size regardless of the amount of
+/// data. `decrypt_final()` is called when the inner reader reaches the end of its data.
+///
+/// Plaintext is returned as soon as SDKMS returns it, which happens before the final request. Callers must not act
+/// on the plaintext until the reader has returned end of file, as only then has the whole ciphertext been processed.
+///
+/// [`SdkmsClient::decrypt_reader()`]: ./struct.SdkmsClient.html#method.decrypt_reader
+pub struct DecryptReader<'a, R: Read> {
+    client: &'a SdkmsClient,
+    key: Option<SobjectDescriptor>,
+    kid: Option<Uuid>,
+    state: Blob,
+    plain: Vec<u8>,
+    pos: usize,
+    chunk_size: usize,
+    inner: R,
+    done: bool,
+}
+
+impl<'a, R: Read> DecryptReader<'a, R> {
+    fn new(client: &'a SdkmsClient, req: &DecryptInitRequest, inner: R) -> Result<Self> {
+        let init = client.decrypt_init(req)?;
+        Ok(DecryptReader {
+            client,
+            key: req.key.clone(),
+            kid: init.kid,
+            state: init.state,
+            plain: Vec::new(),
+            pos: 0,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            inner,
+            done: false,
+        })
+    }
+
+    /// Sets the amount of ciphertext sent to SDKMS in each request. The default is 64 KiB.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Id of the decryption key, returned for non-transient keys.
+    pub fn kid(&self) -> Option<Uuid> {
+        self.kid
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads the next chunk of ciphertext and decrypts it, or completes the decryption at the end of the input.
+    fn fill(&mut self) -> Result<()> {
+        let mut cipher = Vec::with_capacity(self.chunk_size);
+        (&mut self.inner)
+            .take(self.chunk_size as u64)
+            .read_to_end(&mut cipher)?;
+        let plain = if cipher.is_empty() {
+            self.done = true;
+            self.client
+                .decrypt_final(&DecryptFinalRequest {
+                    key: self.key.clone(),
+                    state: self.state.clone(),
+                })?
+                .plain
+        } else {
+            let resp = self.client.decrypt_update(&DecryptUpdateRequest {
+                key: self.key.clone(),
+                cipher: cipher.into(),
+                state: self.state.clone(),
+            })?;
+            self.state = resp.state;
+            resp.plain
+        };
+        self.plain = plain.into();
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<'a, R: Read> Read for DecryptReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill().map_err(io_error)?;
+        }
+        let len = buf.len().min(self.plain.len() - self.pos);
+        buf[..len].copy_from_slice(&self.plain[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+impl<'a, R: Read + fmt::Debug> fmt::Debug for DecryptReader<'a, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DecryptReader")
+            .field("kid", &self.kid)
+            .field("chunk_size", &self.chunk_size)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl SdkmsClient {
+    /// Starts a multi-part encryption with `req` and returns a writer that encrypts into `inner`, see
+    /// [`EncryptWriter`].
+    ///
+    /// [`EncryptWriter`]: ./struct.EncryptWriter.html
+    pub fn encrypt_writer<W: Write>(
+        &self,
+        req: &EncryptInitRequest,
+        inner: W,
+    ) -> Result<EncryptWriter<'_, W>> {
+        EncryptWriter::new(self, req, inner)
+    }
+
+    /// Starts a multi-part decryption with `req` and returns a reader that decrypts from `inner`, see
+    /// [`DecryptReader`].
+    ///
+    /// [`DecryptReader`]: ./struct.DecryptReader.html
+    pub fn decrypt_reader<R: Read>(
+        &self,
+        req: &DecryptInitRequest,
+        inner: R,
+    ) -> Result<DecryptReader<'_, R>> {
+        DecryptReader::new(self, req, inner)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+
+    #[test]
+    fn round_trip() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let key = client
+            .create_sobject(&SobjectRequest {
+                name: Some("stream".to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                ..Default::default()
+            })
+            .unwrap();
+        let key = Some(SobjectDescriptor::Kid(key.kid.unwrap()));
+        let plain: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+
+        let mut writer = client
+            .encrypt_writer(
+                &EncryptInitRequest {
+                    key: key.clone(),
+                    alg: Algorithm::Aes,
+                    mode: Some(CipherMode::Ctr),
+                    iv: None,
+                },
+                Vec::new(),
+            )
+            .unwrap()
+            .with_chunk_size(1000);
+        for part in plain.chunks(333) {
+            writer.write_all(part).unwrap();
+        }
+        let iv = writer.iv().cloned();
+        let cipher = writer.finish().unwrap();
+        assert_eq!(cipher.len(), plain.len());
+        assert_ne!(cipher, plain);
+
+        let mut reader = client
+            .decrypt_reader(
+                &DecryptInitRequest {
+                    key,
+                    alg: Some(Algorithm::Aes),
+                    mode: Some(CipherMode::Ctr),
+                    iv,
+                },
+                &cipher[..],
+            )
+            .unwrap()
+            .with_chunk_size(700);
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plain);
+    }
+
+    #[test]
+    fn failed_update() {
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let kid = client
+            .create_sobject(&SobjectRequest {
+                name: Some("stream".to_owned()),
+                obj_type: Some(ObjectType::Aes),
+                key_size: Some(256),
+                ..Default::default()
+            })
+            .unwrap()
+            .kid
+            .unwrap();
+        let set_enabled = |enabled| {
+            client
+                .update_sobject(
+                    &kid,
+                    &SobjectRequest {
+                        enabled: Some(enabled),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+        };
+
+        let mut writer = client
+            .encrypt_writer(
+                &EncryptInitRequest {
+                    key: Some(SobjectDescriptor::Kid(kid)),
+                    alg: Algorithm::Aes,
+                    mode: Some(CipherMode::Ctr),
+                    iv: None,
+                },
+                Vec::new(),
+            )
+            .unwrap()
+            .with_chunk_size(100);
+        writer.write_all(&[1; 150]).unwrap();
+        set_enabled(false);
+        assert!(writer.flush().is_err());
+        assert_eq!(writer.buffer.len(), 50);
+
+        // The writer stays failed after the key is enabled again
+        set_enabled(true);
+        assert!(writer.write(&[2]).is_err());
+        assert!(writer.flush().is_err());
+        assert_eq!(writer.get_ref().len(), 100);
+        assert!(writer.finish().is_err());
+    }
+}
@@ -0,0 +1,1450 @@
+/* Copyright (c) Fortanix, Inc.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! X.509 certificates and certificate signing requests for keys held in SDKMS.
+//!
+//! [`CsrBuilder`] encodes a PKCS#10 `CertificationRequestInfo` with the public key of a security object and signs it
+//! with the private key, which never leaves SDKMS:
+//!
+//! ```no_run
+//! # use sdkms::{api_model::SobjectDescriptor, x509::*, SdkmsClient};
+//! # fn main() -> Result<(), sdkms::Error> {
+//! # let client = SdkmsClient::builder().build()?;
+//! # let kid = uuid::Uuid::nil();
+//! let subject = DistinguishedName::new()
+//!     .with_common_name("service.example.com")
+//!     .with_organization("Example");
+//! let csr = CsrBuilder::new(&client, SobjectDescriptor::Kid(kid))
+//!     .with_subject(subject)
+//!     .with_subject_alt_names(vec![SubjectAltName::Dns("service.example.com".to_owned())])
+//!     .build_pem()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! RSA keys are signed with PKCS#1 v1.5 padding unless configured otherwise with
+//! [`CsrBuilder::with_signature_algorithm()`], EC keys with ECDSA using the hash matching the curve size.
+//!
+//! [`Certificate`] parses certificates, e.g. the value of `Certificate` security objects with
+//! [`Sobject::certificate()`]. [`SdkmsClient::import_certificate_chain()`] imports a chain of certificates,
+//! [`SdkmsClient::link_certificate()`] links a certificate to its private key and
+//! [`SdkmsClient::list_expiring_certificates()`] finds the certificates to renew.
+//!
+//! [`CsrBuilder`]: ./struct.CsrBuilder.html
+//! [`Certificate`]: ./struct.Certificate.html
+//! [`Sobject::certificate()`]: ../api_model/struct.Sobject.html#method.certificate
+//! [`SdkmsClient::import_certificate_chain()`]: ../struct.SdkmsClient.html#method.import_certificate_chain
+//! [`SdkmsClient::link_certificate()`]: ../struct.SdkmsClient.html#method.link_certificate
+//! [`SdkmsClient::list_expiring_certificates()`]: ../struct.SdkmsClient.html#method.list_expiring_certificates
+//! [`CsrBuilder::with_signature_algorithm()`]: ./struct.CsrBuilder.html#method.with_signature_algorithm
+
+use crate::api_model::*;
+use crate::client::{Result, SdkmsClient};
+use crate::provider::CryptoProvider;
+use crate::public_key::PublicKey;
+
+use der::asn1::{AnyRef, BitStringRef, GeneralizedTime, Ia5StringRef, ObjectIdentifier};
+use der::asn1::{OctetStringRef, PrintableStringRef, UintRef, UtcTime, Utf8StringRef};
+use der::pem::LineEnding;
+use der::{Decode, Encode, Reader, SliceReader, Tag, TagNumber, Tagged};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// PEM label of PKCS#10 certificate signing requests.
+pub const CSR_PEM_LABEL: &str = "CERTIFICATE REQUEST";
+
+const COMMON_NAME: &str = "2.5.4.3";
+const COUNTRY: &str = "2.5.4.6";
+const LOCALITY: &str = "2.5.4.7";
+const STATE: &str = "2.5.4.8";
+const ORGANIZATION: &str = "2.5.4.10";
+const ORGANIZATIONAL_UNIT: &str = "2.5.4.11";
+const EMAIL_ADDRESS: &str = "1.2.840.113549.1.9.1";
+const EXTENSION_REQUEST: &str = "1.2.840.113549.1.9.14";
+
+const SUBJECT_ALT_NAME: &str = "2.5.29.17";
+const KEY_USAGE: &str = "2.5.29.15";
+const BASIC_CONSTRAINTS: &str = "2.5.29.19";
+const EXTENDED_KEY_USAGE: &str = "2.5.29.37";
+
+const RSASSA_PSS: &str = "1.2.840.113549.1.1.10";
+const MGF1: &str = "1.2.840.113549.1.1.8";
+
+fn der_error(err: der::Error) -> Error {
+    Error::encoding(format!("DER encoding failed: {}", err))
+}
+
+/// A name or subject alternative name that is not valid for its ASN.1 string type.
+fn string_error(err: der::Error) -> Error {
+    Error::invalid_input(format!("invalid string value: {}", err))
+}
+
+fn oid(oid: &str) -> ObjectIdentifier {
+    ObjectIdentifier::new_unwrap(oid)
+}
+
+fn encode<T: Encode>(value: &T) -> Result<Vec<u8>> {
+    value.to_der().map_err(der_error)
+}
+
+fn tlv(tag: Tag, contents: &[u8]) -> Result<Vec<u8>> {
+    encode(&AnyRef::new(tag, contents).map_err(der_error)?)
+}
+
+/// Encodes a SEQUENCE of already encoded elements.
+fn sequence(elements: &[&[u8]]) -> Result<Vec<u8>> {
+    tlv(Tag::Sequence, &elements.concat())
+}
+
+/// Encodes a SET OF already encoded elements, sorted as required by DER.
+fn set_of(mut elements: Vec<Vec<u8>>) -> Result<Vec<u8>> {
+    elements.sort();
+    tlv(Tag::Set, &elements.concat())
+}
+
+fn context_specific(number: u8, constructed: bool, contents: &[u8]) -> Result<Vec<u8>> {
+    let tag = Tag::ContextSpecific {
+        constructed,
+        number: TagNumber::new(number),
+    };
+    tlv(tag, contents)
+}
+
+fn algorithm_identifier(algorithm: &str, parameters: Option<&[u8]>) -> Result<Vec<u8>> {
+    sequence(&[&encode(&oid(algorithm))?, parameters.unwrap_or_default()])
+}
+
+/// An X.501 distinguished name. Each attribute is encoded as a separate relative distinguished name, in the order
+/// added.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DistinguishedName {
+    attributes: Vec<(ObjectIdentifier, String)>,
+}
+
+impl DistinguishedName {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attribute of type `attribute_type`, e.g. `2.5.4.5` for the serial number.
+    pub fn with_attribute(mut self, attribute_type: ObjectIdentifier, value: &str) -> Self {
+        self.attributes.push((attribute_type, value.to_owned()));
+        self
+    }
+
+    pub fn with_common_name(self, value: &str) -> Self {
+        self.with_attribute(oid(COMMON_NAME), value)
+    }
+
+    /// Adds the two-letter country code.
+    pub fn with_country(self, value: &str) -> Self {
+        self.with_attribute(oid(COUNTRY), value)
+    }
+
+    pub fn with_locality(self, value: &str) -> Self {
+        self.with_attribute(oid(LOCALITY), value)
+    }
+
+    pub fn with_state(self, value: &str) -> Self {
+        self.with_attribute(oid(STATE), value)
+    }
+
+    pub fn with_organization(self, value: &str) -> Self {
+        self.with_attribute(oid(ORGANIZATION), value)
+    }
+
+    pub fn with_organizational_unit(self, value: &str) -> Self {
+        self.with_attribute(oid(ORGANIZATIONAL_UNIT), value)
+    }
+
+    pub fn with_email_address(self, value: &str) -> Self {
+        self.with_attribute(oid(EMAIL_ADDRESS), value)
+    }
+
+    pub fn attributes(&self) -> &[(ObjectIdentifier, String)] {
+        &self.attributes
+    }
+
+    /// DER encoding of the name as an X.501 `Name`.
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        let mut rdns = Vec::with_capacity(self.attributes.len());
+        for (attribute_type, value) in &self.attributes {
+            // Country codes must be PrintableString and email addresses IA5String (RFC 5280 appendix A.1)
+            let value = if *attribute_type == oid(COUNTRY) {
+                encode(&PrintableStringRef::new(value).map_err(string_error)?)?
+            } else if *attribute_type == oid(EMAIL_ADDRESS) {
+                encode(&Ia5StringRef::new(value).map_err(string_error)?)?
+            } else {
+                encode(&Utf8StringRef::new(value).map_err(string_error)?)?
+            };
+            let attribute = sequence(&[&encode(attribute_type)?, &value])?;
+            rdns.push(set_of(vec![attribute])?);
+        }
+        tlv(Tag::Sequence, &rdns.concat())
+    }
+}
+
+/// An entry of the subject alternative name extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubjectAltName {
+    Dns(String),
+    Email(String),
+    Uri(String),
+    Ip(IpAddr),
+}
+
+impl SubjectAltName {
+    /// DER encoding as a `GeneralName`.
+    fn to_der(&self) -> Result<Vec<u8>> {
+        let ia5 = |number, value: &str| {
+            // IMPLICIT tagging: the content of the IA5String, with the context-specific tag
+            Ia5StringRef::new(value).map_err(string_error)?;
+            context_specific(number, false, value.as_bytes())
+        };
+        match *self {
+            SubjectAltName::Email(ref email) => ia5(1, email),
+            SubjectAltName::Dns(ref name) => ia5(2, name),
+            SubjectAltName::Uri(ref uri) => ia5(6, uri),
+            SubjectAltName::Ip(IpAddr::V4(ip)) => context_specific(7, false, &ip.octets()),
+            SubjectAltName::Ip(IpAddr::V6(ip)) => context_specific(7, false, &ip.octets()),
+        }
+    }
+}
+
+bitflags! {
+    /// Bits of the key usage extension (RFC 5280 section 4.2.1.3).
+    pub struct KeyUsage: u16 {
+        const DIGITAL_SIGNATURE = 1 << 0;
+        const NON_REPUDIATION = 1 << 1;
+        const KEY_ENCIPHERMENT = 1 << 2;
+        const DATA_ENCIPHERMENT = 1 << 3;
+        const KEY_AGREEMENT = 1 << 4;
+        const KEY_CERT_SIGN = 1 << 5;
+        const CRL_SIGN = 1 << 6;
+        const ENCIPHER_ONLY = 1 << 7;
+        const DECIPHER_ONLY = 1 << 8;
+    }
+}
+
+/// A certificate extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    pub extn_id: ObjectIdentifier,
+    pub critical: bool,
+    /// DER encoding of the extension value.
+    pub value: Vec<u8>,
+}
+
+impl Extension {
+    pub fn new(extn_id: ObjectIdentifier, critical: bool, value: Vec<u8>) -> Self {
+        Extension {
+            extn_id,
+            critical,
+            value,
+        }
+    }
+
+    pub fn subject_alt_name(names: &[SubjectAltName]) -> Result<Self> {
+        let names = names
+            .iter()
+            .map(SubjectAltName::to_der)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(
+            oid(SUBJECT_ALT_NAME),
+            false,
+            tlv(Tag::Sequence, &names.concat())?,
+        ))
+    }
+
+    pub fn basic_constraints(ca: bool, path_len: Option<u8>) -> Result<Self> {
+        let ca = if ca { encode(&true)? } else { Vec::new() };
+        let path_len = match path_len {
+            Some(path_len) => encode(&path_len)?,
+            None => Vec::new(),
+        };
+        Ok(Self::new(
+            oid(BASIC_CONSTRAINTS),
+            true,
+            sequence(&[&ca, &path_len])?,
+        ))
+    }
+
+    pub fn key_usage(usage: KeyUsage) -> Result<Self> {
+        // Bit 0 is the most significant bit of the first byte, trailing zero bits are omitted.
+        let bits = usage.bits().reverse_bits();
+        let len = (16 - bits.trailing_zeros() as usize + 7) / 8;
+        let unused_bits = if len == 0 {
+            0
+        } else {
+            (bits.trailing_zeros() % 8) as u8
+        };
+        let bytes = bits.to_be_bytes();
+        let value = BitStringRef::new(unused_bits, &bytes[..len]).map_err(der_error)?;
+        Ok(Self::new(oid(KEY_USAGE), true, encode(&value)?))
+    }
+
+    /// Extended key usage with the given purposes, e.g. `1.3.6.1.5.5.7.3.1` for TLS servers.
+    pub fn extended_key_usage(purposes: &[ObjectIdentifier]) -> Result<Self> {
+        let purposes = purposes.iter().map(encode).collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(
+            oid(EXTENDED_KEY_USAGE),
+            false,
+            tlv(Tag::Sequence, &purposes.concat())?,
+        ))
+    }
+
+    fn to_der(&self) -> Result<Vec<u8>> {
+        let critical = if self.critical {
+            encode(&true)?
+        } else {
+            Vec::new()
+        };
+        let value = encode(&OctetStringRef::new(&self.value).map_err(der_error)?)?;
+        sequence(&[&encode(&self.extn_id)?, &critical, &value])
+    }
+}
+
+/// Encodes a list of extensions as `Extensions`.
+fn extensions_to_der(extensions: &[Extension]) -> Result<Vec<u8>> {
+    let extensions = extensions
+        .iter()
+        .map(Extension::to_der)
+        .collect::<Result<Vec<_>>>()?;
+    tlv(Tag::Sequence, &extensions.concat())
+}
+
+/// Signature algorithms of certificates and certificate signing requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    RsaPkcs1V15(DigestAlgorithm),
+    /// RSASSA-PSS with MGF1 and a salt as long as the hash.
+    RsaPss(DigestAlgorithm),
+    Ecdsa(DigestAlgorithm),
+}
+
+impl SignatureAlgorithm {
+    /// The default signature algorithm for a key: PKCS#1 v1.5 with SHA-256 for RSA keys, and ECDSA with a hash of
+    /// the size of the curve for EC keys.
+    pub fn for_sobject(sobject: &Sobject) -> Result<Self> {
+        match (sobject.obj_type, sobject.elliptic_curve) {
+            (ObjectType::Rsa, _) => Ok(SignatureAlgorithm::RsaPkcs1V15(DigestAlgorithm::Sha256)),
+            (ObjectType::Ec, Some(EllipticCurve::NistP256))
+            | (ObjectType::Ec, Some(EllipticCurve::SecP256K1)) => {
+                Ok(SignatureAlgorithm::Ecdsa(DigestAlgorithm::Sha256))
+            }
+            (ObjectType::Ec, Some(EllipticCurve::NistP384)) => {
+                Ok(SignatureAlgorithm::Ecdsa(DigestAlgorithm::Sha384))
+            }
+            (ObjectType::Ec, Some(EllipticCurve::NistP521)) => {
+                Ok(SignatureAlgorithm::Ecdsa(DigestAlgorithm::Sha512))
+            }
+            _ => Err(Error::invalid_input(
+                "only RSA keys and ECDSA keys can sign certificates",
+            )),
+        }
+    }
+
+    pub fn hash_alg(&self) -> DigestAlgorithm {
+        match *self {
+            SignatureAlgorithm::RsaPkcs1V15(hash)
+            | SignatureAlgorithm::RsaPss(hash)
+            | SignatureAlgorithm::Ecdsa(hash) => hash,
+        }
+    }
+
+    /// The signature mode to use with `SdkmsClient::sign()`.
+    pub fn mode(&self) -> Option<SignatureMode> {
+        match *self {
+            SignatureAlgorithm::RsaPkcs1V15(_) => {
+                Some(SignatureMode::Rsa(RsaSignaturePadding::Pkcs1V15 {}))
+            }
+            SignatureAlgorithm::RsaPss(hash) => {
+                Some(SignatureMode::Rsa(RsaSignaturePadding::Pss {
+                    mgf: Mgf::Mgf1 { hash },
+                }))
+            }
+            SignatureAlgorithm::Ecdsa(_) => None,
+        }
+    }
+
+    fn check_key(&self, obj_type: ObjectType) -> Result<()> {
+        match (self, obj_type) {
+            (SignatureAlgorithm::RsaPkcs1V15(_), ObjectType::Rsa)
+            | (SignatureAlgorithm::RsaPss(_), ObjectType::Rsa)
+            | (SignatureAlgorithm::Ecdsa(_), ObjectType::Ec) => Ok(()),
+            _ => Err(Error::invalid_input(format!(
+                "{:?} can not be used with {:?} keys",
+                self, obj_type
+            ))),
+        }
+    }
+
+    /// DER encoding of the `AlgorithmIdentifier`.
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        let null = encode(&AnyRef::NULL)?;
+        let unsupported =
+            || Error::invalid_input(format!("unsupported signature algorithm {:?}", self));
+        match *self {
+            SignatureAlgorithm::RsaPkcs1V15(hash) => {
+                let algorithm = match hash {
+                    DigestAlgorithm::Sha1 => "1.2.840.113549.1.1.5",
+                    DigestAlgorithm::Sha256 => "1.2.840.113549.1.1.11",
+                    DigestAlgorithm::Sha384 => "1.2.840.113549.1.1.12",
+                    DigestAlgorithm::Sha512 => "1.2.840.113549.1.1.13",
+                    _ => return Err(unsupported()),
+                };
+                algorithm_identifier(algorithm, Some(&null))
+            }
+            SignatureAlgorithm::RsaPss(hash) => {
+                let (algorithm, salt_len): (_, u8) = match hash {
+                    DigestAlgorithm::Sha256 => ("2.16.840.1.101.3.4.2.1", 32),
+                    DigestAlgorithm::Sha384 => ("2.16.840.1.101.3.4.2.2", 48),
+                    DigestAlgorithm::Sha512 => ("2.16.840.1.101.3.4.2.3", 64),
+                    _ => return Err(unsupported()),
+                };
+                // RSASSA-PSS-params (RFC 4055 section 3.1), the trailer field is the default
+                let hash = algorithm_identifier(algorithm, Some(&null))?;
+                let mgf = algorithm_identifier(MGF1, Some(&hash))?;
+                let params = sequence(&[
+                    &context_specific(0, true, &hash)?,
+                    &context_specific(1, true, &mgf)?,
+                    &context_specific(2, true, &encode(&salt_len)?)?,
+                ])?;
+                algorithm_identifier(RSASSA_PSS, Some(&params))
+            }
+            SignatureAlgorithm::Ecdsa(hash) => {
+                let algorithm = match hash {
+                    DigestAlgorithm::Sha256 => "1.2.840.10045.4.3.2",
+                    DigestAlgorithm::Sha384 => "1.2.840.10045.4.3.3",
+                    DigestAlgorithm::Sha512 => "1.2.840.10045.4.3.4",
+                    _ => return Err(unsupported()),
+                };
+                algorithm_identifier(algorithm, None)
+            }
+        }
+    }
+}
+
+/// Fetches the public key of `key` and the signature algorithm to use with it.
+fn signing_key<P: CryptoProvider + ?Sized>(
+    provider: &P,
+    key: &SobjectDescriptor,
+    signature_algorithm: Option<SignatureAlgorithm>,
+) -> Result<(Blob, SignatureAlgorithm)> {
+    let sobject = provider.get_sobject(None, key)?;
+    let signature_algorithm = match signature_algorithm {
+        Some(signature_algorithm) => signature_algorithm,
+        None => SignatureAlgorithm::for_sobject(&sobject)?,
+    };
+    signature_algorithm.check_key(sobject.obj_type)?;
+    let pub_key = sobject
+        .pub_key
+        .ok_or_else(|| Error::invalid_input("sobject has no public key"))?;
+    Ok((pub_key, signature_algorithm))
+}
+
+/// Signs `tbs` with `key` and encodes the signed structure: `SEQUENCE { tbs, signatureAlgorithm, signature }`.
+fn sign_der<P: CryptoProvider + ?Sized>(
+    provider: &P,
+    key: &SobjectDescriptor,
+    signature_algorithm: SignatureAlgorithm,
+    tbs: &[u8],
+) -> Result<Vec<u8>> {
+    let signature = provider
+        .sign(&SignRequest {
+            key: Some(key.clone()),
+            hash_alg: signature_algorithm.hash_alg(),
+            hash: None,
+            data: Some(tbs.to_vec().into()),
+            mode: signature_algorithm.mode(),
+            deterministic_signature: None,
+        })?
+        .signature;
+    let signature = BitStringRef::from_bytes(&signature).map_err(der_error)?;
+    sequence(&[tbs, &signature_algorithm.to_der()?, &encode(&signature)?])
+}
+
+/// Builds PKCS#10 certificate signing requests signed by a key in SDKMS, see the
+/// [module documentation](./index.html).
+#[derive(Debug, Clone)]
+pub struct CsrBuilder<P> {
+    provider: P,
+    key: SobjectDescriptor,
+    subject: DistinguishedName,
+    subject_alt_names: Vec<SubjectAltName>,
+    extensions: Vec<Extension>,
+    signature_algorithm: Option<SignatureAlgorithm>,
+}
+
+impl<P: CryptoProvider> CsrBuilder<P> {
+    /// Creates a builder for a request for `key`, which must be an RSA or EC key with the SIGN operation.
+    pub fn new(provider: P, key: SobjectDescriptor) -> Self {
+        CsrBuilder {
+            provider,
+            key,
+            subject: DistinguishedName::new(),
+            subject_alt_names: Vec::new(),
+            extensions: Vec::new(),
+            signature_algorithm: None,
+        }
+    }
+
+    pub fn with_subject(mut self, subject: DistinguishedName) -> Self {
+        self.subject = subject;
+        self
+    }
+
+    /// Requests a subject alternative name extension with `names`.
+    pub fn with_subject_alt_names(mut self, names: Vec<SubjectAltName>) -> Self {
+        self.subject_alt_names = names;
+        self
+    }
+
+    /// Requests an extension.
+    pub fn with_extension(mut self, extension: Extension) -> Self {
+        self.extensions.push(extension);
+        self
+    }
+
+    /// Sets the signature algorithm, which defaults to [`SignatureAlgorithm::for_sobject()`].
+    ///
+    /// [`SignatureAlgorithm::for_sobject()`]: ./enum.SignatureAlgorithm.html#method.for_sobject
+    pub fn with_signature_algorithm(mut self, signature_algorithm: SignatureAlgorithm) -> Self {
+        self.signature_algorithm = Some(signature_algorithm);
+        self
+    }
+
+    /// Signs the request and returns its DER encoding.
+    pub fn build_der(&self) -> Result<Vec<u8>> {
+        let (pub_key, signature_algorithm) =
+            signing_key(&self.provider, &self.key, self.signature_algorithm)?;
+
+        let mut extensions = Vec::new();
+        if !self.subject_alt_names.is_empty() {
+            extensions.push(Extension::subject_alt_name(&self.subject_alt_names)?);
+        }
+        extensions.extend(self.extensions.iter().cloned());
+        let attributes = if extensions.is_empty() {
+            Vec::new()
+        } else {
+            let extensions = set_of(vec![extensions_to_der(&extensions)?])?;
+            sequence(&[&encode(&oid(EXTENSION_REQUEST))?, &extensions])?
+        };
+
+        // CertificationRequestInfo (RFC 2986 section 4.1), attributes are [0] IMPLICIT SET OF Attribute
+        let info = sequence(&[
+            &encode(&0u8)?,
+            &self.subject.to_der()?,
+            &pub_key,
+            &context_specific(0, true, &attributes)?,
+        ])?;
+        sign_der(&self.provider, &self.key, signature_algorithm, &info)
+    }
+
+    /// Signs the request and returns it PEM encoded, with `\n` line endings.
+    pub fn build_pem(&self) -> Result<String> {
+        der::pem::encode_string(CSR_PEM_LABEL, LineEnding::LF, &self.build_der()?)
+            .map_err(|e| Error::encoding(format!("PEM encoding failed: {}", e)))
+    }
+}
+
+/// Name of the custom metadata attribute of certificates linked to their private key, see
+/// [`SdkmsClient::link_certificate()`]. The value is the key id of the private key.
+///
+/// [`SdkmsClient::link_certificate()`]: ../struct.SdkmsClient.html#method.link_certificate
+pub const PRIVATE_KEY_METADATA: &str = "private_key";
+
+const CERTIFICATE_PEM_LABEL: &str = "CERTIFICATE";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+fn parse_error(err: der::Error) -> Error {
+    Error::invalid_input(format!("invalid certificate: {}", err))
+}
+
+/// Reader over the contents of a DER encoded SEQUENCE.
+fn sequence_reader(der: &[u8]) -> Result<SliceReader<'_>> {
+    let any = AnyRef::from_der(der).map_err(parse_error)?;
+    if any.tag() != Tag::Sequence {
+        return Err(Error::invalid_input(
+            "invalid certificate: expected a SEQUENCE",
+        ));
+    }
+    SliceReader::new(any.value()).map_err(parse_error)
+}
+
+fn parse_time(reader: &mut SliceReader<'_>) -> Result<Time> {
+    let time = match reader.peek_tag().map_err(parse_error)? {
+        Tag::UtcTime => UtcTime::decode(reader)
+            .map_err(parse_error)?
+            .to_unix_duration(),
+        _ => GeneralizedTime::decode(reader)
+            .map_err(parse_error)?
+            .to_unix_duration(),
+    };
+    Ok(Time(time.as_secs()))
+}
+
+fn parse_string(value: AnyRef<'_>) -> String {
+    match value.tag() {
+        Tag::BmpString => {
+            let units: Vec<u16> = value
+                .value()
+                .chunks(2)
+                .map(|unit| u16::from_be_bytes([unit[0], *unit.get(1).unwrap_or(&0)]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(value.value()).into_owned(),
+    }
+}
+
+impl DistinguishedName {
+    /// Parses a DER encoded X.501 `Name`. Multi-valued relative distinguished names are flattened.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let mut name = DistinguishedName::new();
+        let mut rdns = sequence_reader(der)?;
+        while !rdns.is_finished() {
+            let rdn = AnyRef::decode(&mut rdns).map_err(parse_error)?;
+            let mut attributes = SliceReader::new(rdn.value()).map_err(parse_error)?;
+            while !attributes.is_finished() {
+                let attribute = attributes.tlv_bytes().map_err(parse_error)?;
+                let mut attribute = sequence_reader(attribute)?;
+                let attribute_type =
+                    ObjectIdentifier::decode(&mut attribute).map_err(parse_error)?;
+                let value = AnyRef::decode(&mut attribute).map_err(parse_error)?;
+                name.attributes.push((attribute_type, parse_string(value)));
+            }
+        }
+        Ok(name)
+    }
+
+    /// The value of the first common name attribute.
+    pub fn common_name(&self) -> Option<&str> {
+        let common_name = oid(COMMON_NAME);
+        self.attributes
+            .iter()
+            .find(|(attribute_type, _)| *attribute_type == common_name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl SubjectAltName {
+    /// Parses the value of a subject alternative name extension. Unsupported types of names are skipped.
+    fn parse_extension(der: &[u8]) -> Result<Vec<Self>> {
+        let mut names = Vec::new();
+        let mut reader = sequence_reader(der)?;
+        while !reader.is_finished() {
+            let name = AnyRef::decode(&mut reader).map_err(parse_error)?;
+            let number = match name.tag() {
+                Tag::ContextSpecific {
+                    constructed: false,
+                    number,
+                } => number.value(),
+                _ => continue,
+            };
+            let value = name.value();
+            let text = || String::from_utf8_lossy(value).into_owned();
+            names.push(match (number, value.len()) {
+                (1, _) => SubjectAltName::Email(text()),
+                (2, _) => SubjectAltName::Dns(text()),
+                (6, _) => SubjectAltName::Uri(text()),
+                (7, 4) => SubjectAltName::Ip(IpAddr::from(<[u8; 4]>::try_from(value).unwrap())),
+                (7, 16) => SubjectAltName::Ip(IpAddr::from(<[u8; 16]>::try_from(value).unwrap())),
+                _ => continue,
+            });
+        }
+        Ok(names)
+    }
+}
+
+impl Extension {
+    fn parse_extensions(der: &[u8]) -> Result<Vec<Self>> {
+        let mut extensions = Vec::new();
+        let mut reader = sequence_reader(der)?;
+        while !reader.is_finished() {
+            let extension = reader.tlv_bytes().map_err(parse_error)?;
+            let mut extension = sequence_reader(extension)?;
+            let extn_id = ObjectIdentifier::decode(&mut extension).map_err(parse_error)?;
+            let critical = match extension.peek_tag().map_err(parse_error)? {
+                Tag::Boolean => bool::decode(&mut extension).map_err(parse_error)?,
+                _ => false,
+            };
+            let value = OctetStringRef::decode(&mut extension).map_err(parse_error)?;
+            extensions.push(Extension::new(extn_id, critical, value.as_bytes().to_vec()));
+        }
+        Ok(extensions)
+    }
+}
+
+/// Certificates listed by [`SdkmsClient::list_expiring_certificates()`].
+///
+/// [`SdkmsClient::list_expiring_certificates()`]: ../struct.SdkmsClient.html#method.list_expiring_certificates
+#[derive(Debug, Default, Clone)]
+pub struct ExpiringCertificates {
+    /// The certificates expiring within the requested period, sorted by expiration date.
+    pub expiring: Vec<(Sobject, Certificate)>,
+    /// `Certificate` security objects which could not be parsed, with the error.
+    pub invalid: Vec<(Sobject, String)>,
+}
+
+/// A parsed X.509 certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// Big-endian serial number.
+    pub serial_number: Vec<u8>,
+    pub issuer: DistinguishedName,
+    pub subject: DistinguishedName,
+    pub not_before: Time,
+    pub not_after: Time,
+    /// DER encoded `SubjectPublicKeyInfo`.
+    pub public_key: Vec<u8>,
+    pub subject_alt_names: Vec<SubjectAltName>,
+    pub extensions: Vec<Extension>,
+    der: Vec<u8>,
+}
+
+impl Certificate {
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let mut certificate = sequence_reader(der)?;
+        let tbs = certificate.tlv_bytes().map_err(parse_error)?;
+        let mut tbs = sequence_reader(tbs)?;
+        let version = Tag::ContextSpecific {
+            constructed: true,
+            number: TagNumber::N0,
+        };
+        if tbs.peek_tag().map_err(parse_error)? == version {
+            tbs.tlv_bytes().map_err(parse_error)?;
+        }
+        let serial_number = AnyRef::decode(&mut tbs).map_err(parse_error)?;
+        let _signature_algorithm = tbs.tlv_bytes().map_err(parse_error)?;
+        let issuer = DistinguishedName::from_der(tbs.tlv_bytes().map_err(parse_error)?)?;
+        let mut validity = sequence_reader(tbs.tlv_bytes().map_err(parse_error)?)?;
+        let not_before = parse_time(&mut validity)?;
+        let not_after = parse_time(&mut validity)?;
+        let subject = DistinguishedName::from_der(tbs.tlv_bytes().map_err(parse_error)?)?;
+        let public_key = tbs.tlv_bytes().map_err(parse_error)?.to_vec();
+
+        // Skips the unique identifiers, extensions are [3] EXPLICIT
+        let mut extensions = Vec::new();
+        while !tbs.is_finished() {
+            let field = AnyRef::decode(&mut tbs).map_err(parse_error)?;
+            if let Tag::ContextSpecific {
+                constructed: true,
+                number: TagNumber::N3,
+            } = field.tag()
+            {
+                extensions = Extension::parse_extensions(field.value())?;
+            }
+        }
+        let mut subject_alt_names = Vec::new();
+        for extension in &extensions {
+            if extension.extn_id == oid(SUBJECT_ALT_NAME) {
+                subject_alt_names.extend(SubjectAltName::parse_extension(&extension.value)?);
+            }
+        }
+        Ok(Certificate {
+            serial_number: serial_number.value().to_vec(),
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            public_key,
+            subject_alt_names,
+            extensions,
+            der: der.to_vec(),
+        })
+    }
+
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let (label, der) = der::pem::decode_vec(pem.trim().as_bytes())
+            .map_err(|e| Error::invalid_input(format!("invalid PEM: {}", e)))?;
+        if label != CERTIFICATE_PEM_LABEL {
+            return Err(Error::invalid_input(format!(
+                "expected a PEM \"{}\", found \"{}\"",
+                CERTIFICATE_PEM_LABEL, label
+            )));
+        }
+        Self::from_der(&der)
+    }
+
+    /// Parses a certificate chain, either PEM encoded or as concatenated DER certificates. Other PEM blocks, e.g.
+    /// private keys, are ignored.
+    pub fn parse_chain(bytes: &[u8]) -> Result<Vec<Self>> {
+        const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+        const END: &str = "-----END CERTIFICATE-----";
+        let mut chain = Vec::new();
+        match std::str::from_utf8(bytes) {
+            Ok(mut pem) if pem.contains(BEGIN) => {
+                while let Some(start) = pem.find(BEGIN) {
+                    let len = pem[start..].find(END).ok_or_else(|| {
+                        Error::invalid_input("invalid PEM: missing end of certificate")
+                    })?;
+                    let end = start + len + END.len();
+                    chain.push(Self::from_pem(&pem[start..end])?);
+                    pem = &pem[end..];
+                }
+            }
+            _ => {
+                let mut reader = SliceReader::new(bytes).map_err(parse_error)?;
+                while !reader.is_finished() {
+                    chain.push(Self::from_der(reader.tlv_bytes().map_err(parse_error)?)?);
+                }
+            }
+        }
+        if chain.is_empty() {
+            return Err(Error::invalid_input("no certificate found"));
+        }
+        Ok(chain)
+    }
+
+    pub fn as_der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// The certificate PEM encoded, with `\n` line endings.
+    pub fn to_pem(&self) -> Result<String> {
+        der::pem::encode_string(CERTIFICATE_PEM_LABEL, LineEnding::LF, &self.der)
+            .map_err(|e| Error::encoding(format!("PEM encoding failed: {}", e)))
+    }
+
+    pub fn parse_public_key(&self) -> Result<PublicKey> {
+        PublicKey::from_der(&self.public_key)
+    }
+
+    /// Whether the certificate expires before `days` days from now, or has already expired.
+    pub fn expires_within(&self, days: u32) -> bool {
+        self.not_after.0
+            < Time::now()
+                .0
+                .saturating_add(u64::from(days) * SECONDS_PER_DAY)
+    }
+}
+
+impl Sobject {
+    /// Parses the value of a `Certificate` security object.
+    pub fn certificate(&self) -> Result<Certificate> {
+        if self.obj_type != ObjectType::Certificate {
+            return Err(Error::invalid_input("sobject is not a certificate"));
+        }
+        let value = self
+            .value
+            .as_ref()
+            .ok_or_else(|| Error::invalid_input("sobject has no value"))?;
+        Certificate::from_der(value)
+    }
+
+    /// The key id of the private key of a certificate, see [`SdkmsClient::link_certificate()`].
+    ///
+    /// [`SdkmsClient::link_certificate()`]: ../struct.SdkmsClient.html#method.link_certificate
+    pub fn linked_private_key(&self) -> Option<Uuid> {
+        let metadata = self.custom_metadata.as_ref()?;
+        metadata.get(PRIVATE_KEY_METADATA)?.parse().ok()
+    }
+}
+
+impl SobjectRequest {
+    /// A request to import `certificate` as a `Certificate` security object.
+    pub fn from_certificate(certificate: &Certificate) -> Self {
+        SobjectRequest {
+            obj_type: Some(ObjectType::Certificate),
+            value: Some(certificate.as_der().to_vec().into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl SdkmsClient {
+    /// Imports a certificate chain, leaf first, as `Certificate` security objects. The leaf certificate is named
+    /// `name`, and the other certificates `name/1`, `name/2`, etc.
+    pub fn import_certificate_chain(
+        &self,
+        name: &str,
+        chain: &[Certificate],
+    ) -> Result<Vec<Sobject>> {
+        chain
+            .iter()
+            .enumerate()
+            .map(|(i, certificate)| {
+                let name = match i {
+                    0 => name.to_owned(),
+                    i => format!("{}/{}", name, i),
+                };
+                self.import_sobject(&SobjectRequest {
+                    name: Some(name),
+                    ..SobjectRequest::from_certificate(certificate)
+                })
+            })
+            .collect()
+    }
+
+    /// Fetches and parses a `Certificate` security object.
+    pub fn get_certificate(&self, certificate: &SobjectDescriptor) -> Result<Certificate> {
+        let sobject = self.get_sobject(None, certificate)?;
+        self.parse_certificate(sobject)
+    }
+
+    /// Parses the value of a `Certificate` security object, exporting it if it is not included in `sobject`.
+    fn parse_certificate(&self, sobject: Sobject) -> Result<Certificate> {
+        match (&sobject.value, sobject.kid) {
+            (None, Some(kid)) => self
+                .export_sobject(&SobjectDescriptor::Kid(kid))?
+                .certificate(),
+            _ => sobject.certificate(),
+        }
+    }
+
+    /// Links a certificate to its private key by storing the key id of the private key in the custom metadata of
+    /// the certificate, under [`PRIVATE_KEY_METADATA`]. Fails if the public key of `private_key` is not the one of
+    /// the certificate.
+    ///
+    /// [`PRIVATE_KEY_METADATA`]: ./x509/constant.PRIVATE_KEY_METADATA.html
+    pub fn link_certificate(&self, certificate: &Uuid, private_key: &Uuid) -> Result<Sobject> {
+        let sobject = self.get_sobject(None, &SobjectDescriptor::Kid(*certificate))?;
+        let mut custom_metadata = sobject.custom_metadata.clone().unwrap_or_default();
+        let parsed = self.parse_certificate(sobject)?;
+        let key = self.get_sobject(None, &SobjectDescriptor::Kid(*private_key))?;
+        if key.public_key()? != parsed.parse_public_key()? {
+            return Err(Error::invalid_input(
+                "the certificate is not issued for the public key of the private key",
+            ));
+        }
+        custom_metadata.insert(PRIVATE_KEY_METADATA.to_owned(), private_key.to_string());
+        self.update_sobject(
+            certificate,
+            &SobjectRequest {
+                custom_metadata: Some(custom_metadata),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Lists the `Certificate` security objects matching `query_params` that expire within `days` days, including
+    /// expired ones. Security objects whose certificate can not be read or parsed do not fail the listing, they are
+    /// reported in [`ExpiringCertificates::invalid`].
+    ///
+    /// [`ExpiringCertificates::invalid`]: ./x509/struct.ExpiringCertificates.html#structfield.invalid
+    pub fn list_expiring_certificates(
+        &self,
+        days: u32,
+        query_params: ListSobjectsParams,
+    ) -> Result<ExpiringCertificates> {
+        let mut report = ExpiringCertificates::default();
+        for sobject in self.iter_sobjects(query_params) {
+            let sobject = sobject?;
+            if sobject.obj_type != ObjectType::Certificate {
+                continue;
+            }
+            match self.parse_certificate(sobject.clone()) {
+                Ok(certificate) if certificate.expires_within(days) => {
+                    report.expiring.push((sobject, certificate))
+                }
+                Ok(_) => {}
+                Err(err) => report.invalid.push((sobject, err.to_string())),
+            }
+        }
+        report
+            .expiring
+            .sort_by_key(|(_, certificate)| certificate.not_after);
+        Ok(report)
+    }
+}
+
+/// Encodes a certificate validity time: UTCTime until 2049, GeneralizedTime after (RFC 5280 section 4.1.2.5).
+fn encode_time(time: Time) -> Result<Vec<u8>> {
+    let time = Duration::from_secs(time.0);
+    match UtcTime::from_unix_duration(time) {
+        Ok(time) => encode(&time),
+        Err(_) => encode(&GeneralizedTime::from_unix_duration(time).map_err(der_error)?),
+    }
+}
+
+/// The public key of a certificate issued by [`CertificateBuilder`].
+///
+/// [`CertificateBuilder`]: ./struct.CertificateBuilder.html
+#[derive(Debug, Clone)]
+pub enum SubjectKey {
+    /// The public key of an RSA or EC security object.
+    Sobject(SobjectDescriptor),
+    /// A public key held outside of SDKMS.
+    External(PublicKey),
+}
+
+/// Builds X.509 v3 certificates signed by an issuer key in SDKMS.
+///
+/// By default the certificate is self-signed: the subject key is the issuer key and the issuer name is the subject
+/// name. To issue a certificate for another key, set the subject key with [`with_subject_key()`] and the issuer
+/// name, usually with [`with_issuer_certificate()`]:
+///
+/// ```no_run
+/// # use sdkms::{api_model::SobjectDescriptor, x509::*, SdkmsClient};
+/// # fn main() -> Result<(), sdkms::Error> {
+/// # let client = SdkmsClient::builder().build()?;
+/// let ca_key = SobjectDescriptor::Name("ca".to_owned());
+/// let ca = CertificateBuilder::new(&client, ca_key.clone())
+///     .with_subject(DistinguishedName::new().with_common_name("Example CA"))
+///     .with_basic_constraints(true, Some(0))
+///     .with_key_usage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN)
+///     .build()?;
+///
+/// let leaf = CertificateBuilder::new(&client, ca_key)
+///     .with_issuer_certificate(&ca)
+///     .with_subject_key(SubjectKey::Sobject(SobjectDescriptor::Name("service".to_owned())))
+///     .with_subject(DistinguishedName::new().with_common_name("service.example.com"))
+///     .with_subject_alt_names(vec![SubjectAltName::Dns("service.example.com".to_owned())])
+///     .with_key_usage(KeyUsage::DIGITAL_SIGNATURE)
+///     .build_and_import("service certificate")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`with_subject_key()`]: #method.with_subject_key
+/// [`with_issuer_certificate()`]: #method.with_issuer_certificate
+#[derive(Debug, Clone)]
+pub struct CertificateBuilder<P> {
+    provider: P,
+    issuer_key: SobjectDescriptor,
+    issuer: Option<DistinguishedName>,
+    issuer_public_key: Option<Vec<u8>>,
+    subject_key: Option<SubjectKey>,
+    subject: DistinguishedName,
+    serial_number: Option<Vec<u8>>,
+    not_before: Option<Time>,
+    not_after: Option<Time>,
+    basic_constraints: Option<(bool, Option<u8>)>,
+    key_usage: Option<KeyUsage>,
+    subject_alt_names: Vec<SubjectAltName>,
+    extensions: Vec<Extension>,
+    signature_algorithm: Option<SignatureAlgorithm>,
+}
+
+impl<P: CryptoProvider> CertificateBuilder<P> {
+    /// Creates a builder for certificates signed with `issuer_key`, which must be an RSA or EC key with the SIGN
+    /// operation.
+    pub fn new(provider: P, issuer_key: SobjectDescriptor) -> Self {
+        CertificateBuilder {
+            provider,
+            issuer_key,
+            issuer: None,
+            issuer_public_key: None,
+            subject_key: None,
+            subject: DistinguishedName::new(),
+            serial_number: None,
+            not_before: None,
+            not_after: None,
+            basic_constraints: None,
+            key_usage: None,
+            subject_alt_names: Vec::new(),
+            extensions: Vec::new(),
+            signature_algorithm: None,
+        }
+    }
+
+    /// Sets the issuer name, which defaults to the subject name. It must be set when the subject key is not the
+    /// issuer key.
+    pub fn with_issuer(mut self, issuer: DistinguishedName) -> Self {
+        self.issuer = Some(issuer);
+        self
+    }
+
+    /// Sets the issuer name to the subject of `certificate`, which must be the certificate of the issuer key.
+    pub fn with_issuer_certificate(mut self, certificate: &Certificate) -> Self {
+        self.issuer = Some(certificate.subject.clone());
+        self.issuer_public_key = Some(certificate.public_key.clone());
+        self
+    }
+
+    /// Sets the key the certificate is issued for, which defaults to the issuer key. Unless it is the issuer key, the
+    /// issuer name must also be set.
+    pub fn with_subject_key(mut self, subject_key: SubjectKey) -> Self {
+        self.subject_key = Some(subject_key);
+        self
+    }
+
+    pub fn with_subject(mut self, subject: DistinguishedName) -> Self {
+        self.subject = subject;
+        self
+    }
+
+    /// Sets the big-endian serial number, which defaults to a random 127-bit number.
+    pub fn with_serial_number(mut self, serial_number: Vec<u8>) -> Self {
+        self.serial_number = Some(serial_number);
+        self
+    }
+
+    /// Sets the validity period, which defaults to one year from now.
+    pub fn with_validity(mut self, not_before: Time, not_after: Time) -> Self {
+        self.not_before = Some(not_before);
+        self.not_after = Some(not_after);
+        self
+    }
+
+    /// Adds the basic constraints extension, `path_len` is the maximum number of intermediate CAs below this one.
+    pub fn with_basic_constraints(mut self, ca: bool, path_len: Option<u8>) -> Self {
+        self.basic_constraints = Some((ca, path_len));
+        self
+    }
+
+    pub fn with_key_usage(mut self, usage: KeyUsage) -> Self {
+        self.key_usage = Some(usage);
+        self
+    }
+
+    pub fn with_subject_alt_names(mut self, names: Vec<SubjectAltName>) -> Self {
+        self.subject_alt_names = names;
+        self
+    }
+
+    pub fn with_extension(mut self, extension: Extension) -> Self {
+        self.extensions.push(extension);
+        self
+    }
+
+    /// Sets the signature algorithm, which defaults to [`SignatureAlgorithm::for_sobject()`] of the issuer key.
+    ///
+    /// [`SignatureAlgorithm::for_sobject()`]: ./enum.SignatureAlgorithm.html#method.for_sobject
+    pub fn with_signature_algorithm(mut self, signature_algorithm: SignatureAlgorithm) -> Self {
+        self.signature_algorithm = Some(signature_algorithm);
+        self
+    }
+
+    /// Encodes the `TBSCertificate`, signs it with the issuer key and returns the certificate.
+    pub fn build(&self) -> Result<Certificate> {
+        let (issuer_public_key, signature_algorithm) =
+            signing_key(&self.provider, &self.issuer_key, self.signature_algorithm)?;
+        if matches!(self.issuer_public_key, Some(ref public_key) if public_key[..] != issuer_public_key[..])
+        {
+            return Err(Error::invalid_input(
+                "the issuer certificate is not the certificate of the issuer key",
+            ));
+        }
+        let subject_public_key: Vec<u8> = match self.subject_key {
+            None => issuer_public_key.clone().into(),
+            Some(SubjectKey::Sobject(ref key)) => self
+                .provider
+                .get_sobject(None, key)?
+                .pub_key
+                .ok_or_else(|| Error::invalid_input("subject key has no public key"))?
+                .into(),
+            Some(SubjectKey::External(ref key)) => key.to_der()?,
+        };
+        if self.issuer.is_none() && subject_public_key[..] != issuer_public_key[..] {
+            return Err(Error::invalid_input(
+                "the issuer name is required when the subject key is not the issuer key",
+            ));
+        }
+
+        let serial_number = match self.serial_number {
+            Some(ref serial_number) => serial_number.clone(),
+            None => {
+                let mut serial_number = Uuid::new_v4().as_bytes().to_vec();
+                serial_number[0] &= 0x7f;
+                serial_number
+            }
+        };
+        let not_before = self.not_before.unwrap_or_else(Time::now);
+        let not_after = self
+            .not_after
+            .unwrap_or(Time(not_before.0 + 365 * SECONDS_PER_DAY));
+        if not_after < not_before {
+            return Err(Error::invalid_input("not_after is before not_before"));
+        }
+
+        let mut extensions = Vec::new();
+        if let Some((ca, path_len)) = self.basic_constraints {
+            extensions.push(Extension::basic_constraints(ca, path_len)?);
+        }
+        if let Some(usage) = self.key_usage {
+            extensions.push(Extension::key_usage(usage)?);
+        }
+        if !self.subject_alt_names.is_empty() {
+            extensions.push(Extension::subject_alt_name(&self.subject_alt_names)?);
+        }
+        extensions.extend(self.extensions.iter().cloned());
+        let extensions = if extensions.is_empty() {
+            Vec::new()
+        } else {
+            context_specific(3, true, &extensions_to_der(&extensions)?)?
+        };
+
+        // TBSCertificate (RFC 5280 section 4.1), version is [0] EXPLICIT v3
+        let tbs = sequence(&[
+            &context_specific(0, true, &encode(&2u8)?)?,
+            &encode(&UintRef::new(&serial_number).map_err(der_error)?)?,
+            &signature_algorithm.to_der()?,
+            &self.issuer.as_ref().unwrap_or(&self.subject).to_der()?,
+            &sequence(&[&encode_time(not_before)?, &encode_time(not_after)?])?,
+            &self.subject.to_der()?,
+            &subject_public_key,
+            &extensions,
+        ])?;
+        let certificate = sign_der(&self.provider, &self.issuer_key, signature_algorithm, &tbs)?;
+        Certificate::from_der(&certificate)
+    }
+
+    /// Builds the certificate and imports it as a `Certificate` security object named `name`.
+    pub fn build_and_import(&self, name: &str) -> Result<(Certificate, Sobject)> {
+        let certificate = self.build()?;
+        let sobject = self.provider.import_sobject(&SobjectRequest {
+            name: Some(name.to_owned()),
+            ..SobjectRequest::from_certificate(&certificate)
+        })?;
+        Ok((certificate, sobject))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Self-signed P-256 certificate valid from 2026-10-17T06:17:24Z to 2027-10-17T06:17:24Z
+    const CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIB7zCCAZWgAwIBAgICEjQwCgYIKoZIzj0EAwIwOjEZMBcGA1UEAwwQdGVzdC5l
+eGFtcGxlLmNvbTEQMA4GA1UECgwHRXhhbXBsZTELMAkGA1UEBhMCVVMwHhcNMjYx
+MDE3MDYxNzI0WhcNMjcxMDE3MDYxNzI0WjA6MRkwFwYDVQQDDBB0ZXN0LmV4YW1w
+bGUuY29tMRAwDgYDVQQKDAdFeGFtcGxlMQswCQYDVQQGEwJVUzBZMBMGByqGSM49
+AgEGCCqGSM49AwEHA0IABCmHkT4gNjKj0IBhJrhrvWi9/QcfmUeZ8EUD6OTd4No/
+AUv6/mN1GrSOM0rouBeWRLNDxZ+sNuCHOuN0HYfch5ejgYowgYcwHQYDVR0OBBYE
+FDurMpZ8E8nhVIrhN+uT9+5l3OXQMB8GA1UdIwQYMBaAFDurMpZ8E8nhVIrhN+uT
+9+5l3OXQMA8GA1UdEwEB/wQFMAMBAf8wNAYDVR0RBC0wK4IQdGVzdC5leGFtcGxl
+LmNvbYcEfwAAAYERYWRtaW5AZXhhbXBsZS5jb20wCgYIKoZIzj0EAwIDSAAwRQIg
+IXhKyIoh+lA+KbO1swBznCEJ6zWupoq39NPa9s0XmkwCIQCTB3fwU1lw75bu35KC
+Nb3/nGJr6+gLcS8CXRuuV5DdZg==
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn parse_certificate() {
+        let chain = Certificate::parse_chain(CERTIFICATE.repeat(2).as_bytes()).unwrap();
+        assert_eq!(chain.len(), 2);
+        let certificate = &chain[0];
+        assert_eq!(certificate.serial_number, [0x12, 0x34]);
+        let name = DistinguishedName::new()
+            .with_common_name("test.example.com")
+            .with_organization("Example")
+            .with_country("US");
+        assert_eq!(certificate.subject, name);
+        assert_eq!(certificate.issuer, name);
+        assert_eq!(certificate.subject.common_name(), Some("test.example.com"));
+        assert!(matches!(
+            DistinguishedName::new().with_country("é").to_der(),
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(
+            (certificate.not_before, certificate.not_after),
+            (Time(1792217844), Time(1823753844))
+        );
+        assert_eq!(
+            certificate.subject_alt_names,
+            [
+                SubjectAltName::Dns("test.example.com".to_owned()),
+                SubjectAltName::Ip("127.0.0.1".parse().unwrap()),
+                SubjectAltName::Email("admin@example.com".to_owned()),
+            ]
+        );
+        assert!(matches!(
+            certificate.parse_public_key().unwrap(),
+            PublicKey::Ec {
+                curve: EllipticCurve::NistP256,
+                ..
+            }
+        ));
+        assert_eq!(certificate.to_pem().unwrap(), CERTIFICATE);
+        let der = Certificate::parse_chain(certificate.as_der()).unwrap();
+        assert_eq!(der, chain[..1]);
+        assert!(certificate.expires_within(100 * 365));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn certificate_sobjects() {
+        use crate::mock::MockServer;
+
+        let server = MockServer::start().unwrap();
+        let client = server.client().unwrap();
+        let chain = Certificate::parse_chain(CERTIFICATE.as_bytes()).unwrap();
+        let sobjects = client.import_certificate_chain("test", &chain).unwrap();
+        assert_eq!(sobjects[0].obj_type, ObjectType::Certificate);
+        let kid = sobjects[0].kid.unwrap();
+        assert_eq!(
+            client
+                .get_certificate(&SobjectDescriptor::Kid(kid))
+                .unwrap(),
+            chain[0]
+        );
+        let invalid = client
+            .import_sobject(&SobjectRequest {
+                name: Some("invalid".to_owned()),
+                obj_type: Some(ObjectType::Certificate),
+                value: Some(b"not a certificate".to_vec().into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let report = client
+            .list_expiring_certificates(100 * 365, ListSobjectsParams::default())
+            .unwrap();
+        assert_eq!(report.expiring.len(), 1);
+        assert_eq!(report.expiring[0].0.kid, Some(kid));
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].0.kid, invalid.kid);
+    }
+
+    #[cfg(feature = "local")]
+    #[test]
+    fn issue_certificates() {
+        use crate::local::LocalBackend;
+
+        let backend = LocalBackend::new();
+        let create = |name: &str, curve| {
+            let sobject = backend
+                .create_sobject(&SobjectRequest {
+                    name: Some(name.to_owned()),
+                    obj_type: Some(ObjectType::Ec),
+                    elliptic_curve: Some(curve),
+                    ..Default::default()
+                })
+                .unwrap();
+            SobjectDescriptor::Kid(sobject.kid.unwrap())
+        };
+        let ca_key = create("ca", EllipticCurve::NistP384);
+        let leaf_key = create("leaf", EllipticCurve::NistP256);
+
+        let ca_name = DistinguishedName::new().with_common_name("Test CA");
+        let ca = CertificateBuilder::new(&backend, ca_key.clone())
+            .with_subject(ca_name.clone())
+            .with_basic_constraints(true, Some(0))
+            .with_key_usage(KeyUsage::KEY_CERT_SIGN)
+            .build()
+            .unwrap();
+        assert_eq!(ca.issuer, ca_name);
+        assert_eq!(ca.extensions.len(), 2);
+
+        let not_before = Time(1_700_000_000);
+        // After 2049, encoded as GeneralizedTime
+        let not_after = Time(2_600_000_000);
+        let leaf = CertificateBuilder::new(&backend, ca_key.clone())
+            .with_issuer_certificate(&ca)
+            .with_subject_key(SubjectKey::Sobject(leaf_key.clone()))
+            .with_subject(DistinguishedName::new().with_common_name("leaf"))
+            .with_subject_alt_names(vec![SubjectAltName::Dns("leaf.example.com".to_owned())])
+            .with_serial_number(vec![0x01, 0x02])
+            .with_validity(not_before, not_after)
+            .build()
+            .unwrap();
+        assert_eq!(leaf.issuer, ca_name);
+        assert_eq!(leaf.serial_number, [0x01, 0x02]);
+        assert_eq!((leaf.not_before, leaf.not_after), (not_before, not_after));
+        assert_eq!(
+            leaf.public_key,
+            &backend
+                .get_sobject(None, &leaf_key)
+                .unwrap()
+                .pub_key
+                .unwrap()[..]
+        );
+        assert_eq!(
+            leaf.subject_alt_names,
+            [SubjectAltName::Dns("leaf.example.com".to_owned())]
+        );
+
+        // The issuer certificate must match the issuer key
+        let err = CertificateBuilder::new(&backend, leaf_key.clone())
+            .with_issuer_certificate(&ca)
+            .build();
+        assert!(err.is_err());
+
+        // A certificate for another key is not self-signed, it needs an issuer name
+        let err = CertificateBuilder::new(&backend, ca_key.clone())
+            .with_subject_key(SubjectKey::Sobject(leaf_key))
+            .with_subject(DistinguishedName::new().with_common_name("leaf"))
+            .build();
+        assert!(matches!(err, Err(Error::InvalidInput(_))));
+        CertificateBuilder::new(&backend, ca_key.clone())
+            .with_subject_key(SubjectKey::Sobject(ca_key))
+            .with_subject(ca_name)
+            .build()
+            .unwrap();
+    }
+
+    #[cfg(feature = "local")]
+    #[test]
+    fn csr() {
+        use crate::local::LocalBackend;
+
+        let backend = LocalBackend::new();
+        for (obj_type, signature_algorithm) in [
+            (ObjectType::Ec, None),
+            (
+                ObjectType::Rsa,
+                Some(SignatureAlgorithm::RsaPss(DigestAlgorithm::Sha256)),
+            ),
+        ] {
+            let sobject = backend
+                .create_sobject(&SobjectRequest {
+                    name: Some(format!("{:?}", obj_type)),
+                    obj_type: Some(obj_type),
+                    key_size: Some(2048).filter(|_| obj_type == ObjectType::Rsa),
+                    elliptic_curve: Some(EllipticCurve::NistP256)
+                        .filter(|_| obj_type == ObjectType::Ec),
+                    ..Default::default()
+                })
+                .unwrap();
+            let key = SobjectDescriptor::Kid(sobject.kid.unwrap());
+            let mut builder = CsrBuilder::new(&backend, key.clone())
+                .with_subject(
+                    DistinguishedName::new()
+                        .with_common_name("test")
+                        .with_country("US"),
+                )
+                .with_subject_alt_names(vec![SubjectAltName::Dns("example.com".to_owned())])
+                .with_extension(Extension::key_usage(KeyUsage::DIGITAL_SIGNATURE).unwrap());
+            if let Some(signature_algorithm) = signature_algorithm {
+                builder = builder.with_signature_algorithm(signature_algorithm);
+            }
+            let csr = builder.build_der().unwrap();
+            assert!(builder
+                .build_pem()
+                .unwrap()
+                .starts_with("-----BEGIN CERTIFICATE REQUEST-----\n"));
+
+            let csr = AnyRef::from_der(&csr).unwrap();
+            let mut reader = SliceReader::new(csr.value()).unwrap();
+            let info = reader.tlv_bytes().unwrap();
+            let algorithm = reader.tlv_bytes().unwrap();
+            let signature = BitStringRef::decode(&mut reader).unwrap();
+            assert!(reader.is_finished());
+            let signature_algorithm =
+                signature_algorithm.unwrap_or(SignatureAlgorithm::Ecdsa(DigestAlgorithm::Sha256));
+            assert_eq!(algorithm, &signature_algorithm.to_der().unwrap()[..]);
+            let verified = backend
+                .verify(&VerifyRequest {
+                    key: Some(key),
+                    hash_alg: DigestAlgorithm::Sha256,
+                    hash: None,
+                    data: Some(info.to_vec().into()),
+                    mode: signature_algorithm.mode(),
+                    signature: signature.raw_bytes().to_vec().into(),
+                })
+                .unwrap();
+            assert!(verified.result);
+        }
+    }
+
+    #[test]
+    fn key_usage() {
+        let value = |usage| Extension::key_usage(usage).unwrap().value;
+        assert_eq!(value(KeyUsage::DIGITAL_SIGNATURE), [0x03, 0x02, 0x07, 0x80]);
+        assert_eq!(
+            value(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+            [0x03, 0x02, 0x01, 0x06]
+        );
+        assert_eq!(
+            value(KeyUsage::DECIPHER_ONLY),
+            [0x03, 0x03, 0x07, 0x00, 0x80]
+        );
+    }
+}